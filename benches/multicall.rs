@@ -0,0 +1,95 @@
+// Walks a swap across 20 initialized ticks through RpcTicksProvider and MulticallRpcTicksProvider
+// and reports the JSON-RPC round trips and time each took. Needs an archive node in RPC_URL, run
+// with RPC_URL=... cargo bench --bench multicall --features rpc
+use std::time::Instant;
+
+use alloy_primitives::{address, Address};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::BlockId;
+use reth_primitives::U256;
+use uniswap_v3_math::{
+    providers::{MulticallRpcTicksProvider, RpcTicksProvider},
+    storage,
+    utils::u256_to_i256,
+    Math, SwapResult, TicksProvider,
+};
+
+// USDC/WETH 0.05%
+const POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+const FEE: u32 = 500;
+const TICK_SPACING: i32 = 10;
+const BLOCK: u64 = 19_000_000;
+const TICKS_CROSSED: usize = 20;
+
+// Swaps zero for one until TICKS_CROSSED initialized ticks were crossed
+fn walk<P: TicksProvider>(math: &Math<P>) -> SwapResult {
+    let mut steps = math.swap_steps(true, u256_to_i256(U256::MAX >> 1usize));
+    let mut crossed = 0;
+    while crossed < TICKS_CROSSED {
+        let step = steps.next().unwrap().unwrap();
+        if step.step.initialized {
+            crossed += 1;
+        }
+    }
+    steps.finish()
+}
+
+fn pool<P>(slot0: U256, liquidity: u128, provider: P) -> Math<P> {
+    Math::from_slot0(slot0, liquidity, FEE, TICK_SPACING, provider).unwrap()
+}
+
+#[tokio::main]
+async fn main() {
+    let Ok(rpc_url) = std::env::var("RPC_URL") else {
+        eprintln!("RPC_URL is not set, skipping");
+        return;
+    };
+
+    let provider = ProviderBuilder::new().connect_http(rpc_url.parse().unwrap());
+    let block_id = BlockId::number(BLOCK);
+    let slot0 = provider
+        .get_storage_at(POOL, storage::slot0_slot())
+        .block_id(block_id)
+        .await
+        .unwrap();
+    let liquidity = provider
+        .get_storage_at(POOL, storage::liquidity_slot())
+        .block_id(block_id)
+        .await
+        .unwrap()
+        .to::<u128>();
+    let handle = tokio::runtime::Handle::current();
+    let sequential = pool(
+        slot0,
+        liquidity,
+        RpcTicksProvider::new(provider.clone(), POOL, block_id).with_handle(handle.clone()),
+    );
+    let multicall = pool(
+        slot0,
+        liquidity,
+        MulticallRpcTicksProvider::new(provider, POOL, block_id, TICK_SPACING).with_handle(handle),
+    );
+
+    let (sequential, multicall) = tokio::task::spawn_blocking(move || {
+        let start = Instant::now();
+        let expected = walk(&sequential);
+        println!(
+            "eth_getStorageAt: {} round trips in {:?}",
+            sequential.provider.requests(),
+            start.elapsed()
+        );
+
+        let start = Instant::now();
+        let result = walk(&multicall);
+        println!(
+            "multicall:        {} round trips in {:?}",
+            multicall.provider.requests(),
+            start.elapsed()
+        );
+
+        (expected, result)
+    })
+    .await
+    .unwrap();
+    assert_eq!(sequential, multicall);
+}
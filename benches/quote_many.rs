@@ -0,0 +1,69 @@
+// Quotes 1000 in-memory pools sequentially and through batch::quote_many. Run with
+// cargo bench --features rayon
+use criterion::{criterion_group, criterion_main, Criterion};
+use reth_primitives::U256;
+use uniswap_v3_math::{
+    batch::{quote_many, QuoteRequest},
+    providers::MemoryTicksProvider,
+    tick_math::get_sqrt_ratio_at_tick,
+    Math,
+};
+
+const POOLS: usize = 1000;
+
+// Pools with the same positions around different prices, so each quote crosses a few ticks
+fn pools() -> Vec<Math<MemoryTicksProvider>> {
+    let ticks = (-20..20)
+        .flat_map(|i| {
+            [
+                (i * 600, 1_000_000_000_000_000_000),
+                (i * 600 + 6000, -1_000_000_000_000_000_000),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let provider = MemoryTicksProvider::from_initialized_ticks(&ticks, 60).unwrap();
+
+    (0..POOLS)
+        .map(|i| {
+            let tick = (i as i32 % 200 - 100) * 31;
+            Math::builder()
+                .fee(3000)
+                .tick_spacing(60)
+                .liquidity(10_000_000_000_000_000_000)
+                .sqrt_price_x96(get_sqrt_ratio_at_tick(tick).unwrap() + U256::from(1))
+                .provider(provider.clone())
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+fn bench_quote_many(c: &mut Criterion) {
+    let pools = pools();
+    let requests = (0..POOLS)
+        .flat_map(|pool| {
+            [true, false].map(|zero_for_one| QuoteRequest {
+                pool,
+                zero_for_one,
+                amount_in: U256::from(5_000_000_000_000_000_000_u128),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("quote_many");
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            requests
+                .iter()
+                .map(|request| {
+                    pools[request.pool].simulate_swap(request.zero_for_one, request.amount_in)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("rayon", |b| b.iter(|| quote_many(&pools, &requests)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_quote_many);
+criterion_main!(benches);
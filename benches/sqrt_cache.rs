@@ -0,0 +1,84 @@
+// Quotes a swap crossing 30 initialized ticks with and without a SqrtRatioCache, and the sqrt ratio
+// lookups of its steps alone. Run with cargo bench --bench sqrt_cache
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use reth_primitives::U256;
+use uniswap_v3_math::{
+    providers::MemoryTicksProvider,
+    tick_math::{get_sqrt_ratio_at_tick, SqrtRatioCache},
+    utils::u256_to_i256,
+    Math,
+};
+
+const TICKS_CROSSED: usize = 30;
+
+// A position boundary on every usable tick within 3000 of the price, alternating nets so that
+// liquidity stays around 1e21
+fn pool() -> Math<MemoryTicksProvider> {
+    let ticks = (-50..50)
+        .filter(|i| *i != 0)
+        .map(|i: i32| {
+            let liquidity_net = if i % 2 == 0 { 1 } else { -1 } * 1_000_000_000_000_000_000;
+            (i * 60, liquidity_net)
+        })
+        .collect::<Vec<_>>();
+
+    Math::builder()
+        .fee(3000)
+        .tick_spacing(60)
+        .liquidity(1_000_000_000_000_000_000_000)
+        .sqrt_price_x96(get_sqrt_ratio_at_tick(0).unwrap() + U256::from(1))
+        .provider(MemoryTicksProvider::from_initialized_ticks(&ticks, 60).unwrap())
+        .build()
+        .unwrap()
+}
+
+fn bench_sqrt_cache(c: &mut Criterion) {
+    let pool = pool();
+    let cached = pool
+        .clone()
+        .with_sqrt_cache(Arc::new(SqrtRatioCache::new(60).unwrap()));
+    let amount_in = U256::from(100_000_000_000_000_000_000_u128);
+
+    let crossed = pool
+        .swap_steps(true, u256_to_i256(amount_in))
+        .filter(|step| step.as_ref().unwrap().step.initialized)
+        .count();
+    assert!(crossed >= TICKS_CROSSED, "crossed {crossed} ticks");
+    assert_eq!(
+        pool.simulate_swap(true, amount_in).unwrap(),
+        cached.simulate_swap(true, amount_in).unwrap()
+    );
+
+    let mut group = c.benchmark_group("sqrt_cache");
+    group.bench_function("direct", |b| b.iter(|| pool.simulate_swap(true, amount_in)));
+    group.bench_function("cached", |b| {
+        b.iter(|| cached.simulate_swap(true, amount_in))
+    });
+    group.finish();
+
+    let ticks = (-(TICKS_CROSSED as i32)..=0)
+        .map(|i| i * 60)
+        .collect::<Vec<_>>();
+    let cache = SqrtRatioCache::new(60).unwrap();
+    let mut group = c.benchmark_group("sqrt_ratio_at_tick");
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            for tick in &ticks {
+                black_box(get_sqrt_ratio_at_tick(black_box(*tick)).unwrap());
+            }
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for tick in &ticks {
+                black_box(cache.get(black_box(*tick)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_sqrt_cache);
+criterion_main!(benches);
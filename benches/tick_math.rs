@@ -0,0 +1,34 @@
+// Converts 1000 ticks spread over the whole range to sqrt ratios and back. Run with
+// cargo bench --bench tick_math
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use uniswap_v3_math::tick_math::{
+    get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_TICK, MIN_TICK,
+};
+
+fn bench_tick_math(c: &mut Criterion) {
+    let ticks = (MIN_TICK..MAX_TICK)
+        .step_by((MAX_TICK - MIN_TICK) as usize / 1000)
+        .collect::<Vec<_>>();
+    let sqrt_ratios = ticks
+        .iter()
+        .map(|tick| get_sqrt_ratio_at_tick(*tick).unwrap())
+        .collect::<Vec<_>>();
+
+    c.bench_function("get_sqrt_ratio_at_tick", |b| {
+        b.iter(|| {
+            for tick in &ticks {
+                black_box(get_sqrt_ratio_at_tick(black_box(*tick)).unwrap());
+            }
+        })
+    });
+    c.bench_function("get_tick_at_sqrt_ratio", |b| {
+        b.iter(|| {
+            for sqrt_ratio in &sqrt_ratios {
+                black_box(get_tick_at_sqrt_ratio(black_box(*sqrt_ratio)).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_tick_math);
+criterion_main!(benches);
@@ -0,0 +1,232 @@
+use std::{future::Future, ops::RangeInclusive};
+
+use alloy_primitives::I256;
+use reth_primitives::U256;
+
+use crate::{
+    default_sqrt_price_limit,
+    error::UniswapV3MathError,
+    utils::{i256_to_u256, u256_to_i256},
+    CurrentState, Math,
+};
+
+// Async counterpart of TicksProvider for tick data behind an async RPC or database client.
+// Implementations can use async fn.
+pub trait AsyncTicksProvider {
+    fn get_word_at_position(
+        &self,
+        position: i16,
+    ) -> impl Future<Output = Result<U256, UniswapV3MathError>> + Send;
+
+    fn get_liquidity_net_at_tick(
+        &self,
+        tick: i32,
+    ) -> impl Future<Output = Result<i128, UniswapV3MathError>> + Send;
+
+    // Same as TicksProvider::initialized_word_range
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        None
+    }
+}
+
+impl<Provider> Math<Provider>
+where
+    Provider: AsyncTicksProvider,
+{
+    // Same as simulate_swap, awaiting the provider inside the swap loop
+    pub async fn simulate_swap_async(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if amount_in == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let current_state = self
+            .swap_async(
+                zero_for_one,
+                u256_to_i256(amount_in),
+                default_sqrt_price_limit(zero_for_one),
+            )
+            .await?;
+
+        Ok(i256_to_u256(-current_state.amount_calculated))
+    }
+
+    // Same as simulate_swap_exact_output, awaiting the provider inside the swap loop
+    pub async fn simulate_swap_exact_output_async(
+        &self,
+        zero_for_one: bool,
+        amount_out: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if amount_out == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let current_state = self
+            .swap_async(
+                zero_for_one,
+                -u256_to_i256(amount_out),
+                default_sqrt_price_limit(zero_for_one),
+            )
+            .await?;
+
+        // The walk hit the price limit before the full output could be produced, what it did
+        // produce is all the pool has in that direction
+        if current_state.amount_specified_remaining != I256::ZERO {
+            return Err(UniswapV3MathError::InsufficientLiquidity {
+                requested: amount_out,
+                available: amount_out - i256_to_u256(-current_state.amount_specified_remaining),
+            });
+        }
+
+        Ok(i256_to_u256(current_state.amount_calculated))
+    }
+
+    // Drives the same steps as Math::swap, only the provider reads are awaited
+    async fn swap_async(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<CurrentState, UniswapV3MathError> {
+        let mut current_state = self.initial_state(amount_specified);
+
+        while current_state.in_progress(sqrt_price_limit_x96) {
+            if let Some(word_pos) = self.begin_step(&mut current_state, zero_for_one)? {
+                let skipped_to = self.skip_empty_words(
+                    &mut current_state,
+                    word_pos,
+                    zero_for_one,
+                    sqrt_price_limit_x96,
+                    || self.initialized_words(self.provider.initialized_word_range()),
+                )?;
+                match skipped_to {
+                    Some(word_pos) => current_state.load_word(word_pos, U256::ZERO),
+                    None => {
+                        let word = if self.known_empty_word(word_pos) {
+                            U256::ZERO
+                        } else {
+                            self.provider.get_word_at_position(word_pos).await?
+                        };
+                        current_state.load_word(word_pos, word);
+                    }
+                }
+            }
+
+            let step = self.compute_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
+
+            let liquidity_net = match current_state.crossed_tick(&step) {
+                Some(tick) => Some(self.provider.get_liquidity_net_at_tick(tick).await?),
+                None => None,
+            };
+
+            self.finish_step(&mut current_state, step, liquidity_net, zero_for_one)?;
+        }
+
+        Ok(current_state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::executor::block_on;
+    use ruint::uint;
+
+    use super::*;
+    use crate::test::fixture;
+
+    struct AsyncTestProvider {
+        words: HashMap<i16, U256>,
+        liquidity_nets: HashMap<i32, i128>,
+    }
+
+    // Suspends once before completing, like a provider waiting on IO
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    impl AsyncTicksProvider for AsyncTestProvider {
+        async fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+            YieldNow(false).await;
+            Ok(self.words.get(&position).copied().unwrap_or_default())
+        }
+
+        async fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+            YieldNow(false).await;
+            Ok(self.liquidity_nets.get(&tick).copied().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap_async() {
+        let math = fixture();
+        let async_math = Math {
+            fee: math.fee,
+            fee_protocol: math.fee_protocol,
+            liquidity: math.liquidity,
+            sqrt_price_x96: math.sqrt_price_x96,
+            tick: math.tick,
+            tick_spacing: math.tick_spacing,
+            provider: AsyncTestProvider {
+                words: math.provider.words.clone(),
+                liquidity_nets: math.provider.liquidity_nets.clone(),
+            },
+            max_steps: math.max_steps,
+            sqrt_ratio_cache: math.sqrt_ratio_cache.clone(),
+            occupancy: math.occupancy.clone(),
+        };
+
+        //amounts within one range, across several ticks and draining the pool across words
+        let amounts = [
+            U256::ZERO,
+            uint!(100000000000000000_U256),
+            uint!(300000000000000000_U256),
+            uint!(500000000000000000_U256),
+            uint!(1000000000000000000_U256),
+        ];
+
+        for zero_for_one in [true, false] {
+            for amount in amounts {
+                let result = block_on(async_math.simulate_swap_async(zero_for_one, amount));
+                let expected = math.simulate_swap(zero_for_one, amount);
+                assert_eq!(result.unwrap(), expected.unwrap());
+
+                let result =
+                    block_on(async_math.simulate_swap_exact_output_async(zero_for_one, amount));
+                match math.simulate_swap_exact_output(zero_for_one, amount) {
+                    Ok(expected) => assert_eq!(result.unwrap(), expected),
+                    Err(UniswapV3MathError::InsufficientLiquidity {
+                        requested,
+                        available,
+                    }) => assert!(matches!(
+                        result.unwrap_err(),
+                        UniswapV3MathError::InsufficientLiquidity {
+                            requested: async_requested,
+                            available: async_available,
+                        } if async_requested == requested && async_available == available
+                    )),
+                    Err(error) => panic!("unexpected error {error}"),
+                }
+            }
+        }
+    }
+}
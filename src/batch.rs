@@ -0,0 +1,101 @@
+use rayon::prelude::*;
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, Math, TicksProvider};
+
+// One exact input quote for quote_many, pool being an index into its pools
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuoteRequest {
+    pub pool: usize,
+    pub zero_for_one: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_u256"))]
+    pub amount_in: U256,
+}
+
+// Runs simulate_swap for every request on the rayon thread pool, returning the outputs in the
+// order of requests. Math only reads its provider, so a Sync provider is all that's needed to
+// share pools across threads.
+pub fn quote_many<P: TicksProvider + Sync>(
+    pools: &[Math<P>],
+    requests: &[QuoteRequest],
+) -> Vec<Result<U256, UniswapV3MathError>> {
+    requests
+        .par_iter()
+        .map(|request| {
+            pools
+                .get(request.pool)
+                .ok_or(UniswapV3MathError::UnknownPool { pool: request.pool })?
+                .simulate_swap(request.zero_for_one, request.amount_in)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::{
+        providers::{CachedTicksProvider, MemoryTicksProvider},
+        snapshot::PoolSnapshot,
+        test::fixture,
+        SwapResult,
+    };
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<Math<MemoryTicksProvider>>();
+        assert_send_sync::<Math<CachedTicksProvider<MemoryTicksProvider>>>();
+        assert_send_sync::<Math<&PoolSnapshot>>();
+        assert_send_sync::<SwapResult>();
+        assert_send_sync::<UniswapV3MathError>();
+    }
+
+    #[test]
+    fn test_quote_many() {
+        let math = fixture();
+        let pools = [500, 3000, 10000].map(|fee| Math {
+            fee,
+            ..math.with_provider(MemoryTicksProvider::from_words_and_nets(
+                math.provider.words.clone(),
+                math.provider.liquidity_nets.clone(),
+            ))
+        });
+
+        let mut requests = vec![];
+        for pool in 0..pools.len() {
+            for zero_for_one in [true, false] {
+                for amount_in in [
+                    uint!(1000000000000000_U256),
+                    uint!(100000000000000000_U256),
+                    uint!(1000000000000000000_U256),
+                ] {
+                    requests.push(QuoteRequest {
+                        pool,
+                        zero_for_one,
+                        amount_in,
+                    });
+                }
+            }
+        }
+        requests.push(QuoteRequest {
+            pool: pools.len(),
+            ..Default::default()
+        });
+
+        let results = quote_many(&pools, &requests);
+        assert_eq!(results.len(), requests.len());
+        for (request, result) in requests.iter().zip(&results[..requests.len() - 1]) {
+            let expected =
+                pools[request.pool].simulate_swap(request.zero_for_one, request.amount_in);
+            assert_eq!(result.as_ref().unwrap(), &expected.unwrap());
+        }
+        assert!(matches!(
+            results.last().unwrap(),
+            Err(UniswapV3MathError::UnknownPool { pool: 3 })
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    occupancy::OccupancySummary,
+    tick_math::{
+        get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, tick_spacing_for_fee, SqrtRatioCache,
+        MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK,
+    },
+    Math,
+};
+
+// Fees are in hundredths of a bip, the pool requires them below 100%
+pub(crate) const MAX_FEE: u32 = 1_000_000;
+
+// Builds a Math from its parts and checks that they describe a valid pool state. The tick is
+// derived from the price when not set, and a fee that is set has to be a canonical fee tier with
+// its tick spacing unless allow_custom_fee_tier is called.
+#[derive(Debug, Default, Clone)]
+pub struct MathBuilder<Provider> {
+    fee: Option<u32>,
+    allow_custom_fee_tier: bool,
+    fee_protocol: u8,
+    liquidity: u128,
+    sqrt_price_x96: U256,
+    tick: Option<i32>,
+    tick_spacing: i32,
+    provider: Provider,
+    max_steps: usize,
+    sqrt_ratio_cache: Option<Arc<SqrtRatioCache>>,
+    occupancy: Option<Arc<OccupancySummary>>,
+}
+
+impl Math<()> {
+    pub fn builder() -> MathBuilder<()> {
+        MathBuilder::default()
+    }
+}
+
+impl<Provider> MathBuilder<Provider> {
+    pub fn fee(mut self, fee: u32) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    // Accepts any fee below 100% with any tick spacing, for forks and custom pools whose pairs
+    // aren't in tick_math::FEE_TIERS
+    pub fn allow_custom_fee_tier(mut self) -> Self {
+        self.allow_custom_fee_tier = true;
+        self
+    }
+
+    pub fn fee_protocol(mut self, fee_protocol: u8) -> Self {
+        self.fee_protocol = fee_protocol;
+        self
+    }
+
+    pub fn liquidity(mut self, liquidity: u128) -> Self {
+        self.liquidity = liquidity;
+        self
+    }
+
+    pub fn sqrt_price_x96(mut self, sqrt_price_x96: U256) -> Self {
+        self.sqrt_price_x96 = sqrt_price_x96;
+        self
+    }
+
+    pub fn tick(mut self, tick: i32) -> Self {
+        self.tick = Some(tick);
+        self
+    }
+
+    pub fn tick_spacing(mut self, tick_spacing: i32) -> Self {
+        self.tick_spacing = tick_spacing;
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn sqrt_ratio_cache(mut self, cache: Arc<SqrtRatioCache>) -> Self {
+        self.sqrt_ratio_cache = Some(cache);
+        self
+    }
+
+    pub fn occupancy(mut self, summary: Arc<OccupancySummary>) -> Self {
+        self.occupancy = Some(summary);
+        self
+    }
+
+    pub fn provider<P>(self, provider: P) -> MathBuilder<P> {
+        MathBuilder {
+            fee: self.fee,
+            allow_custom_fee_tier: self.allow_custom_fee_tier,
+            fee_protocol: self.fee_protocol,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            tick_spacing: self.tick_spacing,
+            provider,
+            max_steps: self.max_steps,
+            sqrt_ratio_cache: self.sqrt_ratio_cache,
+            occupancy: self.occupancy,
+        }
+    }
+
+    pub fn build(self) -> Result<Math<Provider>, UniswapV3MathError> {
+        if self.tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing {
+                tick_spacing: self.tick_spacing,
+            });
+        }
+        let fee = self.fee.unwrap_or_default();
+        if fee >= MAX_FEE {
+            return Err(UniswapV3MathError::InvalidFee { fee });
+        }
+        if self.fee.is_some()
+            && !self.allow_custom_fee_tier
+            && tick_spacing_for_fee(fee) != Some(self.tick_spacing)
+        {
+            return Err(UniswapV3MathError::FeeTickSpacingMismatch {
+                fee,
+                tick_spacing: self.tick_spacing,
+            });
+        }
+        if self.sqrt_price_x96 < MIN_SQRT_RATIO || self.sqrt_price_x96 >= MAX_SQRT_RATIO {
+            return Err(UniswapV3MathError::SqrtPriceOutOfRange {
+                sqrt_price_x96: self.sqrt_price_x96,
+            });
+        }
+
+        let price_tick = get_tick_at_sqrt_ratio(self.sqrt_price_x96)?;
+        let tick = match self.tick {
+            Some(tick) if !(MIN_TICK..=MAX_TICK).contains(&tick) => {
+                return Err(UniswapV3MathError::TickOutOfRange { tick });
+            }
+            // A swap moving left that stops exactly on a tick leaves the pool one tick below it
+            Some(tick)
+                if tick == price_tick
+                    || (tick == price_tick - 1
+                        && get_sqrt_ratio_at_tick(price_tick)? == self.sqrt_price_x96) =>
+            {
+                tick
+            }
+            Some(tick) => {
+                return Err(UniswapV3MathError::TickPriceMismatch { tick, price_tick });
+            }
+            None => price_tick,
+        };
+
+        Ok(Math {
+            fee,
+            fee_protocol: self.fee_protocol,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick,
+            tick_spacing: self.tick_spacing,
+            provider: self.provider,
+            max_steps: self.max_steps,
+            sqrt_ratio_cache: self.sqrt_ratio_cache,
+            occupancy: self.occupancy,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::test::fixture;
+
+    #[test]
+    fn test_math_builder() {
+        let expected = fixture();
+        let builder = Math::builder()
+            .fee(3000)
+            .liquidity(expected.liquidity)
+            .sqrt_price_x96(expected.sqrt_price_x96)
+            .tick_spacing(60)
+            .provider(expected.provider.clone());
+
+        //the tick is derived from the price unless given
+        let math = builder.clone().build().unwrap();
+        assert_eq!(math.tick, 7000);
+        let amount_in = uint!(300000000000000000_U256);
+        assert_eq!(
+            math.simulate_swap(true, amount_in).unwrap(),
+            expected.simulate_swap(true, amount_in).unwrap()
+        );
+        assert_eq!(builder.clone().tick(7000).build().unwrap().tick, 7000);
+
+        //one below a price exactly on a tick is where a swap moving left leaves the pool
+        let on_tick = builder
+            .clone()
+            .sqrt_price_x96(get_sqrt_ratio_at_tick(6600).unwrap());
+        assert_eq!(on_tick.clone().build().unwrap().tick, 6600);
+        assert_eq!(on_tick.clone().tick(6599).build().unwrap().tick, 6599);
+        assert!(matches!(
+            on_tick.tick(6601).build(),
+            Err(UniswapV3MathError::TickPriceMismatch {
+                tick: 6601,
+                price_tick: 6600
+            })
+        ));
+        assert!(matches!(
+            builder.clone().tick(6999).build(),
+            Err(UniswapV3MathError::TickPriceMismatch {
+                tick: 6999,
+                price_tick: 7000
+            })
+        ));
+
+        assert!(matches!(
+            builder.clone().tick_spacing(0).build(),
+            Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing: 0 })
+        ));
+        assert!(matches!(
+            builder.clone().fee(1_000_000).build(),
+            Err(UniswapV3MathError::InvalidFee { fee: 1_000_000 })
+        ));
+        assert!(builder
+            .clone()
+            .fee(999_999)
+            .allow_custom_fee_tier()
+            .build()
+            .is_ok());
+        for sqrt_price_x96 in [U256::ZERO, MIN_SQRT_RATIO - U256::from(1), MAX_SQRT_RATIO] {
+            let result = builder.clone().sqrt_price_x96(sqrt_price_x96).build();
+            assert!(matches!(
+                result,
+                Err(UniswapV3MathError::SqrtPriceOutOfRange { .. })
+            ));
+        }
+        assert!(builder
+            .clone()
+            .sqrt_price_x96(MIN_SQRT_RATIO)
+            .tick(MIN_TICK)
+            .build()
+            .is_ok());
+        assert!(matches!(
+            builder.tick(MAX_TICK + 1).build(),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick == MAX_TICK + 1
+        ));
+    }
+
+    #[test]
+    fn test_math_builder_fee_tier() {
+        let builder = Math::builder()
+            .liquidity(1_000_000_000_000_000_000)
+            .sqrt_price_x96(get_sqrt_ratio_at_tick(0).unwrap());
+
+        for (fee, tick_spacing) in [(100, 1), (500, 10), (3000, 60), (10000, 200)] {
+            let math = builder
+                .clone()
+                .fee(fee)
+                .tick_spacing(tick_spacing)
+                .build()
+                .unwrap();
+            assert_eq!((math.fee, math.tick_spacing), (fee, tick_spacing));
+        }
+
+        //a canonical fee with another tier's spacing, and a fork's tier
+        for (fee, tick_spacing) in [(3000, 10), (2500, 50)] {
+            let result = builder.clone().fee(fee).tick_spacing(tick_spacing).build();
+            assert!(matches!(
+                result,
+                Err(UniswapV3MathError::FeeTickSpacingMismatch { fee: f, tick_spacing: s })
+                    if f == fee && s == tick_spacing
+            ));
+
+            let math = builder
+                .clone()
+                .fee(fee)
+                .tick_spacing(tick_spacing)
+                .allow_custom_fee_tier()
+                .build()
+                .unwrap();
+            assert_eq!((math.fee, math.tick_spacing), (fee, tick_spacing));
+        }
+
+        //without a fee there is nothing to cross-check
+        assert_eq!(builder.tick_spacing(50).build().unwrap().fee, 0);
+    }
+}
@@ -1,3 +1,7 @@
+use std::path::PathBuf;
+
+use alloy_primitives::I256;
+use reth_primitives::U256;
 use thiserror::Error;
 
 // TODO: make these errors better, some errors in univ3 libs are just require(condition) without a
@@ -27,14 +31,119 @@ pub enum UniswapV3MathError {
     LiquiditySub,
     #[error("Liquidity Add")]
     LiquidityAdd,
+    #[error(
+        "Crossing tick {tick} with liquidity_net {liquidity_net} underflows liquidity {liquidity}"
+    )]
+    LiquidityUnderflow {
+        tick: i32,
+        liquidity: u128,
+        liquidity_net: i128,
+    },
+    #[error(
+        "Crossing tick {tick} with liquidity_net {liquidity_net} overflows liquidity {liquidity}"
+    )]
+    LiquidityOverflow {
+        tick: i32,
+        liquidity: u128,
+        liquidity_net: i128,
+    },
     #[error("The given tick must be less than, or equal to, the maximum tick")]
     T,
     #[error(
         "Second inequality must be < because the price can never reach the price at the max tick"
     )]
     R,
+    #[error("Sqrt ratios must be sorted in ascending order")]
+    SqrtRatiosNotSorted,
     #[error("Overflow when casting to U160")]
     SafeCastToU160Overflow,
+    #[error("Sqrt price limit must be between the current price and the price bound")]
+    InvalidSqrtPriceLimit,
+    #[error("Amount specified must not be zero")]
+    AmountSpecifiedZero,
+    #[error("Amount specified of I256::MIN has no exact output counterpart")]
+    AmountSpecifiedOverflow,
+    #[error("Requested output {requested} exceeds the {available} the pool can provide")]
+    InsufficientLiquidity { requested: U256, available: U256 },
+    #[error("No input up to the maximum yields the requested output")]
+    OutputNotReachable,
+    #[error("No exact input swap ends in tick {tick}")]
+    TickNotReachable { tick: i32 },
+    #[error("Swap did not finish within {steps} steps, {remaining} left to swap")]
+    MaxStepsExceeded { steps: usize, remaining: I256 },
+    #[error("Swap step made no progress")]
+    SwapNoProgress,
+    #[error("Tick {tick} is not a multiple of the tick spacing {spacing}")]
+    TickNotAligned { tick: i32, spacing: i32 },
+    #[error("Quote curve range [{min_in}, {max_in}] is empty or starts at zero on a log scale")]
+    InvalidCurveRange { min_in: U256, max_in: U256 },
+    #[error("Tick range [{tick_lower}, {tick_upper}) is empty")]
+    InvalidTickRange { tick_lower: i32, tick_upper: i32 },
+    #[error("Tick spacing {tick_spacing} must be positive")]
+    InvalidTickSpacing { tick_spacing: i32 },
+    #[error("Fee {fee} must be below 1000000")]
+    InvalidFee { fee: u32 },
+    #[error("Fee {fee} with tick spacing {tick_spacing} is not a canonical fee tier")]
+    FeeTickSpacingMismatch { fee: u32, tick_spacing: i32 },
+    #[error("Sqrt price {sqrt_price_x96} is outside [MIN_SQRT_RATIO, MAX_SQRT_RATIO)")]
+    SqrtPriceOutOfRange { sqrt_price_x96: U256 },
+    #[error("Price {price} is not a positive finite number")]
+    InvalidPrice { price: f64 },
+    #[error("Invalid decimal price {price:?}")]
+    InvalidDecimalPrice { price: String },
+    #[error("Tick {tick} is outside [MIN_TICK, MAX_TICK]")]
+    TickOutOfRange { tick: i32 },
+    #[error("Tick {tick} does not match the sqrt price, which is in tick {price_tick}")]
+    TickPriceMismatch { tick: i32, price_tick: i32 },
+    #[error("No liquidity_net for tick {tick}")]
+    MissingLiquidityNet { tick: i32 },
+    #[error("No bitmap word at position {position}")]
+    MissingWord { position: i16 },
+    #[error("No pool at index {pool}")]
+    UnknownPool { pool: usize },
+    #[error("Invalid snapshot: {reason}")]
+    InvalidSnapshot { reason: String },
+    // Reading, writing or parsing a snapshot file failed
+    #[error("Snapshot file {}: {source}", path.display())]
+    SnapshotFile {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    // A store file or one of its entries failed its checksum. entry is None when the header or
+    // trailer is bad.
+    #[error(
+        "Snapshot store is corrupted{}",
+        entry.map(|entry| format!(" at entry {entry}")).unwrap_or_default()
+    )]
+    SnapshotStoreCorrupted { entry: Option<usize> },
+    #[error("Snapshot store is truncated, {needed} bytes needed but the file has {len}")]
+    SnapshotStoreTruncated { len: usize, needed: usize },
+    #[error("Snapshot store format version {found}, expected {expected}")]
+    SnapshotStoreVersion { found: u32, expected: u32 },
+    // verify_against found an initialized tick in a word the summary claims is empty
+    #[error("Occupancy summary claims word {word_pos} is empty but it holds an initialized tick")]
+    StaleOccupancy { word_pos: i16 },
+    #[error("Multicall call {index} failed")]
+    MulticallFailed { index: usize },
     #[error("Error while fetching word from chain")]
     OnchainProvider,
+    // A batch read returned a different number of values than it was asked for
+    #[error("Provider returned {got} values for a batch of {expected}")]
+    ProviderBatchLength { expected: usize, got: usize },
+    // A sync read through an async provider that has no runtime handle attached
+    #[error("No runtime handle to block on, attach one with with_handle")]
+    MissingRuntimeHandle,
+    // A sync read through an async provider from a current_thread runtime's own thread
+    #[error("Can't block on a read from inside a current_thread runtime")]
+    BlockingInRuntime,
+    // Wraps a provider's own error so callers can downcast it back after a failed simulation
+    #[error("Provider error: {0}")]
+    Provider(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl UniswapV3MathError {
+    pub fn provider(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        UniswapV3MathError::Provider(error.into())
+    }
 }
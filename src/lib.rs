@@ -1,20 +1,52 @@
 use alloy_primitives::I256;
+use builder::MAX_FEE;
 use error::UniswapV3MathError;
+use full_math::mul_div;
+use liquidity_math::apply_liquidity_net;
+use occupancy::OccupancySummary;
+use providers::CachedTicksProvider;
 use reth_primitives::U256;
+use ruint::aliases::U512;
+use slippage::target_sqrt_price;
+use sqrt_price_math::{_get_amount_0_delta, _get_amount_1_delta, Q128, Q192, Q96};
+use std::{
+    ops::{ControlFlow, RangeInclusive},
+    sync::Arc,
+};
 use swap_math::compute_swap_step;
 use tick_bitmap::{next_initialized_tick_within_one_word, position};
 use tick_math::{
-    calculate_compressed, get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK,
+    calculate_compressed, get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, max_usable_tick,
+    min_usable_tick, validate_tick, validate_tick_range, SqrtRatioCache, MAX_SQRT_RATIO, MAX_TICK,
     MIN_SQRT_RATIO, MIN_TICK,
 };
 use utils::*;
 
+#[cfg(feature = "async")]
+pub mod async_provider;
+#[cfg(feature = "rayon")]
+pub mod batch;
 pub mod bit_math;
+pub mod builder;
 pub mod error;
 pub mod full_math;
 pub mod liquidity_math;
+pub mod multicall;
+pub mod mut_provider;
+pub mod occupancy;
+pub mod providers;
+#[cfg(feature = "serde")]
+pub mod serde_u256;
+pub mod slippage;
+pub mod slot0;
+pub mod snapshot;
 pub mod sqrt_price_math;
+pub mod storage;
+#[cfg(feature = "persistence")]
+pub mod store;
 pub mod swap_math;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod tick;
 pub mod tick_bitmap;
 pub mod tick_math;
@@ -22,23 +54,108 @@ pub mod unsafe_math;
 pub mod utils;
 
 // Used to retrieve ticks and words from the chain. Ideally, this trait should be implemented by a
-// database reader.
+// database reader. Provider failures can be returned as UniswapV3MathError::Provider to keep the
+// original error available to the caller.
 pub trait TicksProvider {
     fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError>;
 
     fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError>;
+
+    // Returns the words at positions, in order. Providers that can batch reads (multicall, a single
+    // database query) should override this together with words_per_batch.
+    fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+        positions
+            .iter()
+            .map(|position| self.get_word_at_position(*position))
+            .collect()
+    }
+
+    // Returns the liquidity nets at ticks, in order
+    fn get_liquidity_nets(&self, ticks: &[i32]) -> Result<Vec<i128>, UniswapV3MathError> {
+        ticks
+            .iter()
+            .map(|tick| self.get_liquidity_net_at_tick(*tick))
+            .collect()
+    }
+
+    // How many words the swap loop requests through get_words when it needs a new one, the needed
+    // word followed by the next ones in the swap direction. 1 fetches one word at a time.
+    fn words_per_batch(&self) -> usize {
+        1
+    }
+
+    // Positions of the lowest and highest words with an initialized tick, empty when there are
+    // none and None when unknown. Lets a swap that runs out of liquidity past them go straight to
+    // its price limit instead of reading every empty word on the way.
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        None
+    }
+}
+
+// Lets Math borrow or share a provider (Math<&P>, Math<Arc<P>>) and hold a trait object
+// (Math<Box<dyn TicksProvider>>). Every method is forwarded so batching overrides are kept.
+macro_rules! forward_ticks_provider {
+    ($($wrapper:ty),*) => {$(
+        impl<T: TicksProvider + ?Sized> TicksProvider for $wrapper {
+            fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+                (**self).get_word_at_position(position)
+            }
+
+            fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+                (**self).get_liquidity_net_at_tick(tick)
+            }
+
+            fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+                (**self).get_words(positions)
+            }
+
+            fn get_liquidity_nets(&self, ticks: &[i32]) -> Result<Vec<i128>, UniswapV3MathError> {
+                (**self).get_liquidity_nets(ticks)
+            }
+
+            fn words_per_batch(&self) -> usize {
+                (**self).words_per_batch()
+            }
+
+            fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+                (**self).initialized_word_range()
+            }
+        }
+    )*};
 }
 
+forward_ticks_provider!(&T, Arc<T>, Box<T>);
+
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Math<Provider> {
     pub fee: u32,
+    // slot0.feeProtocol, the protocol's share of swap fees as 1/n with token0's n in the low 4 bits
+    // and token1's in the high 4. 0 leaves all fees to LPs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_protocol: u8,
     pub liquidity: u128,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
     pub sqrt_price_x96: U256,
     pub tick: i32,
     pub tick_spacing: i32,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub provider: Provider,
+    // Upper bound on swap loop iterations, 0 means DEFAULT_MAX_STEPS
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_steps: usize,
+    // Sqrt ratios the swap loop reads instead of computing them, see Math::with_sqrt_cache
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub sqrt_ratio_cache: Option<Arc<SqrtRatioCache>>,
+    // Words the swap loop can load as empty without reading them, see Math::with_occupancy
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub occupancy: Option<Arc<OccupancySummary>>,
 }
 
+// A walk from one tick bound to the other at tick spacing 1 stops at 6932 word boundaries, so
+// this leaves room for a few thousand initialized ticks on the way
+pub const DEFAULT_MAX_STEPS: usize = 10_000;
+
 impl<Provider> Math<Provider>
 where
     Provider: TicksProvider,
@@ -49,6 +166,49 @@ where
         self.tick = tick;
     }
 
+    // Moves the pool to the state a simulated swap ended in, so follow-up swaps can be simulated
+    // on top of it
+    pub fn apply_swap(&mut self, result: &SwapResult) {
+        self.update(result.liquidity, result.sqrt_price_x96, result.tick);
+    }
+
+    pub fn state(&self) -> SwapState {
+        SwapState {
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            liquidity: self.liquidity,
+        }
+    }
+
+    // Simulates an exact input swap starting from state instead of the pool's own, with the pool's
+    // fee, tick spacing and provider. state is checked like MathBuilder::build checks it, and
+    // SwapResult::state gives the state to chain the next hypothetical from.
+    pub fn simulate_swap_from(
+        &self,
+        state: SwapState,
+        zero_for_one: bool,
+        amount_in: U256,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let math = Math::builder()
+            .fee(self.fee)
+            .fee_protocol(self.fee_protocol)
+            .liquidity(state.liquidity)
+            .sqrt_price_x96(state.sqrt_price_x96)
+            .tick(state.tick)
+            .tick_spacing(self.tick_spacing)
+            .allow_custom_fee_tier()
+            .max_steps(self.max_steps)
+            .provider(&self.provider)
+            .build()?;
+
+        math.swap_exact_input(
+            zero_for_one,
+            amount_in,
+            default_sqrt_price_limit(zero_for_one),
+            |_| {},
+        )
+    }
+
     pub fn simulate_swap(
         &self,
         zero_for_one: bool,
@@ -58,149 +218,4206 @@ where
             return Ok(U256::ZERO);
         }
 
-        //Set sqrt_price_limit_x96 to the max or min sqrt price in the pool depending on
-        // zero_for_one
-        let sqrt_price_limit_x96 = if zero_for_one {
-            MIN_SQRT_RATIO + RUINT_ONE
-        } else {
-            MAX_SQRT_RATIO - RUINT_ONE
-        };
+        let current_state = self.swap(
+            zero_for_one,
+            u256_to_i256(amount_in),
+            default_sqrt_price_limit(zero_for_one),
+            |_| {},
+        )?;
 
-        //Initialize a mutable state state struct to hold the dynamic simulated state of the pool
-        let mut current_state = CurrentState {
-            sqrt_price_x96: self.sqrt_price_x96, //Active price on the pool
-            amount_calculated: I256::ZERO,       //Amount of token_out that has been calculated
-            amount_specified_remaining: u256_to_i256(amount_in),
-            tick: self.tick,           //Current i24 tick of the pool
-            liquidity: self.liquidity, //Current available liquidity in the tick range
-            word_pos: position(calculate_compressed(self.tick, self.tick_spacing)).0,
+        Ok(i256_to_u256(-current_state.amount_calculated))
+    }
+
+    // Quotes several input amounts with a single walk over the ticks, returning outputs in the order
+    // of amounts. Each result is identical to calling simulate_swap with that amount.
+    pub fn simulate_swap_many(
+        &self,
+        zero_for_one: bool,
+        amounts: &[U256],
+    ) -> Result<Vec<U256>, UniswapV3MathError> {
+        let mut amounts_out = vec![U256::ZERO; amounts.len()];
+        self.walk_amounts_in(zero_for_one, amounts, |i, current_state| {
+            amounts_out[i] = i256_to_u256(-current_state.amount_calculated);
+        })?;
+
+        Ok(amounts_out)
+    }
+
+    // Quotes points input sizes from min_in to max_in, both included, spaced evenly or
+    // geometrically according to scale, with a single walk over the ticks. Every point matches
+    // simulate_swap with its amount_in.
+    pub fn quote_curve(
+        &self,
+        zero_for_one: bool,
+        min_in: U256,
+        max_in: U256,
+        points: usize,
+        scale: CurveScale,
+    ) -> Result<Vec<CurvePoint>, UniswapV3MathError> {
+        if min_in > max_in || (scale == CurveScale::Log && min_in == U256::ZERO) {
+            return Err(UniswapV3MathError::InvalidCurveRange { min_in, max_in });
+        }
+
+        let amounts = curve_amounts(min_in, max_in, points, scale);
+        let mut curve = vec![CurvePoint::default(); amounts.len()];
+        self.walk_amounts_in(zero_for_one, &amounts, |i, current_state| {
+            let amount_out = i256_to_u256(-current_state.amount_calculated);
+            let amount_used = amounts[i] - i256_to_u256(current_state.amount_specified_remaining);
+            let (execution_price_x96, _) =
+                execution_price(amount_used, amount_out, self.sqrt_price_x96, zero_for_one);
+            curve[i] = CurvePoint {
+                amount_in: amounts[i],
+                amount_out,
+                execution_price_x96,
+                sqrt_price_x96: current_state.sqrt_price_x96,
+                tick: current_state.tick,
+            };
+        })?;
+
+        Ok(curve)
+    }
+
+    // Simulates an exact input swap that stops once the price reaches sqrt_price_limit_x96, like the
+    // pool's sqrtPriceLimitX96. A zero limit means no limit.
+    pub fn simulate_swap_with_limit(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let sqrt_price_limit_x96 =
+            self.checked_sqrt_price_limit(zero_for_one, sqrt_price_limit_x96)?;
+        self.swap_exact_input(zero_for_one, amount_in, sqrt_price_limit_x96, |_| {})
+    }
+
+    // Same as simulate_swap, but also returns every iteration of the swap loop
+    pub fn simulate_swap_traced(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+    ) -> Result<(SwapResult, Vec<SwapStepTrace>), UniswapV3MathError> {
+        let mut trace = vec![];
+
+        let result = self.swap_exact_input(
+            zero_for_one,
+            amount_in,
+            default_sqrt_price_limit(zero_for_one),
+            |step| trace.push(step),
+        )?;
+
+        Ok((result, trace))
+    }
+
+    // Same as simulate_swap, but calls hook after every step, once its tick has been crossed.
+    // Breaking from the hook stops the swap there and returns the partial result as Aborted.
+    pub fn simulate_swap_with_hook(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+        mut hook: impl FnMut(&StepInfo) -> ControlFlow<()>,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let sqrt_price_limit_x96 = default_sqrt_price_limit(zero_for_one);
+        let mut current_state = self.initial_state(u256_to_i256(amount_in));
+
+        while current_state.in_progress(sqrt_price_limit_x96) {
+            let trace = self.swap_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
+            let info = StepInfo {
+                sqrt_price_start_x96: trace.step.sqrt_price_start_x96,
+                sqrt_price_x96: current_state.sqrt_price_x96,
+                amount_in: trace.step.amount_in,
+                amount_out: trace.step.amount_out,
+                fee_amount: trace.step.fee_amount,
+                crossed: current_state.crossed_tick(&trace.step).is_some(),
+                tick: current_state.tick,
+                liquidity: current_state.liquidity,
+            };
+
+            if hook(&info).is_break() {
+                return Ok(SwapResult {
+                    fill_status: FillStatus::Aborted,
+                    ..SwapResult::from_state(u256_to_i256(amount_in), &current_state)
+                });
+            }
+        }
+
+        Ok(SwapResult::from_state(
+            u256_to_i256(amount_in),
+            &current_state,
+        ))
+    }
+
+    // Reports how far an exact input swap moves the pool price and the average price paid for it
+    pub fn price_impact(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+    ) -> Result<PriceImpact, UniswapV3MathError> {
+        let result = self.swap_exact_input(
+            zero_for_one,
+            amount_in,
+            default_sqrt_price_limit(zero_for_one),
+            |_| {},
+        )?;
+
+        let sqrt_price_before = self.sqrt_price_x96;
+        let sqrt_price_after = result.sqrt_price_x96;
+        let impact_bps = impact_bps(sqrt_price_before, sqrt_price_after)?;
+
+        Ok(PriceImpact {
+            sqrt_price_before,
+            sqrt_price_after,
+            impact_bps,
+            execution_price_x96: result.execution_price_x96,
+        })
+    }
+
+    // Quotes selling amount of token0 and selling amount of token1 from the current state. Both
+    // runs read through one cache, so the words and ticks they share are only fetched once.
+    pub fn quote_both_directions(&self, amount: U256) -> Result<TwoSidedQuote, UniswapV3MathError> {
+        let math = self.with_provider(CachedTicksProvider::new(&self.provider));
+
+        let quote = |zero_for_one| -> Result<SideQuote, UniswapV3MathError> {
+            let result = math.swap_exact_input(
+                zero_for_one,
+                amount,
+                default_sqrt_price_limit(zero_for_one),
+                |_| {},
+            )?;
+
+            Ok(SideQuote {
+                amount_out: result.amount_out,
+                sqrt_price_after: result.sqrt_price_x96,
+                impact_bps: impact_bps(self.sqrt_price_x96, result.sqrt_price_x96)?,
+            })
         };
 
-        let mut word = self.provider.get_word_at_position(current_state.word_pos)?;
+        Ok(TwoSidedQuote {
+            zero_for_one: quote(true)?,
+            one_for_zero: quote(false)?,
+        })
+    }
 
-        while current_state.amount_specified_remaining != I256::ZERO
-            && current_state.sqrt_price_x96 != sqrt_price_limit_x96
-        {
-            //Initialize a new step struct to hold the dynamic state of the pool at each step
-            let mut step = StepComputations {
-                sqrt_price_start_x96: current_state.sqrt_price_x96, /* Set the sqrt_price_start_x96 to the current sqrt_price_x96 */
-                ..Default::default()
+    // Quotes an exact input in each direction from the current state, for market makers refreshing
+    // both sides at once. The walks start from one shared initial state and read through one cache,
+    // so the starting word and any word or tick both sides reach are fetched once, and both use the
+    // pool's SqrtRatioCache if one is attached. Each side equals simulate_swap_with_limit without a
+    // limit.
+    pub fn quote_two_sided(
+        &self,
+        amount_in_zero_for_one: U256,
+        amount_in_one_for_zero: U256,
+    ) -> Result<TwoSidedSwap, UniswapV3MathError> {
+        let math = self.with_provider(CachedTicksProvider::new(&self.provider));
+        let initial_state = math.initial_state(I256::ZERO);
+
+        let side = |zero_for_one, amount_in: U256| -> Result<SwapResult, UniswapV3MathError> {
+            let amount_specified = u256_to_i256(amount_in);
+            let mut current_state = CurrentState {
+                amount_specified_remaining: amount_specified,
+                exact_input: true,
+                ..initial_state.clone()
             };
+            let sqrt_price_limit_x96 = default_sqrt_price_limit(zero_for_one);
+            while current_state.in_progress(sqrt_price_limit_x96) {
+                math.swap_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
+            }
+
+            Ok(SwapResult::from_state(amount_specified, &current_state))
+        };
+
+        let zero_for_one = side(true, amount_in_zero_for_one)?;
+        let one_for_zero = side(false, amount_in_one_for_zero)?;
+        let spread_pips = spread_pips(self.sqrt_price_x96, &zero_for_one, &one_for_zero);
+
+        Ok(TwoSidedSwap {
+            zero_for_one,
+            one_for_zero,
+            mid_sqrt_price_x96: self.sqrt_price_x96,
+            spread_pips,
+        })
+    }
+
+    // Returns (amount_in, amount_out) needed to move the pool price to target_sqrt_price_x96, with
+    // the fee included in amount_in. The direction follows from the side of the target.
+    pub fn simulate_swap_to_price(
+        &self,
+        target_sqrt_price_x96: U256,
+    ) -> Result<(U256, U256), UniswapV3MathError> {
+        if target_sqrt_price_x96 < MIN_SQRT_RATIO || target_sqrt_price_x96 >= MAX_SQRT_RATIO {
+            return Err(UniswapV3MathError::SqrtPriceOutOfRange {
+                sqrt_price_x96: target_sqrt_price_x96,
+            });
+        }
+
+        if target_sqrt_price_x96 == self.sqrt_price_x96 {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+
+        let zero_for_one = target_sqrt_price_x96 < self.sqrt_price_x96;
+
+        // An unbounded exact input, so the walk only stops at the target price
+        let current_state = self.swap(zero_for_one, I256::MAX, target_sqrt_price_x96, |_| {})?;
+        let result = SwapResult::from_state(I256::MAX, &current_state);
+
+        Ok((result.amount_in, result.amount_out))
+    }
+
+    // Input with fees needed to move the spot price by bps basis points, down when zero_for_one
+    // and up otherwise. The target sqrt price is the current one scaled by sqrt(1 -/+ bps/10000),
+    // rounded towards the current price, so the move never exceeds bps. Targets outside the price
+    // bounds fail like simulate_swap_to_price.
+    pub fn amount_to_move_price(
+        &self,
+        zero_for_one: bool,
+        bps: u32,
+    ) -> Result<U256, UniswapV3MathError> {
+        let target_sqrt_price_x96 = target_sqrt_price(self.sqrt_price_x96, zero_for_one, bps)?;
+        Ok(self.simulate_swap_to_price(target_sqrt_price_x96)?.0)
+    }
+
+    // Input with fees that moves the price onto the start of target_tick, so that
+    // get_tick_at_sqrt_ratio of the pool price after an exact input swap of it is target_tick.
+    // Targets past the last liquidity in the swap direction can't be landed on, the price moves
+    // over them for free.
+    pub fn input_to_reach_tick(&self, target_tick: i32) -> Result<U256, UniswapV3MathError> {
+        validate_tick(target_tick, self.tick_spacing)?;
+        if target_tick == self.tick {
+            return Ok(U256::ZERO);
+        }
+
+        // The walks below read the same words and liquidity nets
+        let math = self.with_provider(CachedTicksProvider::new(&self.provider));
+        let zero_for_one = target_tick < self.tick;
+        let (mut amount_in, _) =
+            math.simulate_swap_to_price(get_sqrt_ratio_at_tick(target_tick)?)?;
+
+        // Both walks round the step amounts up, but the final partial step of the exact input swap
+        // derives its price from the amount instead. Moving up that lands on or past the target, in
+        // the target tick. Moving down it can overshoot by less than a wei into the tick below,
+        // which one wei less avoids.
+        let lands = |amount_in: U256| -> Result<bool, UniswapV3MathError> {
+            let result = math.swap_exact_input(
+                zero_for_one,
+                amount_in,
+                default_sqrt_price_limit(zero_for_one),
+                |_| {},
+            )?;
+            Ok(result.fill_status == FillStatus::FullyFilled
+                && get_tick_at_sqrt_ratio(result.sqrt_price_x96)? == target_tick)
+        };
+        if zero_for_one && !lands(amount_in)? {
+            amount_in -= RUINT_ONE;
+        }
+        if !lands(amount_in)? {
+            return Err(UniswapV3MathError::TickNotReachable { tick: target_tick });
+        }
+
+        Ok(amount_in)
+    }
+
+    // Input with fees and output needed to move the price onto each of the next n_ticks initialized
+    // ticks in the swap direction, cumulative from the current price. Fewer points are returned
+    // when the walk reaches the price bound first.
+    pub fn depth_chart(
+        &self,
+        zero_for_one: bool,
+        n_ticks: usize,
+    ) -> Result<Vec<DepthPoint>, UniswapV3MathError> {
+        let sqrt_price_limit_x96 = default_sqrt_price_limit(zero_for_one);
+        let mut current_state = self.initial_state(I256::MAX);
+        let mut points = Vec::with_capacity(n_ticks);
+
+        while points.len() < n_ticks && current_state.in_progress(sqrt_price_limit_x96) {
+            let trace = self.swap_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
+            if trace.step.initialized
+                && current_state.sqrt_price_x96 == trace.step.sqrt_price_next_x96
+            {
+                let result = SwapResult::from_state(I256::MAX, &current_state);
+                points.push(DepthPoint {
+                    tick: trace.step.tick_next,
+                    sqrt_price_x96: trace.step.sqrt_price_next_x96,
+                    amount_in: result.amount_in,
+                    amount_out: result.amount_out,
+                    liquidity: trace.liquidity_before,
+                });
+            }
+        }
+
+        Ok(points)
+    }
+
+    // Price impact in pips, effective price and whether the pool ran out of liquidity for every size
+    // in both directions, for risk dashboards. Each direction is quoted with a single walk over the
+    // ticks like simulate_swap_many, sizes can come in any order and the points keep it. Sizes
+    // past the available liquidity report the impact of moving the price to its bound.
+    pub fn slippage_surface(&self, sizes: &[U256]) -> Result<SlippageSurface, UniswapV3MathError> {
+        let side = |zero_for_one| -> Result<Vec<SurfacePoint>, UniswapV3MathError> {
+            let mut points = vec![SurfacePoint::default(); sizes.len()];
+            self.walk_amounts_in(zero_for_one, sizes, |i, current_state| {
+                let result = SwapResult::from_state(u256_to_i256(sizes[i]), current_state);
+                points[i] = SurfacePoint {
+                    amount_in: sizes[i],
+                    impact_pips: impact_pips(self.sqrt_price_x96, result.sqrt_price_x96),
+                    effective_price_x96: result.execution_price_x96,
+                    exhausted: result.fill_status == FillStatus::LiquidityExhausted,
+                };
+            })?;
+            Ok(points)
+        };
+
+        Ok(SlippageSurface {
+            zero_for_one: side(true)?,
+            one_for_zero: side(false)?,
+        })
+    }
+
+    // Bisects simulate_swap for the smallest input whose output is at least desired_out, stopping
+    // once the bracket is within tolerance. The returned input always fills desired_out, which
+    // makes it safe to submit when the analytic exact output path is off by rounding. Words and
+    // liquidity nets are fetched once and shared across probes.
+    pub fn find_input_for_output(
+        &self,
+        zero_for_one: bool,
+        desired_out: U256,
+        tolerance: U256,
+        max_amount_in: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if desired_out == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let math = self.with_provider(CachedTicksProvider::new(&self.provider));
+
+        if math.simulate_swap(zero_for_one, max_amount_in)? < desired_out {
+            return Err(UniswapV3MathError::OutputNotReachable);
+        }
+
+        // low never fills desired_out and high always does
+        let mut low = U256::ZERO;
+        let mut high = max_amount_in;
+        let tolerance = tolerance.max(RUINT_ONE);
+
+        // Each probe halves the bracket, so this is enough for any U256 range
+        for _ in 0..MAX_SEARCH_ITERATIONS {
+            if high - low <= tolerance {
+                break;
+            }
+
+            let mid = low + ((high - low) >> 1usize);
+            if math.simulate_swap(zero_for_one, mid)? >= desired_out {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        Ok(high)
+    }
+
+    // Returns the input amount, including fees, required to receive exactly amount_out
+    pub fn simulate_swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if amount_out == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let current_state = self.swap(
+            zero_for_one,
+            -u256_to_i256(amount_out),
+            default_sqrt_price_limit(zero_for_one),
+            |_| {},
+        )?;
+
+        // The walk hit the price limit before the full output could be produced, what it did
+        // produce is all the pool has in that direction
+        if current_state.amount_specified_remaining != I256::ZERO {
+            return Err(UniswapV3MathError::InsufficientLiquidity {
+                requested: amount_out,
+                available: amount_out - i256_to_u256(-current_state.amount_specified_remaining),
+            });
+        }
+
+        Ok(i256_to_u256(current_state.amount_calculated))
+    }
+
+    // simulate_swap with fee_pips in place of the pool's fee for this call only
+    pub fn simulate_swap_with_fee(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+        fee_pips: u32,
+    ) -> Result<U256, UniswapV3MathError> {
+        self.with_fee(fee_pips)?
+            .simulate_swap(zero_for_one, amount_in)
+    }
+
+    // simulate_swap_exact_output with fee_pips in place of the pool's fee for this call only
+    pub fn simulate_swap_exact_output_with_fee(
+        &self,
+        zero_for_one: bool,
+        amount_out: U256,
+        fee_pips: u32,
+    ) -> Result<U256, UniswapV3MathError> {
+        self.with_fee(fee_pips)?
+            .simulate_swap_exact_output(zero_for_one, amount_out)
+    }
+
+    // Returns (amount0, amount1) like the pool's swap function, positive for what the pool receives
+    // and negative for what it pays out. A positive amount_specified is an exact input and a
+    // negative one an exact output. Like the pool, a swap that runs out of liquidity returns the
+    // partial amounts.
+    pub fn simulate_swap_signed(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        if amount_specified == I256::ZERO {
+            return Ok((I256::ZERO, I256::ZERO));
+        }
+
+        self.swap_signed(
+            zero_for_one,
+            amount_specified,
+            default_sqrt_price_limit(zero_for_one),
+        )
+    }
+
+    // Mirrors the pool's swap(zeroForOne, amountSpecified, sqrtPriceLimitX96), including its
+    // checks: a zero amount_specified and a limit behind the current price or past the bounds are
+    // rejected. A zero limit means no limit. Returns (amount0, amount1) signed like
+    // simulate_swap_signed.
+    pub fn simulate_swap_amount_specified(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        if amount_specified == I256::ZERO {
+            return Err(UniswapV3MathError::AmountSpecifiedZero);
+        }
+
+        let sqrt_price_limit_x96 =
+            self.checked_sqrt_price_limit(zero_for_one, sqrt_price_limit_x96)?;
+        self.swap_signed(zero_for_one, amount_specified, sqrt_price_limit_x96)
+    }
+
+    // The swap loop as an iterator over its steps, for callers that want to inspect the state as it
+    // goes or stop early. amount_specified is signed like in swap, and consuming every step gives
+    // the same result as the matching simulate_swap or simulate_swap_exact_output call.
+    pub fn swap_steps(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+    ) -> SwapStepIter<'_, Provider> {
+        SwapStepIter {
+            math: self,
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96: default_sqrt_price_limit(zero_for_one),
+            current_state: self.initial_state(amount_specified),
+            failed: false,
+        }
+    }
 
-            let compressed = calculate_compressed(current_state.tick, self.tick_spacing);
-            let (word_pos, bit_pos) = position(compressed);
+    // Next initialized tick at or below tick when zero_for_one and above it otherwise, searching
+    // word by word like the swap loop, or None when there is none before the usable ticks end.
+    // Words the occupancy summary proves empty aren't read.
+    pub fn next_initialized_tick(
+        &self,
+        tick: i32,
+        zero_for_one: bool,
+    ) -> Result<Option<i32>, UniswapV3MathError> {
+        if self.tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing {
+                tick_spacing: self.tick_spacing,
+            });
+        }
+        let (min_word, max_word) = (
+            position(calculate_compressed(
+                min_usable_tick(self.tick_spacing),
+                self.tick_spacing,
+            ))
+            .0,
+            position(calculate_compressed(
+                max_usable_tick(self.tick_spacing),
+                self.tick_spacing,
+            ))
+            .0,
+        );
 
-            if word_pos != current_state.word_pos {
-                word = self.provider.get_word_at_position(current_state.word_pos)?;
-                current_state.word_pos = word_pos;
+        let mut tick = tick;
+        loop {
+            let (word_pos, bit_pos) = word_position(tick, self.tick_spacing, zero_for_one);
+            if !(min_word..=max_word).contains(&word_pos) {
+                return Ok(None);
             }
 
-            (step.tick_next, step.initialized) = next_initialized_tick_within_one_word(
+            let word = if self.known_empty_word(word_pos) {
+                U256::ZERO
+            } else {
+                self.provider.get_word_at_position(word_pos)?
+            };
+            let (next, initialized) = next_initialized_tick_within_one_word(
                 bit_pos,
                 word,
                 self.tick_spacing,
                 zero_for_one,
-                compressed,
+                calculate_compressed(tick, self.tick_spacing),
             )?;
+            if initialized {
+                return Ok(Some(next));
+            }
 
-            // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of
-            // these bounds Note: this could be removed as we are clamping in the batch contract
-            step.tick_next = step.tick_next.clamp(MIN_TICK, MAX_TICK);
+            //next is the word's last tick in the search direction, continue in the word after it
+            tick = if zero_for_one { next - 1 } else { next };
+        }
+    }
 
-            //Get the next sqrt price from the input amount
-            step.sqrt_price_next_x96 = get_sqrt_ratio_at_tick(step.tick_next)?;
+    // Replaces a zero limit with the price bound and checks the limit like the pool does, a limit
+    // behind the current price would never be reached
+    fn checked_sqrt_price_limit(
+        &self,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        let sqrt_price_limit_x96 = if sqrt_price_limit_x96 == U256::ZERO {
+            default_sqrt_price_limit(zero_for_one)
+        } else {
+            sqrt_price_limit_x96
+        };
 
-            //Target spot price
-            let swap_target_sqrt_ratio = if zero_for_one {
-                if step.sqrt_price_next_x96 < sqrt_price_limit_x96 {
-                    sqrt_price_limit_x96
-                } else {
-                    step.sqrt_price_next_x96
-                }
-            } else if step.sqrt_price_next_x96 > sqrt_price_limit_x96 {
-                sqrt_price_limit_x96
-            } else {
-                step.sqrt_price_next_x96
-            };
+        let valid_limit = if zero_for_one {
+            sqrt_price_limit_x96 < self.sqrt_price_x96 && sqrt_price_limit_x96 > MIN_SQRT_RATIO
+        } else {
+            sqrt_price_limit_x96 > self.sqrt_price_x96 && sqrt_price_limit_x96 < MAX_SQRT_RATIO
+        };
+        if !valid_limit {
+            return Err(UniswapV3MathError::InvalidSqrtPriceLimit);
+        }
 
-            //Compute swap step and update the current state
-            (
-                current_state.sqrt_price_x96,
-                step.amount_in,
-                step.amount_out,
-                step.fee_amount,
-            ) = compute_swap_step(
-                current_state.sqrt_price_x96,
-                swap_target_sqrt_ratio,
-                current_state.liquidity,
-                current_state.amount_specified_remaining,
-                self.fee,
-            )?;
+        Ok(sqrt_price_limit_x96)
+    }
 
-            //Decrement the amount remaining to be swapped and amount received from the step
-            current_state.amount_specified_remaining = current_state
-                .amount_specified_remaining
-                .overflowing_sub(u256_to_i256(
-                    step.amount_in.overflowing_add(step.fee_amount).0,
-                ))
-                .0;
+    // Runs a swap and returns (amount0, amount1) with the pool's signs. I256::MIN has no exact
+    // output counterpart, negating it inside the swap step would overflow.
+    fn swap_signed(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        if amount_specified == I256::MIN {
+            return Err(UniswapV3MathError::AmountSpecifiedOverflow);
+        }
 
-            current_state.amount_calculated -= u256_to_i256(step.amount_out);
+        let current_state =
+            self.swap(zero_for_one, amount_specified, sqrt_price_limit_x96, |_| {})?;
 
-            //If the price moved all the way to the next price, recompute the liquidity change for
-            // the next iteration
-            if current_state.sqrt_price_x96 == step.sqrt_price_next_x96 {
-                if step.initialized {
-                    let mut liquidity_net =
-                        self.provider.get_liquidity_net_at_tick(step.tick_next)?;
-
-                    // we are on a tick boundary, and the next tick is initialized, so we must
-                    // charge a protocol fee
-                    if zero_for_one {
-                        liquidity_net = -liquidity_net;
-                    }
+        let amount_specified_used = amount_specified - current_state.amount_specified_remaining;
+        Ok(if zero_for_one == current_state.exact_input {
+            (amount_specified_used, current_state.amount_calculated)
+        } else {
+            (current_state.amount_calculated, amount_specified_used)
+        })
+    }
 
-                    current_state.liquidity = if liquidity_net < 0 {
-                        current_state.liquidity - (-liquidity_net as u128)
-                    } else {
-                        current_state.liquidity + (liquidity_net as u128)
-                    };
+    // Runs exact input swaps of all amounts without limit in a single walk over the ticks, calling
+    // visit with the index and final state of each, in increasing order of amount
+    fn walk_amounts_in(
+        &self,
+        zero_for_one: bool,
+        amounts: &[U256],
+        mut visit: impl FnMut(usize, &CurrentState),
+    ) -> Result<(), UniswapV3MathError> {
+        let mut order = (0..amounts.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| amounts[i]);
 
-                    //Increment the current tick
-                    current_state.tick = if zero_for_one {
-                        step.tick_next.wrapping_sub(1)
-                    } else {
-                        step.tick_next
-                    }
-                }
-                //If the current_state sqrt price is not equal to the step sqrt price, then we are
-                // not on the same tick. Update the current_state.tick to the tick
-                // at the current_state.sqrt_price_x96
-            } else if current_state.sqrt_price_x96 != step.sqrt_price_start_x96 {
-                current_state.tick = get_tick_at_sqrt_ratio(current_state.sqrt_price_x96)?;
+        let sqrt_price_limit_x96 = default_sqrt_price_limit(zero_for_one);
+
+        // A larger input takes the same full steps as a smaller one, so every swap resumes from the
+        // start of the step where the previous, smaller swap ran out
+        let mut checkpoint = self.initial_state(I256::ZERO);
+        checkpoint.exact_input = true;
+        let mut checkpoint_amount = U256::ZERO;
+
+        for i in order {
+            let amount_in = amounts[i];
+            if amount_in == U256::ZERO {
+                visit(i, &checkpoint);
+                continue;
+            }
+
+            let mut current_state = checkpoint.clone();
+            current_state.amount_specified_remaining += u256_to_i256(amount_in - checkpoint_amount);
+
+            checkpoint = current_state.clone();
+            while current_state.in_progress(sqrt_price_limit_x96) {
+                checkpoint = current_state.clone();
+                self.swap_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
             }
+            checkpoint_amount = amount_in;
+
+            visit(i, &current_state);
         }
 
-        Ok(i256_to_u256(-current_state.amount_calculated))
+        Ok(())
     }
-}
 
-struct CurrentState {
-    amount_specified_remaining: I256,
-    amount_calculated: I256,
-    sqrt_price_x96: U256,
-    tick: i32,
-    liquidity: u128,
-    word_pos: i16,
-}
+    // Runs an exact input swap and collects the result
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: U256,
+        sqrt_price_limit_x96: U256,
+        on_step: impl FnMut(SwapStepTrace),
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let current_state = self.swap(
+            zero_for_one,
+            u256_to_i256(amount_in),
+            sqrt_price_limit_x96,
+            on_step,
+        )?;
 
-#[derive(Default)]
-struct StepComputations {
-    sqrt_price_start_x96: U256,
-    tick_next: i32,
-    initialized: bool,
-    sqrt_price_next_x96: U256,
+        Ok(SwapResult::from_state(
+            u256_to_i256(amount_in),
+            &current_state,
+        ))
+    }
+
+    // Runs the pool's swap loop. Like amountSpecified in the pool contract, a positive
+    // amount_specified is an exact input and a negative one an exact output. on_step is called once
+    // per iteration, callers that don't need a trace pass a no-op closure.
+    fn swap(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+        mut on_step: impl FnMut(SwapStepTrace),
+    ) -> Result<CurrentState, UniswapV3MathError> {
+        let mut steps = SwapStepIter {
+            math: self,
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x96,
+            current_state: self.initial_state(amount_specified),
+            failed: false,
+        };
+        for step in &mut steps {
+            on_step(step?);
+        }
+
+        Ok(steps.current_state)
+    }
+
+    // One iteration of the swap loop, advancing current_state to the next initialized tick, the
+    // price limit or the point where the specified amount runs out
+    fn swap_step(
+        &self,
+        current_state: &mut CurrentState,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<SwapStepTrace, UniswapV3MathError> {
+        if let Some(word_pos) = self.begin_step(current_state, zero_for_one)? {
+            let skipped_to = self.skip_empty_words(
+                current_state,
+                word_pos,
+                zero_for_one,
+                sqrt_price_limit_x96,
+                || self.initialized_words(self.provider.initialized_word_range()),
+            )?;
+            match skipped_to {
+                Some(word_pos) => current_state.load_word(word_pos, U256::ZERO),
+                None => {
+                    let word = match current_state.prefetched_word(word_pos) {
+                        Some(word) => word,
+                        None if self.known_empty_word(word_pos) => U256::ZERO,
+                        None => self.fetch_word(current_state, word_pos, zero_for_one)?,
+                    };
+                    current_state.load_word(word_pos, word);
+                }
+            }
+        }
+
+        let step = self.compute_step(current_state, zero_for_one, sqrt_price_limit_x96)?;
+
+        let liquidity_net = match current_state.crossed_tick(&step) {
+            Some(tick) => Some(self.provider.get_liquidity_net_at_tick(tick)?),
+            None => None,
+        };
+
+        self.finish_step(current_state, step, liquidity_net, zero_for_one)
+    }
+
+    // Fetches the word at word_pos, prefetching the following words in the swap direction when the
+    // provider reads in batches
+    fn fetch_word(
+        &self,
+        current_state: &mut CurrentState,
+        word_pos: i16,
+        zero_for_one: bool,
+    ) -> Result<U256, UniswapV3MathError> {
+        let words_per_batch = self.provider.words_per_batch();
+        if words_per_batch <= 1 {
+            return self.provider.get_word_at_position(word_pos);
+        }
+
+        // Words past the usable ticks can never hold an initialized one
+        let last_word_pos = if zero_for_one {
+            position(calculate_compressed(
+                min_usable_tick(self.tick_spacing),
+                self.tick_spacing,
+            ))
+            .0
+        } else {
+            position(calculate_compressed(
+                max_usable_tick(self.tick_spacing),
+                self.tick_spacing,
+            ))
+            .0
+        };
+
+        let mut positions = vec![word_pos];
+        while positions.len() < words_per_batch {
+            let previous = positions[positions.len() - 1];
+            if (zero_for_one && previous <= last_word_pos)
+                || (!zero_for_one && previous >= last_word_pos)
+            {
+                break;
+            }
+            positions.push(if zero_for_one {
+                previous - 1
+            } else {
+                previous + 1
+            });
+        }
+
+        let words = self.provider.get_words(&positions)?;
+        if words.len() != positions.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: positions.len(),
+                got: words.len(),
+            });
+        }
+
+        let word = words[0];
+        current_state.prefetched_words = positions.into_iter().zip(words).skip(1).collect();
+
+        Ok(word)
+    }
+}
+
+// The swap loop split around its provider reads, so that sync and async providers drive the same
+// steps. A step is begin_step, loading the returned word if any, compute_step, fetching the
+// liquidity_net of the crossed tick if any, and finish_step.
+impl<Provider> Math<Provider> {
+    // Same pool on the same provider with fee_pips in place of fee, for quoting forks whose swap
+    // fee differs from the one in pool state or comparing fee tiers. Any quoting method can be
+    // called on the result.
+    pub fn with_fee(&self, fee_pips: u32) -> Result<Math<&Provider>, UniswapV3MathError> {
+        if fee_pips >= MAX_FEE {
+            return Err(UniswapV3MathError::InvalidFee { fee: fee_pips });
+        }
+
+        Ok(Math {
+            fee: fee_pips,
+            ..self.with_provider(&self.provider)
+        })
+    }
+
+    // Same pool with the swap loop reading the sqrt ratios of the ticks it steps to from cache
+    // rather than computing them. Results are unchanged; a cache built for another tick spacing
+    // only serves the ticks both spacings share.
+    pub fn with_sqrt_cache(self, cache: Arc<SqrtRatioCache>) -> Self {
+        Math {
+            sqrt_ratio_cache: Some(cache),
+            ..self
+        }
+    }
+
+    // Same pool with the swap loop loading the words summary proves empty as zero instead of
+    // reading them from the provider. Results are unchanged as long as the summary never claims an
+    // occupied word is empty, see OccupancySummary. A summary for another tick spacing is ignored.
+    pub fn with_occupancy(self, summary: Arc<OccupancySummary>) -> Self {
+        Math {
+            occupancy: Some(summary),
+            ..self
+        }
+    }
+
+    // Same pool state on top of another provider
+    fn with_provider<P>(&self, provider: P) -> Math<P> {
+        Math {
+            fee: self.fee,
+            fee_protocol: self.fee_protocol,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            tick_spacing: self.tick_spacing,
+            provider,
+            max_steps: self.max_steps,
+            sqrt_ratio_cache: self.sqrt_ratio_cache.clone(),
+            occupancy: self.occupancy.clone(),
+        }
+    }
+
+    // The pool's current price, as stored in slot0
+    pub fn mid_sqrt_price(&self) -> U256 {
+        self.sqrt_price_x96
+    }
+
+    // Raw price of token0 in token1 at tick as numerator / denominator. That's the square of the
+    // tick's sqrt price over 2^192, exact below 2^64. Above, the square no longer fits and both
+    // sides are divided by 2^64, dropping at most 2^-192 of the price.
+    pub fn price_at_tick(&self, tick: i32) -> Result<(U256, U256), UniswapV3MathError> {
+        let sqrt_price_x96 = get_sqrt_ratio_at_tick(tick)?;
+
+        if sqrt_price_x96 < Q128 {
+            Ok((sqrt_price_x96 * sqrt_price_x96, Q192))
+        } else {
+            let q64 = U256::from(1_u128 << 64);
+            Ok((mul_div(sqrt_price_x96, sqrt_price_x96, q64)?, Q128))
+        }
+    }
+
+    // Tick whose price is nearest to the raw price numerator / denominator of token0 in token1. The
+    // sqrt price is the integer square root of numerator * 2^192 / denominator, computed in 512 bits
+    // as the quotient exceeds 256 bits for prices above 2^64.
+    pub fn tick_for_price(
+        &self,
+        numerator: U256,
+        denominator: U256,
+    ) -> Result<i32, UniswapV3MathError> {
+        if denominator == U256::ZERO {
+            return Err(UniswapV3MathError::DenominatorIsZero);
+        }
+
+        let ratio_x192 = (U512::from(numerator) << 192usize) / U512::from(denominator);
+        let sqrt_price_x96 = ratio_x192.root(2);
+        if sqrt_price_x96 < U512::from(MIN_SQRT_RATIO)
+            || sqrt_price_x96 >= U512::from(MAX_SQRT_RATIO)
+        {
+            return Err(UniswapV3MathError::SqrtPriceOutOfRange {
+                sqrt_price_x96: U256::saturating_from(sqrt_price_x96),
+            });
+        }
+
+        let sqrt_price_x96 = U256::from(sqrt_price_x96);
+        let tick = get_tick_at_sqrt_ratio(sqrt_price_x96)?;
+        if tick < MAX_TICK
+            && get_sqrt_ratio_at_tick(tick + 1)? - sqrt_price_x96
+                < sqrt_price_x96 - get_sqrt_ratio_at_tick(tick)?
+        {
+            Ok(tick + 1)
+        } else {
+            Ok(tick)
+        }
+    }
+
+    // Price of one whole token0 in whole token1, sqrt_price_x96^2 / 2^192 scaled by the decimals
+    pub fn price_token0_in_token1(&self, decimals_0: u8, decimals_1: u8) -> f64 {
+        let sqrt_price = f64::from(self.sqrt_price_x96) / 2f64.powi(96);
+        sqrt_price * sqrt_price * 10f64.powi(decimals_0 as i32 - decimals_1 as i32)
+    }
+
+    // Exact decimal expansion of price_token0_in_token1, which always terminates as the raw price
+    // is a fraction over 2^192
+    pub fn price_token0_in_token1_decimal(
+        &self,
+        decimals_0: u8,
+        decimals_1: u8,
+    ) -> Result<String, UniswapV3MathError> {
+        let integer = mul_div(self.sqrt_price_x96, self.sqrt_price_x96, Q192)?;
+        let fraction_mask = Q192 - RUINT_ONE;
+
+        // The low 192 bits of the wrapping square are the numerator of the fractional part
+        let mut remainder = self.sqrt_price_x96.wrapping_mul(self.sqrt_price_x96) & fraction_mask;
+        let mut digits = integer.to_string();
+        let integer_digits = digits.len() as i32;
+        while remainder != U256::ZERO {
+            remainder *= U256::from(10);
+            digits.push(char::from(b'0' + (remainder >> 192usize).to::<u8>()));
+            remainder &= fraction_mask;
+        }
+
+        Ok(place_decimal_point(
+            &digits,
+            integer_digits + decimals_0 as i32 - decimals_1 as i32,
+        ))
+    }
+
+    // Amounts a mint of liquidity_delta over [tick_lower, tick_upper) pays in, rounded up like the
+    // pool. The returned liquidity is the active liquidity after the mint.
+    pub fn simulate_mint(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: u128,
+    ) -> Result<LiquidityChange, UniswapV3MathError> {
+        self.modify_position(tick_lower, tick_upper, liquidity_delta, true)
+    }
+
+    // Amounts a burn of liquidity_delta over [tick_lower, tick_upper) releases, rounded down like
+    // the pool. The returned liquidity is the active liquidity after the burn.
+    pub fn simulate_burn(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: u128,
+    ) -> Result<LiquidityChange, UniswapV3MathError> {
+        self.modify_position(tick_lower, tick_upper, liquidity_delta, false)
+    }
+
+    // Mirrors the pool's _modifyPosition: below the range the position is all token0, above it all
+    // token1, and inside it both, with the active liquidity changing as well
+    fn modify_position(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: u128,
+        mint: bool,
+    ) -> Result<LiquidityChange, UniswapV3MathError> {
+        validate_tick_range(tick_lower, tick_upper, self.tick_spacing)?;
+
+        let sqrt_ratio_lower_x96 = get_sqrt_ratio_at_tick(tick_lower)?;
+        let sqrt_ratio_upper_x96 = get_sqrt_ratio_at_tick(tick_upper)?;
+
+        let mut liquidity = self.liquidity;
+        let (amount_0, amount_1) = if self.tick < tick_lower {
+            let amount_0 = _get_amount_0_delta(
+                sqrt_ratio_lower_x96,
+                sqrt_ratio_upper_x96,
+                liquidity_delta,
+                mint,
+            )?;
+            (amount_0, U256::ZERO)
+        } else if self.tick < tick_upper {
+            liquidity = if mint {
+                liquidity
+                    .checked_add(liquidity_delta)
+                    .ok_or(UniswapV3MathError::LiquidityAdd)?
+            } else {
+                liquidity
+                    .checked_sub(liquidity_delta)
+                    .ok_or(UniswapV3MathError::LiquiditySub)?
+            };
+            let amount_0 = _get_amount_0_delta(
+                self.sqrt_price_x96,
+                sqrt_ratio_upper_x96,
+                liquidity_delta,
+                mint,
+            )?;
+            let amount_1 = _get_amount_1_delta(
+                sqrt_ratio_lower_x96,
+                self.sqrt_price_x96,
+                liquidity_delta,
+                mint,
+            )?;
+            (amount_0, amount_1)
+        } else {
+            let amount_1 = _get_amount_1_delta(
+                sqrt_ratio_lower_x96,
+                sqrt_ratio_upper_x96,
+                liquidity_delta,
+                mint,
+            )?;
+            (U256::ZERO, amount_1)
+        };
+
+        Ok(LiquidityChange {
+            amount_0,
+            amount_1,
+            liquidity,
+        })
+    }
+
+    fn initial_state(&self, amount_specified: I256) -> CurrentState {
+        CurrentState {
+            sqrt_price_start_x96: self.sqrt_price_x96,
+            sqrt_price_x96: self.sqrt_price_x96, //Active price on the pool
+            amount_calculated: I256::ZERO, //Amount of the unspecified token that has been calculated
+            amount_specified_remaining: amount_specified,
+            exact_input: amount_specified > I256::ZERO,
+            fee_amount: U256::ZERO, //Fees paid so far, part of the input side
+            protocol_fee: U256::ZERO, //Part of fee_amount taken by the protocol
+            fee_growth_global_x128: U256::ZERO, //Fee growth of the input token since the start
+            tick: self.tick,        //Current i24 tick of the pool
+            liquidity: self.liquidity, //Current available liquidity in the tick range
+            word_pos: None,
+            word: U256::ZERO,
+            prefetched_words: vec![],
+            steps: 0,
+            ticks_crossed: 0,
+        }
+    }
+
+    // Counts the step against max_steps and returns the bitmap word to load, if it isn't loaded yet.
+    // Every iteration counts, whether it crosses an initialized tick, stops at a word boundary or
+    // jumps through empty words with skip_empty_words.
+    fn begin_step(
+        &self,
+        current_state: &mut CurrentState,
+        zero_for_one: bool,
+    ) -> Result<Option<i16>, UniswapV3MathError> {
+        //tick_spacing is a public field, check it before anything divides by it
+        if self.tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing {
+                tick_spacing: self.tick_spacing,
+            });
+        }
+
+        let max_steps = if self.max_steps == 0 {
+            DEFAULT_MAX_STEPS
+        } else {
+            self.max_steps
+        };
+        if current_state.steps == max_steps {
+            return Err(UniswapV3MathError::MaxStepsExceeded {
+                steps: current_state.steps,
+                remaining: current_state.amount_specified_remaining,
+            });
+        }
+        current_state.steps += 1;
+
+        let (word_pos, _) = word_position(current_state.tick, self.tick_spacing, zero_for_one);
+
+        Ok((current_state.word_pos != Some(word_pos)).then_some(word_pos))
+    }
+
+    // Without liquidity and with every initialized word behind, the rest of the swap only moves the
+    // price through empty words. Moves the state to where the pool's walk would enter the word
+    // holding the limit and returns that word's position to load as empty, or None to fetch
+    // word_pos as usual.
+    fn skip_empty_words(
+        &self,
+        current_state: &mut CurrentState,
+        word_pos: i16,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U256,
+        initialized_word_range: impl FnOnce() -> Option<RangeInclusive<i16>>,
+    ) -> Result<Option<i16>, UniswapV3MathError> {
+        if current_state.liquidity != 0 {
+            return Ok(None);
+        }
+        let Some(initialized_words) = initialized_word_range() else {
+            return Ok(None);
+        };
+        let past_initialized = if zero_for_one {
+            initialized_words.is_empty() || word_pos < *initialized_words.start()
+        } else {
+            initialized_words.is_empty() || word_pos > *initialized_words.end()
+        };
+        if !past_initialized {
+            return Ok(None);
+        }
+
+        let limit_tick = get_tick_at_sqrt_ratio(sqrt_price_limit_x96)?;
+        let (limit_word_pos, _) = word_position(limit_tick, self.tick_spacing, zero_for_one);
+        let limit_word_ahead = if zero_for_one {
+            limit_word_pos < word_pos
+        } else {
+            limit_word_pos > word_pos
+        };
+        if !limit_word_ahead {
+            return Ok(Some(word_pos));
+        }
+
+        // Moving left the walk enters a word from the next word's first tick, which is uninitialized
+        // and leaves the tick one below it. Moving right it enters from the previous word's last tick.
+        let boundary_tick = if zero_for_one {
+            (limit_word_pos as i32 + 1) * 256 * self.tick_spacing
+        } else {
+            (limit_word_pos as i32 * 256 - 1) * self.tick_spacing
+        };
+        current_state.sqrt_price_x96 = self.sqrt_ratio_at_tick(boundary_tick)?;
+        current_state.tick = if zero_for_one {
+            boundary_tick - 1
+        } else {
+            boundary_tick
+        };
+
+        Ok(Some(limit_word_pos))
+    }
+
+    // The summary from with_occupancy, if it is for this pool's tick spacing
+    fn usable_occupancy(&self) -> Option<&OccupancySummary> {
+        self.occupancy
+            .as_deref()
+            .filter(|summary| summary.tick_spacing() == self.tick_spacing)
+    }
+
+    fn known_empty_word(&self, word_pos: i16) -> bool {
+        self.usable_occupancy()
+            .is_some_and(|summary| summary.is_empty(word_pos))
+    }
+
+    // The provider's initialized_word_range for skip_empty_words, or the summary's occupied words
+    // when the provider can't tell
+    fn initialized_words(
+        &self,
+        provider_range: Option<RangeInclusive<i16>>,
+    ) -> Option<RangeInclusive<i16>> {
+        provider_range.or_else(|| {
+            self.usable_occupancy()
+                .map(|summary| summary.occupied_range())
+        })
+    }
+
+    fn sqrt_ratio_at_tick(&self, tick: i32) -> Result<U256, UniswapV3MathError> {
+        match &self.sqrt_ratio_cache {
+            Some(cache) => cache.get(tick),
+            None => get_sqrt_ratio_at_tick(tick),
+        }
+    }
+
+    // Moves the price towards the next initialized tick in the loaded word and accounts the amounts
+    fn compute_step(
+        &self,
+        current_state: &mut CurrentState,
+        zero_for_one: bool,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<StepComputations, UniswapV3MathError> {
+        //Initialize a new step struct to hold the dynamic state of the pool at each step
+        let mut step = StepComputations {
+            sqrt_price_start_x96: current_state.sqrt_price_x96, /* Set the sqrt_price_start_x96 to the current sqrt_price_x96 */
+            ..Default::default()
+        };
+
+        let compressed = calculate_compressed(current_state.tick, self.tick_spacing);
+        let (_, bit_pos) = word_position(current_state.tick, self.tick_spacing, zero_for_one);
+
+        (step.tick_next, step.initialized) = next_initialized_tick_within_one_word(
+            bit_pos,
+            current_state.word,
+            self.tick_spacing,
+            zero_for_one,
+            compressed,
+        )?;
+
+        // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of
+        // these bounds. Word boundaries are multiples of tick_spacing, so the only targets past
+        // the usable ticks are these bounds. Like the pool, the walk has to reach them to get to
+        // the price limit once every initialized tick is crossed, so this is not
+        // min/max_usable_tick.
+        step.tick_next = step.tick_next.clamp(MIN_TICK, MAX_TICK);
+
+        //Get the next sqrt price from the input amount
+        step.sqrt_price_next_x96 = self.sqrt_ratio_at_tick(step.tick_next)?;
+
+        //Target spot price
+        let swap_target_sqrt_ratio = if zero_for_one {
+            if step.sqrt_price_next_x96 < sqrt_price_limit_x96 {
+                sqrt_price_limit_x96
+            } else {
+                step.sqrt_price_next_x96
+            }
+        } else if step.sqrt_price_next_x96 > sqrt_price_limit_x96 {
+            sqrt_price_limit_x96
+        } else {
+            step.sqrt_price_next_x96
+        };
+
+        //Compute swap step and update the current state
+        (
+            current_state.sqrt_price_x96,
+            step.amount_in,
+            step.amount_out,
+            step.fee_amount,
+        ) = compute_swap_step(
+            current_state.sqrt_price_x96,
+            swap_target_sqrt_ratio,
+            current_state.liquidity,
+            current_state.amount_specified_remaining,
+            self.fee,
+        )?;
+
+        current_state.fee_amount += step.fee_amount;
+
+        // The protocol takes fee_amount / n of the input token's fees, like the pool
+        let fee_protocol = if zero_for_one {
+            self.fee_protocol % 16
+        } else {
+            self.fee_protocol >> 4
+        };
+        if fee_protocol > 0 {
+            step.protocol_fee = step.fee_amount / U256::from(fee_protocol);
+            current_state.protocol_fee += step.protocol_fee;
+        }
+
+        // Fee growth per unit of liquidity from the LP part of the fees, skipped without liquidity
+        // like the pool. feeGrowthGlobal wraps on overflow.
+        if current_state.liquidity > 0 {
+            let fee_growth = mul_div(
+                step.fee_amount - step.protocol_fee,
+                Q128,
+                U256::from(current_state.liquidity),
+            )?;
+            current_state.fee_growth_global_x128 = current_state
+                .fee_growth_global_x128
+                .wrapping_add(fee_growth);
+        }
+
+        if current_state.exact_input {
+            //Decrement the amount remaining to be swapped and amount received from the step
+            current_state.amount_specified_remaining = current_state
+                .amount_specified_remaining
+                .overflowing_sub(u256_to_i256(
+                    step.amount_in.overflowing_add(step.fee_amount).0,
+                ))
+                .0;
+
+            current_state.amount_calculated -= u256_to_i256(step.amount_out);
+        } else {
+            //Increment the (negative) amount remaining by the output and accumulate the input
+            // paid for it
+            current_state.amount_specified_remaining += u256_to_i256(step.amount_out);
+
+            current_state.amount_calculated = current_state
+                .amount_calculated
+                .overflowing_add(u256_to_i256(
+                    step.amount_in.overflowing_add(step.fee_amount).0,
+                ))
+                .0;
+        }
+
+        Ok(step)
+    }
+
+    // Applies the crossed tick's liquidity_net, as returned by crossed_tick, and updates the tick
+    fn finish_step(
+        &self,
+        current_state: &mut CurrentState,
+        step: StepComputations,
+        liquidity_net: Option<i128>,
+        zero_for_one: bool,
+    ) -> Result<SwapStepTrace, UniswapV3MathError> {
+        let liquidity_before = current_state.liquidity;
+        let tick_start = current_state.tick;
+
+        //If the price moved all the way to the next price, recompute the liquidity change for
+        // the next iteration
+        if current_state.sqrt_price_x96 == step.sqrt_price_next_x96 {
+            if let Some(liquidity_net) = liquidity_net {
+                // we are on a tick boundary, and the next tick is initialized, so we must
+                // apply its liquidity_net, negated when moving left. Failing here means the
+                // provider's data disagrees with the tracked liquidity, so report where.
+                current_state.liquidity =
+                    apply_liquidity_net(current_state.liquidity, liquidity_net, zero_for_one)
+                        .map_err(|err| match err {
+                            UniswapV3MathError::LiquiditySub => {
+                                UniswapV3MathError::LiquidityUnderflow {
+                                    tick: step.tick_next,
+                                    liquidity: current_state.liquidity,
+                                    liquidity_net,
+                                }
+                            }
+                            UniswapV3MathError::LiquidityAdd => {
+                                UniswapV3MathError::LiquidityOverflow {
+                                    tick: step.tick_next,
+                                    liquidity: current_state.liquidity,
+                                    liquidity_net,
+                                }
+                            }
+                            err => err,
+                        })?;
+                current_state.ticks_crossed += 1;
+            }
+
+            //Move to the tick we just reached, whether or not it was initialized
+            current_state.tick = if zero_for_one {
+                step.tick_next.wrapping_sub(1)
+            } else {
+                step.tick_next
+            };
+            //If the current_state sqrt price is not equal to the step sqrt price, then we are
+            // not on the same tick. Update the current_state.tick to the tick
+            // at the current_state.sqrt_price_x96
+        } else if current_state.sqrt_price_x96 != step.sqrt_price_start_x96 {
+            current_state.tick = get_tick_at_sqrt_ratio(current_state.sqrt_price_x96)?;
+        }
+
+        // Crossing a tick can legitimately leave the price and the amounts untouched, so only a
+        // step that also stays on the same tick is stuck
+        let amount_moved = if current_state.exact_input {
+            step.amount_in + step.fee_amount
+        } else {
+            step.amount_out
+        };
+        if amount_moved == U256::ZERO
+            && current_state.sqrt_price_x96 == step.sqrt_price_start_x96
+            && current_state.tick == tick_start
+        {
+            return Err(UniswapV3MathError::SwapNoProgress);
+        }
+
+        Ok(SwapStepTrace {
+            step,
+            liquidity_before,
+            liquidity_after: current_state.liquidity,
+        })
+    }
+}
+
+const MAX_SEARCH_ITERATIONS: usize = 256;
+pub(crate) const BPS_DENOMINATOR: u64 = 10_000;
+const PIPS_DENOMINATOR: u64 = 1_000_000;
+
+// Relative move of the spot price from before to after in basis points, |after^2 - before^2| /
+// before^2 factored to keep the products within 512 bits. Rounded down and saturating at u32::MAX.
+fn impact_bps(sqrt_price_before: U256, sqrt_price_after: U256) -> Result<u32, UniswapV3MathError> {
+    let impact_bps = relative_price_move(sqrt_price_before, sqrt_price_after, BPS_DENOMINATOR)?;
+    Ok(impact_bps.try_into().unwrap_or(u32::MAX))
+}
+
+// Same as impact_bps in pips, hundredths of a basis point. Moves whose products don't fit in 256
+// bits, up from very low prices, saturate at u32::MAX as well.
+fn impact_pips(sqrt_price_before: U256, sqrt_price_after: U256) -> u32 {
+    relative_price_move(sqrt_price_before, sqrt_price_after, PIPS_DENOMINATOR)
+        .map_or(u32::MAX, |impact_pips| {
+            impact_pips.try_into().unwrap_or(u32::MAX)
+        })
+}
+
+fn relative_price_move(
+    sqrt_price_before: U256,
+    sqrt_price_after: U256,
+    denominator: u64,
+) -> Result<U256, UniswapV3MathError> {
+    let sqrt_price_delta = if sqrt_price_after > sqrt_price_before {
+        sqrt_price_after - sqrt_price_before
+    } else {
+        sqrt_price_before - sqrt_price_after
+    };
+    mul_div(
+        mul_div(
+            sqrt_price_delta,
+            sqrt_price_after + sqrt_price_before,
+            sqrt_price_before,
+        )?,
+        U256::from(denominator),
+        sqrt_price_before,
+    )
+}
+
+// Ask paid for token0 selling token1 minus bid received for token0 selling it, both relative to
+// the mid price at sqrt_price_x96, in pips. Each is factored like execution_price and rounded
+// down, and the spread saturates at u32::MAX. None when either side received nothing.
+fn spread_pips(
+    sqrt_price_x96: U256,
+    zero_for_one: &SwapResult,
+    one_for_zero: &SwapResult,
+) -> Option<u32> {
+    if zero_for_one.amount_out == U256::ZERO || one_for_zero.amount_out == U256::ZERO {
+        return None;
+    }
+
+    // amount_1 / amount_0 over the mid price
+    let of_mid_pips = |amount_1: U256, amount_0: U256| {
+        mul_div(amount_1, Q96, sqrt_price_x96)
+            .and_then(|amount| mul_div(amount, Q96, sqrt_price_x96))
+            .and_then(|amount| mul_div(amount, U256::from(PIPS_DENOMINATOR), amount_0))
+            .unwrap_or(U256::MAX)
+    };
+    let ask_pips = of_mid_pips(one_for_zero.amount_in, one_for_zero.amount_out);
+    let bid_pips = of_mid_pips(zero_for_one.amount_out, zero_for_one.amount_in);
+
+    Some(
+        ask_pips
+            .saturating_sub(bid_pips)
+            .try_into()
+            .unwrap_or(u32::MAX),
+    )
+}
+
+// amount_in / amount_out as a Q64.96, and its deviation in basis points from the mid price at
+// sqrt_price_x96, both zero when amount_out is. Compared as amount_out / amount_in over the mid
+// price in the same direction, token1 per token0 selling token0 and the inverse selling token1,
+// each factored to stay within mul_div. The deviation is rounded up and saturates at u32::MAX.
+fn execution_price(
     amount_in: U256,
     amount_out: U256,
-    fee_amount: U256,
+    sqrt_price_x96: U256,
+    zero_for_one: bool,
+) -> (U256, u32) {
+    if amount_out == U256::ZERO {
+        return (U256::ZERO, 0);
+    }
+
+    let execution_price_x96 = mul_div(amount_in, Q96, amount_out).unwrap_or(U256::MAX);
+
+    let received_of_mid_bps = if zero_for_one {
+        mul_div(amount_out, Q96, sqrt_price_x96)
+            .and_then(|amount| mul_div(amount, Q96, sqrt_price_x96))
+    } else {
+        mul_div(amount_out, sqrt_price_x96, Q96)
+            .and_then(|amount| mul_div(amount, sqrt_price_x96, Q96))
+    }
+    .and_then(|amount| mul_div(amount, U256::from(BPS_DENOMINATOR), amount_in));
+    let deviation_bps = match received_of_mid_bps {
+        Ok(bps) => U256::from(BPS_DENOMINATOR).saturating_sub(bps),
+        Err(_) => U256::ZERO,
+    };
+
+    (
+        execution_price_x96,
+        deviation_bps.try_into().unwrap_or(u32::MAX),
+    )
+}
+
+// points amounts from min_in to max_in for quote_curve. Log spacing goes through f64, so inner
+// points are approximate, but they stay increasing and within the range.
+fn curve_amounts(min_in: U256, max_in: U256, points: usize, scale: CurveScale) -> Vec<U256> {
+    if points <= 1 {
+        return vec![min_in; points];
+    }
+
+    let intervals = points - 1;
+    (0..points)
+        .map(|i| match scale {
+            _ if i == intervals => max_in,
+            CurveScale::Linear => {
+                min_in + mul_div(max_in - min_in, U256::from(i), U256::from(intervals)).unwrap()
+            }
+            CurveScale::Log => {
+                let (min, max) = (f64::from(min_in), f64::from(max_in));
+                let amount = min * (max / min).powf(i as f64 / intervals as f64);
+                U256::try_from(amount)
+                    .unwrap_or(max_in)
+                    .clamp(min_in, max_in)
+            }
+        })
+        .collect()
+}
+
+// Formats digits with the decimal point after the first point digits, which may lie outside of
+// them, without leading or trailing zeros
+fn place_decimal_point(digits: &str, point: i32) -> String {
+    let (integer, fraction) = if point <= 0 {
+        (String::new(), "0".repeat(-point as usize) + digits)
+    } else if point as usize >= digits.len() {
+        (
+            digits.to_string() + &"0".repeat(point as usize - digits.len()),
+            String::new(),
+        )
+    } else {
+        let (integer, fraction) = digits.split_at(point as usize);
+        (integer.to_string(), fraction.to_string())
+    };
+
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        integer => integer,
+    };
+    match fraction.trim_end_matches('0') {
+        "" => integer.to_string(),
+        fraction => format!("{integer}.{fraction}"),
+    }
+}
+
+// The furthest price a swap can move to, used when the caller does not set a limit
+fn default_sqrt_price_limit(zero_for_one: bool) -> U256 {
+    if zero_for_one {
+        MIN_SQRT_RATIO + RUINT_ONE
+    } else {
+        MAX_SQRT_RATIO - RUINT_ONE
+    }
+}
+
+// Returns the bitmap word and bit to search from. Searching right starts at the next compressed
+// tick, like TickBitmap.nextInitializedTickWithinOneWord does for lte == false.
+fn word_position(tick: i32, tick_spacing: i32, zero_for_one: bool) -> (i16, u8) {
+    let compressed = calculate_compressed(tick, tick_spacing);
+
+    if zero_for_one {
+        position(compressed)
+    } else {
+        position(compressed + 1)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapResult {
+    // Input consumed, including fees
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    // Input left over when the price limit was reached first, or the output not produced for an
+    // exact output swap
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_remaining: U256,
+    // Fees paid across all steps, included in amount_in
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub total_fee: U256,
+    // Split of total_fee between the protocol and LPs according to fee_protocol
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub protocol_fee: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub lp_fee: U256,
+    // Increase of the input token's feeGrowthGlobalX128 over the swap
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub fee_growth_global_delta_x128: U256,
+    // Why the swap stopped, anything but FullyFilled leaves amount_remaining unswapped
+    pub fill_status: FillStatus,
+    // Initialized ticks crossed, each of which the pool reads and updates. Ending exactly on an
+    // initialized tick crosses it too.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ticks_crossed: u32,
+    // Input consumed including fees over output received, as a Q64.96, and how much worse that is
+    // than the pre-swap mid price in basis points. Both are zero when nothing was received.
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_u256"))]
+    pub execution_price_x96: U256,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub execution_deviation_bps: u32,
+    // Pool state after the swap
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+impl SwapResult {
+    // Pool state after the swap
+    pub fn state(&self) -> SwapState {
+        SwapState {
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            liquidity: self.liquidity,
+        }
+    }
+
+    fn from_state(amount_specified: I256, current_state: &CurrentState) -> Self {
+        let amount_remaining = current_state.amount_specified_remaining.unsigned_abs();
+        let amount_specified_used =
+            (amount_specified - current_state.amount_specified_remaining).unsigned_abs();
+        let (amount_in, amount_out) = if current_state.exact_input {
+            (
+                amount_specified_used,
+                current_state.amount_calculated.unsigned_abs(),
+            )
+        } else {
+            (
+                current_state.amount_calculated.unsigned_abs(),
+                amount_specified_used,
+            )
+        };
+
+        let (execution_price_x96, execution_deviation_bps) = execution_price(
+            amount_in,
+            amount_out,
+            current_state.sqrt_price_start_x96,
+            current_state.sqrt_price_x96 < current_state.sqrt_price_start_x96,
+        );
+
+        // The default limits are the price bounds, which are only reached once liquidity runs out
+        let fill_status = if amount_remaining == U256::ZERO {
+            FillStatus::FullyFilled
+        } else if current_state.sqrt_price_x96 == default_sqrt_price_limit(true)
+            || current_state.sqrt_price_x96 == default_sqrt_price_limit(false)
+        {
+            FillStatus::LiquidityExhausted
+        } else {
+            FillStatus::PriceLimitReached
+        };
+
+        SwapResult {
+            amount_in,
+            amount_out,
+            amount_remaining,
+            total_fee: current_state.fee_amount,
+            protocol_fee: current_state.protocol_fee,
+            lp_fee: current_state.fee_amount - current_state.protocol_fee,
+            fee_growth_global_delta_x128: current_state.fee_growth_global_x128,
+            fill_status,
+            ticks_crossed: current_state.ticks_crossed,
+            execution_price_x96,
+            execution_deviation_bps,
+            sqrt_price_x96: current_state.sqrt_price_x96,
+            tick: current_state.tick,
+            liquidity: current_state.liquidity,
+        }
+    }
+}
+
+// The part of the pool state a swap moves, see Math::simulate_swap_from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapState {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FillStatus {
+    #[default]
+    FullyFilled,
+    // The price reached the given sqrt price limit with input left
+    PriceLimitReached,
+    // The pool ran out of liquidity in the swap direction with input left
+    LiquidityExhausted,
+    // A simulate_swap_with_hook hook stopped the swap
+    Aborted,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceImpact {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_before: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_after: U256,
+    // Relative move of the spot price in basis points, rounded down and saturating at u32::MAX
+    pub impact_bps: u32,
+    // Input consumed including fees over output received, as a Q64.96. Zero when nothing was
+    // received.
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub execution_price_x96: U256,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LiquidityChange {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_0: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_1: U256,
+    // Active liquidity after the change, only differs from the pool's when the range contains the
+    // current tick
+    pub liquidity: u128,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TwoSidedQuote {
+    // Selling token0 for token1
+    pub zero_for_one: SideQuote,
+    // Selling token1 for token0
+    pub one_for_zero: SideQuote,
+}
+
+// Math::quote_two_sided, the full result of each side and where they leave the price relative to
+// each other
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TwoSidedSwap {
+    // Selling token0 for token1
+    pub zero_for_one: SwapResult,
+    // Selling token1 for token0
+    pub one_for_zero: SwapResult,
+    // The pool price both sides start from
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub mid_sqrt_price_x96: U256,
+    // Gap between the effective prices of buying and selling token0, relative to the mid price in
+    // pips. None when either side received nothing.
+    pub spread_pips: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SideQuote {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_after: U256,
+    // Deviation of the post-swap price from the current mid price, as in PriceImpact
+    pub impact_bps: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CurveScale {
+    #[default]
+    Linear,
+    Log,
+}
+
+// A point of Math::quote_curve. sqrt_price_x96 and tick are the pool state after the swap, the
+// marginal price the next unit of input would get.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CurvePoint {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    // Input consumed over output as in SwapResult, less than amount_in once the pool runs out of
+    // liquidity
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub execution_price_x96: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+}
+
+// A point of Math::depth_chart. liquidity is the active liquidity on the way to tick, before
+// crossing it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DepthPoint {
+    pub tick: i32,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_x96: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    pub liquidity: u128,
+}
+
+// Math::slippage_surface, one point per size in the order they were given for each direction
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlippageSurface {
+    // Selling token0 for token1
+    pub zero_for_one: Vec<SurfacePoint>,
+    // Selling token1 for token0
+    pub one_for_zero: Vec<SurfacePoint>,
+}
+
+impl SlippageSurface {
+    // Lossy f64 copy of the surface for plotting layers. Sizes and prices lose precision past 2^53
+    // and the points must not be fed back into any calculation.
+    pub fn to_chart_points(&self) -> Vec<SurfaceChartPoint> {
+        let side = |zero_for_one, points: &[SurfacePoint]| {
+            points
+                .iter()
+                .map(move |point| SurfaceChartPoint {
+                    zero_for_one,
+                    size: f64::from(point.amount_in),
+                    impact_pips: point.impact_pips as f64,
+                    effective_price: f64::from(point.effective_price_x96) / 2f64.powi(96),
+                    exhausted: point.exhausted,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut chart_points = side(true, &self.zero_for_one);
+        chart_points.extend(side(false, &self.one_for_zero));
+        chart_points
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfacePoint {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    // Relative move of the spot price in pips, rounded down and saturating at u32::MAX. Zero for a
+    // zero size.
+    pub impact_pips: u32,
+    // Input consumed including fees over output received as in SwapResult, zero when nothing was
+    // received
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub effective_price_x96: U256,
+    // The pool ran out of liquidity before the whole size was swapped, the impact and price are
+    // those of the input it could take
+    pub exhausted: bool,
+}
+
+// A SurfacePoint in f64, see SlippageSurface::to_chart_points. effective_price is in raw token
+// units, input per output.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceChartPoint {
+    pub zero_for_one: bool,
+    pub size: f64,
+    pub impact_pips: f64,
+    pub effective_price: f64,
+    pub exhausted: bool,
+}
+
+// What simulate_swap_with_hook passes to its hook after a step. sqrt_price_x96, tick and
+// liquidity are the pool state after the step, past the crossed tick if crossed is set.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepInfo {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_start_x96: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_x96: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub fee_amount: U256,
+    pub crossed: bool,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+// One iteration of the swap loop. liquidity_before and liquidity_after only differ when the step
+// ended on an initialized tick and crossed it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapStepTrace {
+    pub step: StepComputations,
+    pub liquidity_before: u128,
+    pub liquidity_after: u128,
+}
+
+// Yields one SwapStepTrace per iteration of the swap loop, see Math::swap_steps. The iterator ends
+// with the swap, or after the first error.
+pub struct SwapStepIter<'a, Provider> {
+    math: &'a Math<Provider>,
+    zero_for_one: bool,
+    amount_specified: I256,
+    sqrt_price_limit_x96: U256,
+    current_state: CurrentState,
+    failed: bool,
+}
+
+impl<Provider> SwapStepIter<'_, Provider> {
+    // Totals of the steps taken so far
+    pub fn finish(&self) -> SwapResult {
+        SwapResult::from_state(self.amount_specified, &self.current_state)
+    }
+}
+
+impl<Provider> Iterator for SwapStepIter<'_, Provider>
+where
+    Provider: TicksProvider,
+{
+    type Item = Result<SwapStepTrace, UniswapV3MathError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || !self.current_state.in_progress(self.sqrt_price_limit_x96) {
+            return None;
+        }
+
+        let step = self.math.swap_step(
+            &mut self.current_state,
+            self.zero_for_one,
+            self.sqrt_price_limit_x96,
+        );
+        self.failed = step.is_err();
+        Some(step)
+    }
+}
+
+#[derive(Clone)]
+struct CurrentState {
+    amount_specified_remaining: I256,
+    exact_input: bool,
+    amount_calculated: I256,
+    fee_amount: U256,
+    protocol_fee: U256,
+    fee_growth_global_x128: U256,
+    // Pool price before the first step
+    sqrt_price_start_x96: U256,
+    sqrt_price_x96: U256,
+    tick: i32,
+    liquidity: u128,
+    // Bitmap word the next step searches in, None until the first step loads it
+    word_pos: Option<i16>,
+    word: U256,
+    // Words read ahead of the walk by fetch_word, empty unless the provider batches
+    prefetched_words: Vec<(i16, U256)>,
+    steps: usize,
+    ticks_crossed: u32,
+}
+
+impl CurrentState {
+    fn in_progress(&self, sqrt_price_limit_x96: U256) -> bool {
+        self.amount_specified_remaining != I256::ZERO && self.sqrt_price_x96 != sqrt_price_limit_x96
+    }
+
+    fn prefetched_word(&self, word_pos: i16) -> Option<U256> {
+        self.prefetched_words
+            .iter()
+            .find(|(position, _)| *position == word_pos)
+            .map(|(_, word)| *word)
+    }
+
+    fn load_word(&mut self, word_pos: i16, word: U256) {
+        self.word_pos = Some(word_pos);
+        self.word = word;
+    }
+
+    // The initialized tick a computed step ended on, whose liquidity_net finish_step needs
+    fn crossed_tick(&self, step: &StepComputations) -> Option<i32> {
+        (step.initialized && self.sqrt_price_x96 == step.sqrt_price_next_x96)
+            .then_some(step.tick_next)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepComputations {
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_start_x96: U256,
+    pub tick_next: i32,
+    pub initialized: bool,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub sqrt_price_next_x96: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_in: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub amount_out: U256,
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub fee_amount: U256,
+    // Part of fee_amount taken by the protocol
+    #[cfg_attr(feature = "serde", serde(with = "serde_u256"))]
+    pub protocol_fee: U256,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ruint::uint;
+    use std::{
+        cell::{Cell, RefCell},
+        collections::HashMap,
+    };
+
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct TestProvider {
+        pub(crate) words: HashMap<i16, U256>,
+        pub(crate) liquidity_nets: HashMap<i32, i128>,
+        pub(crate) calls: Cell<usize>,
+    }
+
+    impl TestProvider {
+        fn new(ticks: &[(i32, i128)], tick_spacing: i32) -> Self {
+            let mut provider = Self::default();
+            for &(tick, liquidity_net) in ticks {
+                let (word_pos, bit_pos) = position(calculate_compressed(tick, tick_spacing));
+                *provider.words.entry(word_pos).or_default() |= RUINT_ONE << bit_pos as usize;
+                provider.liquidity_nets.insert(tick, liquidity_net);
+            }
+            provider
+        }
+    }
+
+    impl TicksProvider for TestProvider {
+        fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(self.words.get(&position).copied().unwrap_or_default())
+        }
+
+        fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(self.liquidity_nets.get(&tick).copied().unwrap_or_default())
+        }
+    }
+
+    // 0.3% pool with spacing 60 and three positions, [600, 14400] 1e18, [4200, 9000] 5e17 and
+    // [6600, 7800] 2e18, priced halfway through tick 7000. Expected amounts below were
+    // cross-checked against a big-integer model of the v3-core swap loop.
+    pub(crate) fn fixture() -> Math<TestProvider> {
+        Math {
+            fee: 3000,
+            fee_protocol: 0,
+            liquidity: 3_500_000_000_000_000_000,
+            sqrt_price_x96: uint!(112430957613446531369126327961_U256),
+            tick: 7000,
+            tick_spacing: 60,
+            provider: TestProvider::new(
+                &[
+                    (600, 1_000_000_000_000_000_000),
+                    (4200, 500_000_000_000_000_000),
+                    (6600, 2_000_000_000_000_000_000),
+                    (7800, -2_000_000_000_000_000_000),
+                    (9000, -500_000_000_000_000_000),
+                    (14400, -1_000_000_000_000_000_000),
+                ],
+                60,
+            ),
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap() {
+        let math = fixture();
+
+        //zero for one within the first range, crossing 6600
+        let result = math.simulate_swap(true, uint!(100000000000000000_U256));
+        assert_eq!(result.unwrap(), uint!(190588990397212441_U256));
+
+        //zero for one crossing 6600 and 4200
+        let result = math.simulate_swap(true, uint!(300000000000000000_U256));
+        assert_eq!(result.unwrap(), uint!(483831005796314856_U256));
+
+        //one for zero without crossing
+        let result = math.simulate_swap(false, uint!(100000000000000000_U256));
+        assert_eq!(result.unwrap(), uint!(48534558852249574_U256));
+
+        //one for zero crossing 7800 and 9000
+        let result = math.simulate_swap(false, uint!(500000000000000000_U256));
+        assert_eq!(result.unwrap(), uint!(214467136656093273_U256));
+    }
+
+    #[test]
+    fn test_simulate_swap_exact_output() {
+        let math = fixture();
+
+        //zero for one crossing 6600
+        let amount_out = uint!(200000000000000000_U256);
+        let amount_in = math.simulate_swap_exact_output(true, amount_out).unwrap();
+        assert_eq!(amount_in, uint!(105365365874132610_U256));
+        assert!(math.simulate_swap(true, amount_in).unwrap() >= amount_out);
+        assert!(math.simulate_swap(true, amount_in - RUINT_ONE).unwrap() < amount_out);
+
+        //one for zero crossing 7800 and 9000
+        let amount_in = math.simulate_swap_exact_output(false, amount_out).unwrap();
+        assert_eq!(amount_in, uint!(457765284726808090_U256));
+        assert!(math.simulate_swap(false, amount_in).unwrap() >= amount_out);
+        assert!(math.simulate_swap(false, amount_in - RUINT_ONE).unwrap() < amount_out);
+
+        //zero output needs zero input
+        let result = math.simulate_swap_exact_output(true, U256::ZERO);
+        assert_eq!(result.unwrap(), U256::ZERO);
+
+        //more token0 than the pool holds
+        let result = math.simulate_swap_exact_output(false, uint!(1000000000000000000_U256));
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::InsufficientLiquidity { requested, available }
+                if requested == uint!(1000000000000000000_U256)
+                    && available == uint!(306658635827202878_U256)
+        ));
+
+        //exactly what is available fills
+        let amount_in = math.simulate_swap_exact_output(false, uint!(306658635827202878_U256));
+        assert!(amount_in.is_ok());
+    }
+
+    #[test]
+    fn test_simulate_swap_signed() {
+        let math = fixture();
+        let amount = |value: i128| I256::try_from(value).unwrap();
+
+        //exact inputs and outputs from the reference model on the synthetic fixture pool, not Swap
+        //events from a deployed pool; the last swap drains the pool and only uses part of its input
+        let cases = [
+            (
+                true,
+                300_000_000_000_000_000,
+                300_000_000_000_000_000,
+                -483_831_005_796_314_856,
+            ),
+            (
+                false,
+                300_000_000_000_000_000,
+                -139_053_509_891_775_925,
+                300_000_000_000_000_000,
+            ),
+            (
+                true,
+                -200_000_000_000_000_000,
+                105_365_365_874_132_610,
+                -200_000_000_000_000_000,
+            ),
+            (
+                false,
+                -200_000_000_000_000_000,
+                -200_000_000_000_000_000,
+                457_765_284_726_808_090,
+            ),
+            (
+                true,
+                1_000_000_000_000_000_000,
+                348_269_550_567_467_233,
+                -537_597_771_449_200_557,
+            ),
+        ];
+        for (zero_for_one, amount_specified, amount_0, amount_1) in cases {
+            assert_eq!(
+                math.simulate_swap_signed(zero_for_one, amount(amount_specified))
+                    .unwrap(),
+                (amount(amount_0), amount(amount_1))
+            );
+        }
+
+        //same amounts as the unsigned quotes
+        assert_eq!(
+            math.simulate_swap(false, uint!(300000000000000000_U256))
+                .unwrap(),
+            uint!(139053509891775925_U256)
+        );
+        assert_eq!(
+            math.simulate_swap_exact_output(true, uint!(200000000000000000_U256))
+                .unwrap(),
+            uint!(105365365874132610_U256)
+        );
+
+        assert_eq!(
+            math.simulate_swap_signed(true, I256::ZERO).unwrap(),
+            (I256::ZERO, I256::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_price_accessors() {
+        let math = fixture();
+        assert_eq!(math.mid_sqrt_price(), math.sqrt_price_x96);
+
+        assert_eq!(math.price_at_tick(0).unwrap(), (Q192, Q192));
+        assert_eq!(
+            math.price_at_tick(7000).unwrap(),
+            (
+                U256::from_str_radix(
+                    "12640088233364602494296246888118959272486789397092762163856",
+                    10
+                )
+                .unwrap(),
+                Q192
+            )
+        );
+
+        //known prices, rounded to the nearest tick: 2 is 1.0001^6931.8
+        for (numerator, denominator, tick) in [
+            (1_u128, 1_u128, 0),
+            (2, 1, 6932),
+            (1, 2, -6932),
+            //1 wei of a 6 decimals token for 1 wei of an 18 decimals one at 3000 per unit, and back
+            (3_000_000_000, 1_000_000_000_000_000_000, -196256),
+            (1_000_000_000_000_000_000, 3_000_000_000, 196256),
+            (1, 2_000_000_000_000_000, -352337),
+            (1, 1_000_000_000_000_000_000_000_000_000_000, -690810),
+        ] {
+            let result = math.tick_for_price(U256::from(numerator), U256::from(denominator));
+            assert_eq!(result.unwrap(), tick);
+        }
+
+        //every tick's price maps back to it, including past 2^64 where the ratio needs 512 bits
+        for tick in (MIN_TICK..MAX_TICK)
+            .step_by(9973)
+            .chain([MIN_TICK, MAX_TICK - 1])
+        {
+            let (numerator, denominator) = math.price_at_tick(tick).unwrap();
+            assert_eq!(math.tick_for_price(numerator, denominator).unwrap(), tick);
+        }
+
+        let result = math.tick_for_price(U256::MAX, U256::from(1));
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SqrtPriceOutOfRange { .. }
+        ));
+        let result = math.tick_for_price(U256::from(1), U256::MAX);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SqrtPriceOutOfRange { .. }
+        ));
+        let result = math.tick_for_price(U256::from(1), U256::ZERO);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::DenominatorIsZero
+        ));
+    }
+
+    #[test]
+    fn test_quote_curve() {
+        let math = fixture();
+        let unit = U256::from(1_000_000_000_000_000_000_u128);
+        let (min_in, max_in) = (unit / U256::from(1000), unit);
+
+        for scale in [CurveScale::Linear, CurveScale::Log] {
+            for zero_for_one in [true, false] {
+                let curve = math
+                    .quote_curve(zero_for_one, min_in, max_in, 50, scale)
+                    .unwrap();
+                assert_eq!(curve.len(), 50);
+                assert_eq!(curve[0].amount_in, min_in);
+                assert_eq!(curve[49].amount_in, max_in);
+
+                for (point, next) in curve.iter().zip(&curve[1..]) {
+                    assert!(point.amount_in < next.amount_in);
+                    assert!(point.amount_out <= next.amount_out);
+                }
+                for point in &curve {
+                    let result = math
+                        .simulate_swap_with_limit(zero_for_one, point.amount_in, U256::ZERO)
+                        .unwrap();
+                    assert_eq!(point.amount_out, result.amount_out);
+                    assert_eq!(point.execution_price_x96, result.execution_price_x96);
+                    assert_eq!(point.sqrt_price_x96, result.sqrt_price_x96);
+                    assert_eq!(point.tick, result.tick);
+                }
+            }
+        }
+
+        //log spacing grows by a constant factor, 10 per 3 intervals here, up to f64 precision
+        let curve = math
+            .quote_curve(true, min_in, max_in, 10, CurveScale::Log)
+            .unwrap();
+        for (i, expected) in [(3, min_in * U256::from(10)), (6, min_in * U256::from(100))] {
+            let error = curve[i].amount_in.abs_diff(expected);
+            assert!(error * U256::from(1_000_000_000) < expected);
+        }
+
+        //a single point is min_in, a linear curve may start at zero
+        let curve = math
+            .quote_curve(true, min_in, max_in, 1, CurveScale::Log)
+            .unwrap();
+        assert_eq!(curve.len(), 1);
+        assert_eq!(curve[0].amount_in, min_in);
+        let curve = math
+            .quote_curve(true, U256::ZERO, max_in, 3, CurveScale::Linear)
+            .unwrap();
+        assert_eq!(curve[0].amount_out, U256::ZERO);
+        assert_eq!(curve[0].tick, math.tick);
+        assert_eq!(curve[1].amount_in, max_in / U256::from(2));
+
+        for (min_in, scale) in [
+            (max_in + U256::from(1), CurveScale::Linear),
+            (U256::ZERO, CurveScale::Log),
+        ] {
+            let result = math.quote_curve(true, min_in, max_in, 10, scale);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::InvalidCurveRange { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap_with_fee() {
+        let math = fixture();
+        let amount = U256::from(200_000_000_000_000_000_u128);
+
+        for zero_for_one in [true, false] {
+            //overriding with the pool's own fee changes nothing
+            assert_eq!(
+                math.simulate_swap_with_fee(zero_for_one, amount, math.fee)
+                    .unwrap(),
+                math.simulate_swap(zero_for_one, amount).unwrap()
+            );
+
+            //without fees the same input buys strictly more, and the same output costs less
+            assert!(
+                math.simulate_swap_with_fee(zero_for_one, amount, 0)
+                    .unwrap()
+                    > math.simulate_swap(zero_for_one, amount).unwrap()
+            );
+            let amount_out = U256::from(50_000_000_000_000_000_u128);
+            assert!(
+                math.simulate_swap_exact_output_with_fee(zero_for_one, amount_out, 0)
+                    .unwrap()
+                    < math
+                        .simulate_swap_exact_output(zero_for_one, amount_out)
+                        .unwrap()
+            );
+
+            //1 bps sits between the two
+            let one_bps = math
+                .simulate_swap_with_fee(zero_for_one, amount, 100)
+                .unwrap();
+            assert!(one_bps > math.simulate_swap(zero_for_one, amount).unwrap());
+            assert!(
+                one_bps
+                    < math
+                        .simulate_swap_with_fee(zero_for_one, amount, 0)
+                        .unwrap()
+            );
+        }
+        assert_eq!(math.fee, 3000);
+
+        let result = math.simulate_swap_with_fee(true, amount, 1_000_000);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::InvalidFee { fee: 1_000_000 }
+        ));
+        assert!(math.with_fee(999_999).is_ok());
+    }
+
+    #[test]
+    fn test_simulate_swap_from() {
+        let math = fixture();
+        let amount_in = U256::from(200_000_000_000_000_000_u128);
+
+        //from the pool's own state, same as simulating on the pool
+        let result = math
+            .simulate_swap_from(math.state(), true, amount_in)
+            .unwrap();
+        assert_eq!(
+            result,
+            math.simulate_swap_with_limit(true, amount_in, U256::ZERO)
+                .unwrap()
+        );
+
+        //chained hypotheticals match applying each swap to a copy of the pool
+        let chained = math
+            .simulate_swap_from(result.state(), false, amount_in)
+            .unwrap();
+        let mut moved = math.clone();
+        moved.apply_swap(&result);
+        assert_eq!(
+            chained,
+            moved
+                .simulate_swap_with_limit(false, amount_in, U256::ZERO)
+                .unwrap()
+        );
+
+        //a different liquidity without touching the pool
+        let state = SwapState {
+            liquidity: math.liquidity * 2,
+            ..math.state()
+        };
+        let mut deeper = math.clone();
+        deeper.liquidity = state.liquidity;
+        assert_eq!(
+            math.simulate_swap_from(state, true, amount_in).unwrap(),
+            deeper
+                .simulate_swap_with_limit(true, amount_in, U256::ZERO)
+                .unwrap()
+        );
+        assert_eq!(math.liquidity, fixture().liquidity);
+
+        //states the builder would reject
+        let state = SwapState {
+            tick: 7200,
+            ..math.state()
+        };
+        let result = math.simulate_swap_from(state, true, amount_in);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::TickPriceMismatch { tick: 7200, .. }
+        ));
+        let state = SwapState {
+            sqrt_price_x96: MAX_SQRT_RATIO,
+            ..math.state()
+        };
+        let result = math.simulate_swap_from(state, true, amount_in);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SqrtPriceOutOfRange { .. }
+        ));
+    }
+
+    #[test]
+    fn test_simulate_swap_amount_specified() {
+        let math = fixture();
+        let amount = |value: i128| I256::try_from(value).unwrap();
+
+        //without a limit, same as simulate_swap_signed
+        for (zero_for_one, amount_specified) in [
+            (true, 300_000_000_000_000_000),
+            (false, -200_000_000_000_000_000),
+        ] {
+            assert_eq!(
+                math.simulate_swap_amount_specified(
+                    zero_for_one,
+                    amount(amount_specified),
+                    U256::ZERO
+                )
+                .unwrap(),
+                math.simulate_swap_signed(zero_for_one, amount(amount_specified))
+                    .unwrap()
+            );
+        }
+
+        //stopped at 6600 with input left, exact input and exact output alike
+        let limit = get_sqrt_ratio_at_tick(6600).unwrap();
+        let expected = (
+            amount(50_034_871_589_563_148),
+            amount(-98_465_544_646_028_615),
+        );
+        let result =
+            math.simulate_swap_amount_specified(true, amount(300_000_000_000_000_000), limit);
+        assert_eq!(result.unwrap(), expected);
+        let result =
+            math.simulate_swap_amount_specified(true, amount(-200_000_000_000_000_000), limit);
+        assert_eq!(result.unwrap(), expected);
+
+        let result = math.simulate_swap_amount_specified(true, I256::ZERO, U256::ZERO);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::AmountSpecifiedZero
+        ));
+        for zero_for_one in [true, false] {
+            let result = math.simulate_swap_amount_specified(zero_for_one, I256::MIN, U256::ZERO);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::AmountSpecifiedOverflow
+            ));
+        }
+        let result = math.simulate_swap_signed(true, I256::MIN);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::AmountSpecifiedOverflow
+        ));
+
+        //a limit above the price can't be reached selling token0
+        let result = math.simulate_swap_amount_specified(
+            true,
+            amount(1),
+            get_sqrt_ratio_at_tick(7200).unwrap(),
+        );
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::InvalidSqrtPriceLimit
+        ));
+    }
+
+    #[test]
+    fn test_simulate_swap_with_limit() {
+        let math = fixture();
+
+        //zero for one stopped at 6600, which is still crossed
+        let limit = get_sqrt_ratio_at_tick(6600).unwrap();
+        let result = math.simulate_swap_with_limit(true, uint!(300000000000000000_U256), limit);
+        assert_eq!(
+            result.unwrap(),
+            SwapResult {
+                amount_in: uint!(50034871589563148_U256),
+                amount_out: uint!(98465544646028615_U256),
+                amount_remaining: uint!(249965128410436852_U256),
+                total_fee: uint!(150104614768690_U256),
+                protocol_fee: U256::ZERO,
+                lp_fee: uint!(150104614768690_U256),
+                fee_growth_global_delta_x128: uint!(14593701028355854058394038875888150_U256),
+                fill_status: FillStatus::PriceLimitReached,
+                ticks_crossed: 1,
+                execution_price_x96: uint!(40259473015956577667720994796_U256),
+                execution_deviation_bps: 228,
+                sqrt_price_x96: limit,
+                tick: 6599,
+                liquidity: 1_500_000_000_000_000_000,
+            }
+        );
+
+        //zero for one stopped at an uninitialized tick
+        let limit = get_sqrt_ratio_at_tick(6000).unwrap();
+        let result = math
+            .simulate_swap_with_limit(true, uint!(100000000000000000_U256), limit)
+            .unwrap();
+        assert_eq!(result.amount_out, uint!(160125471666195980_U256));
+        assert_eq!(result.amount_remaining, uint!(17025704162230026_U256));
+        assert_eq!(result.tick, 6000);
+
+        //one for zero stopped at 8400, after crossing 7800
+        let limit = get_sqrt_ratio_at_tick(8400).unwrap();
+        let result = math
+            .simulate_swap_with_limit(false, uint!(500000000000000000_U256), limit)
+            .unwrap();
+        assert_eq!(result.amount_out, uint!(126658737210739991_U256));
+        assert_eq!(result.amount_remaining, uint!(229162774948252924_U256));
+        assert_eq!(result.fill_status, FillStatus::PriceLimitReached);
+        assert_eq!(result.sqrt_price_x96, limit);
+        assert_eq!(result.tick, 8400);
+        assert_eq!(result.liquidity, 1_500_000_000_000_000_000);
+
+        //a limit that is not reached fills the full input
+        let amount_in = uint!(100000000000000000_U256);
+        let result = math
+            .simulate_swap_with_limit(false, amount_in, limit)
+            .unwrap();
+        assert_eq!(result.amount_in, amount_in);
+        assert_eq!(result.amount_remaining, U256::ZERO);
+        assert_eq!(result.fill_status, FillStatus::FullyFilled);
+        assert_eq!(
+            result.amount_out,
+            math.simulate_swap(false, amount_in).unwrap()
+        );
+
+        //without a limit only running out of liquidity stops the swap early
+        let result = math
+            .simulate_swap_with_limit(true, uint!(1000000000000000000_U256), U256::ZERO)
+            .unwrap();
+        assert_eq!(result.fill_status, FillStatus::LiquidityExhausted);
+        assert_eq!(result.amount_remaining, uint!(651730449432532767_U256));
+        assert_eq!(result.liquidity, 0);
+
+        //a zero limit falls back to the default limit
+        let amount_in = uint!(500000000000000000_U256);
+        let result = math.simulate_swap_with_limit(false, amount_in, U256::ZERO);
+        assert_eq!(
+            result.unwrap().amount_out,
+            math.simulate_swap(false, amount_in).unwrap()
+        );
+
+        //limits on the wrong side of the price or out of bounds
+        for (zero_for_one, limit) in [
+            (true, get_sqrt_ratio_at_tick(8400).unwrap()),
+            (true, math.sqrt_price_x96),
+            (true, MIN_SQRT_RATIO),
+            (false, get_sqrt_ratio_at_tick(6000).unwrap()),
+            (false, math.sqrt_price_x96),
+            (false, MAX_SQRT_RATIO),
+        ] {
+            let result = math.simulate_swap_with_limit(zero_for_one, amount_in, limit);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::InvalidSqrtPriceLimit
+            ));
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap_traced() {
+        let math = fixture();
+        let amount_in = uint!(300000000000000000_U256);
+
+        let (result, trace) = math.simulate_swap_traced(true, amount_in).unwrap();
+        assert_eq!(
+            result.amount_out,
+            math.simulate_swap(true, amount_in).unwrap()
+        );
+        assert_eq!(result.amount_remaining, U256::ZERO);
+
+        //crosses 6600 and 4200, then stops short of 600
+        let expected = [
+            (
+                6600,
+                uint!(49884766974794458_U256),
+                uint!(98465544646028615_U256),
+                uint!(150104614768690_U256),
+                3_500_000_000_000_000_000,
+                1_500_000_000_000_000_000,
+            ),
+            (
+                4200,
+                uint!(137485742402188564_U256),
+                uint!(235920107910463917_U256),
+                uint!(413698322173085_U256),
+                1_500_000_000_000_000_000,
+                1_000_000_000_000_000_000,
+            ),
+            (
+                600,
+                uint!(111729490623016977_U256),
+                uint!(149445353239822324_U256),
+                uint!(336197063058226_U256),
+                1_000_000_000_000_000_000,
+                1_000_000_000_000_000_000,
+            ),
+        ];
+        assert_eq!(trace.len(), expected.len());
+
+        let mut sqrt_price_start_x96 = math.sqrt_price_x96;
+        for (entry, (tick_next, amount_in, amount_out, fee_amount, before, after)) in
+            trace.iter().zip(expected)
+        {
+            assert_eq!(entry.step.sqrt_price_start_x96, sqrt_price_start_x96);
+            assert_eq!(entry.step.tick_next, tick_next);
+            assert!(entry.step.initialized);
+            assert_eq!(
+                entry.step.sqrt_price_next_x96,
+                get_sqrt_ratio_at_tick(tick_next).unwrap()
+            );
+            assert_eq!(entry.step.amount_in, amount_in);
+            assert_eq!(entry.step.amount_out, amount_out);
+            assert_eq!(entry.step.fee_amount, fee_amount);
+            assert_eq!(entry.liquidity_before, before);
+            assert_eq!(entry.liquidity_after, after);
+            sqrt_price_start_x96 = entry.step.sqrt_price_next_x96;
+        }
+
+        //a zero input takes no steps
+        let (result, trace) = math.simulate_swap_traced(true, U256::ZERO).unwrap();
+        assert!(trace.is_empty());
+        assert_eq!(result.sqrt_price_x96, math.sqrt_price_x96);
+    }
+
+    #[test]
+    fn test_simulate_swap_with_hook() {
+        let math = fixture();
+        let amount_in = uint!(300000000000000000_U256);
+
+        //a hook that never breaks sees the traced steps and changes nothing
+        let mut steps = vec![];
+        let result = math
+            .simulate_swap_with_hook(true, amount_in, |info| {
+                steps.push(info.clone());
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        let (traced, trace) = math.simulate_swap_traced(true, amount_in).unwrap();
+        assert_eq!(result, traced);
+        assert_eq!(steps.len(), trace.len());
+        for (info, entry) in steps.iter().zip(&trace) {
+            assert_eq!(info.sqrt_price_start_x96, entry.step.sqrt_price_start_x96);
+            assert_eq!(info.amount_in, entry.step.amount_in);
+            assert_eq!(info.amount_out, entry.step.amount_out);
+            assert_eq!(info.fee_amount, entry.step.fee_amount);
+            assert_eq!(info.liquidity, entry.liquidity_after);
+        }
+        assert!(steps[0].crossed);
+        assert_eq!(steps[0].tick, 6599);
+        assert_eq!(steps[0].liquidity, 1_500_000_000_000_000_000);
+        assert!(!steps[2].crossed);
+        assert_eq!(steps[2].sqrt_price_x96, result.sqrt_price_x96);
+
+        //stop once two ticks have been crossed
+        let mut crossed = 0;
+        let result = math
+            .simulate_swap_with_hook(true, amount_in, |info| {
+                crossed += info.crossed as usize;
+                if crossed == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(result.fill_status, FillStatus::Aborted);
+        assert_eq!(result.tick, 4199);
+        assert_eq!(result.liquidity, 1_000_000_000_000_000_000);
+        assert_eq!(
+            result.amount_in,
+            trace[..2]
+                .iter()
+                .map(|entry| entry.step.amount_in + entry.step.fee_amount)
+                .sum::<U256>()
+        );
+        assert_eq!(result.amount_remaining, amount_in - result.amount_in);
+        assert_eq!(
+            (result.amount_in, result.amount_out),
+            math.simulate_swap_to_price(get_sqrt_ratio_at_tick(4200).unwrap())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_swap_steps() {
+        let math = fixture();
+        let amount_in = uint!(300000000000000000_U256);
+        let (expected, trace) = math.simulate_swap_traced(true, amount_in).unwrap();
+
+        //stop after the first step and resume
+        let mut steps = math.swap_steps(true, u256_to_i256(amount_in));
+        assert_eq!(steps.next().unwrap().unwrap(), trace[0]);
+        let partial = steps.finish();
+        assert_eq!(partial.tick, 6599);
+        assert_eq!(
+            partial.amount_in,
+            trace[0].step.amount_in + trace[0].step.fee_amount
+        );
+        assert_eq!(partial.amount_remaining, amount_in - partial.amount_in);
+        assert_eq!(partial.fill_status, FillStatus::PriceLimitReached);
+
+        let rest = steps.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rest, trace[1..]);
+        assert!(steps.next().is_none());
+        assert_eq!(steps.finish(), expected);
+
+        //exact outputs total like simulate_swap_exact_output
+        let amount_out = uint!(200000000000000000_U256);
+        let mut steps = math.swap_steps(false, -u256_to_i256(amount_out));
+        assert_eq!(steps.by_ref().count(), 3);
+        let result = steps.finish();
+        assert_eq!(result.amount_out, amount_out);
+        assert_eq!(
+            result.amount_in,
+            math.simulate_swap_exact_output(false, amount_out).unwrap()
+        );
+        assert_eq!(result.fill_status, FillStatus::FullyFilled);
+
+        //the iterator ends after an error
+        let math = Math {
+            max_steps: 1,
+            ..math
+        };
+        let mut steps = math.swap_steps(true, u256_to_i256(amount_in));
+        assert!(steps.next().unwrap().is_ok());
+        assert!(matches!(
+            steps.next().unwrap().unwrap_err(),
+            UniswapV3MathError::MaxStepsExceeded { .. }
+        ));
+        assert!(steps.next().is_none());
+    }
+
+    #[test]
+    fn test_ticks_crossed() {
+        let math = fixture();
+
+        //3e17 crosses 6600 and 4200 and stops short of 600
+        let amount_in = uint!(300000000000000000_U256);
+        let result = math.simulate_swap_with_limit(true, amount_in, U256::ZERO);
+        assert_eq!(result.unwrap().ticks_crossed, 2);
+
+        //ending exactly on 6600 crosses it, one wei less stops before it
+        let to_tick = uint!(50034871589563148_U256);
+        let result = math.simulate_swap_with_limit(true, to_tick, U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(result.sqrt_price_x96, get_sqrt_ratio_at_tick(6600).unwrap());
+        assert_eq!(result.ticks_crossed, 1);
+        let result = math.simulate_swap_with_limit(true, to_tick - RUINT_ONE, U256::ZERO);
+        assert_eq!(result.unwrap().ticks_crossed, 0);
+
+        //uninitialized word boundaries don't count, draining up crosses 7800, 9000 and 14400
+        let amount_in = uint!(1000000000000000000_U256);
+        let result = math.simulate_swap_with_limit(false, amount_in, U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(result.fill_status, FillStatus::LiquidityExhausted);
+        assert_eq!(result.ticks_crossed, 3);
+
+        let result = math.simulate_swap_with_limit(true, U256::ZERO, U256::ZERO);
+        assert_eq!(result.unwrap().ticks_crossed, 0);
+    }
+
+    #[test]
+    fn test_execution_price() {
+        let math = fixture();
+        let amount_in = uint!(300000000000000000_U256);
+
+        //3e17 of token0 moves the price down to tick 4657, 3e17 of token1 only up to 7703
+        let result = math.simulate_swap_with_limit(true, amount_in, U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(
+            result.execution_price_x96,
+            uint!(49125517938148510212199065838_U256)
+        );
+        assert_eq!(result.execution_deviation_bps, 1992);
+        assert_eq!(
+            result.execution_price_x96,
+            math.price_impact(true, amount_in)
+                .unwrap()
+                .execution_price_x96
+        );
+
+        let result = math.simulate_swap_with_limit(false, amount_in, U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(
+            result.execution_price_x96,
+            uint!(170930232345649294025056686602_U256)
+        );
+        assert_eq!(result.execution_deviation_bps, 666);
+
+        //a small swap mostly pays the 30 bps fee, the deviation is rounded up
+        let result = math.simulate_swap_with_limit(true, uint!(1000000000000_U256), U256::ZERO);
+        assert_eq!(result.unwrap().execution_deviation_bps, 31);
+
+        //a single wei is all fee, nothing is received
+        let result = math.simulate_swap_with_limit(true, RUINT_ONE, U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(result.amount_out, U256::ZERO);
+        assert_eq!(result.execution_price_x96, U256::ZERO);
+        assert_eq!(result.execution_deviation_bps, 0);
+    }
+
+    #[test]
+    fn test_simulate_swap_total_fee() {
+        let math = fixture();
+
+        //fees are the part of the input that never reaches the pool
+        for (zero_for_one, amount_in) in [
+            (true, uint!(100000000000000000_U256)),
+            (true, uint!(300000000000000000_U256)),
+            (false, uint!(500000000000000000_U256)),
+        ] {
+            let (result, trace) = math.simulate_swap_traced(zero_for_one, amount_in).unwrap();
+            let swapped = trace
+                .iter()
+                .fold(U256::ZERO, |acc, entry| acc + entry.step.amount_in);
+            assert!(trace.len() > 1);
+            assert_eq!(result.amount_in, amount_in);
+            assert_eq!(result.total_fee, amount_in - swapped);
+        }
+
+        let (result, _) = math
+            .simulate_swap_traced(true, uint!(300000000000000000_U256))
+            .unwrap();
+        assert_eq!(result.total_fee, uint!(900000000000001_U256));
+    }
+
+    #[test]
+    fn test_protocol_fee() {
+        let mut math = fixture();
+        let zero_for_one = uint!(300000000000000000_U256);
+        let one_for_zero = uint!(500000000000000000_U256);
+
+        //with the fee switch off every fee goes to LPs
+        let result = math.simulate_swap_with_limit(true, zero_for_one, U256::ZERO);
+        let without = result.unwrap();
+        assert_eq!(without.protocol_fee, U256::ZERO);
+        assert_eq!(without.lp_fee, without.total_fee);
+
+        //1/4 of token0 fees and 1/10 of token1 fees, divided per step like the pool does
+        math.fee_protocol = 4 | (10 << 4);
+        let (result, trace) = math.simulate_swap_traced(true, zero_for_one).unwrap();
+        assert_eq!(result.protocol_fee, uint!(224999999999999_U256));
+        assert_eq!(result.lp_fee, result.total_fee - result.protocol_fee);
+        for entry in &trace {
+            assert_eq!(
+                entry.step.protocol_fee,
+                entry.step.fee_amount / uint!(4_U256)
+            );
+        }
+        //amounts and the final state do not depend on the protocol fee
+        assert_eq!(
+            SwapResult {
+                protocol_fee: U256::ZERO,
+                lp_fee: result.total_fee,
+                fee_growth_global_delta_x128: without.fee_growth_global_delta_x128,
+                ..result
+            },
+            without
+        );
+
+        let result = math.simulate_swap_with_limit(false, one_for_zero, U256::ZERO);
+        assert_eq!(result.unwrap().protocol_fee, uint!(149999999999999_U256));
+
+        math.fee_protocol = 10 | (4 << 4);
+        let result = math.simulate_swap_with_limit(true, zero_for_one, U256::ZERO);
+        assert_eq!(result.unwrap().protocol_fee, uint!(89999999999999_U256));
+        let result = math.simulate_swap_with_limit(false, one_for_zero, U256::ZERO);
+        assert_eq!(result.unwrap().protocol_fee, uint!(374999999999999_U256));
+    }
+
+    #[test]
+    fn test_fee_growth_global() {
+        let mut math = fixture();
+
+        //sum of lp fee * 2**128 / liquidity over the steps, worked out from the model on the
+        //synthetic fixture pool rather than read from feeGrowthGlobal before and after a swap
+        let result =
+            math.simulate_swap_with_limit(true, uint!(300000000000000000_U256), U256::ZERO);
+        assert_eq!(
+            result.unwrap().fee_growth_global_delta_x128,
+            uint!(222845129571195881427229964603837999_U256)
+        );
+        let result =
+            math.simulate_swap_with_limit(false, uint!(500000000000000000_U256), U256::ZERO);
+        assert_eq!(
+            result.unwrap().fee_growth_global_delta_x128,
+            uint!(315523707975448340632585676966420211_U256)
+        );
+
+        //steps through ranges without liquidity add nothing
+        let result =
+            math.simulate_swap_with_limit(true, uint!(400000000000000000_U256), U256::ZERO);
+        let result = result.unwrap();
+        assert_eq!(result.liquidity, 0);
+        assert_eq!(
+            result.fee_growth_global_delta_x128,
+            uint!(272120960323119019065375048027288179_U256)
+        );
+
+        //the protocol fee is not part of the fee growth
+        math.fee_protocol = 4;
+        let result =
+            math.simulate_swap_with_limit(true, uint!(300000000000000000_U256), U256::ZERO);
+        assert_eq!(
+            result.unwrap().fee_growth_global_delta_x128,
+            uint!(167133847178397186537100457069729874_U256)
+        );
+    }
+
+    #[test]
+    fn test_quote_both_directions() {
+        let math = fixture();
+        let amount = uint!(100000000000000000_U256);
+
+        let quote = math.quote_both_directions(amount).unwrap();
+        for (zero_for_one, side) in [(true, &quote.zero_for_one), (false, &quote.one_for_zero)] {
+            let impact = math.price_impact(zero_for_one, amount).unwrap();
+            assert_eq!(
+                side.amount_out,
+                math.simulate_swap(zero_for_one, amount).unwrap()
+            );
+            assert_eq!(side.sqrt_price_after, impact.sqrt_price_after);
+            assert_eq!(side.impact_bps, impact.impact_bps);
+        }
+        assert_eq!(quote.zero_for_one.impact_bps, 1222);
+
+        //the word holding the current tick is read once for both directions
+        let separate = fixture();
+        separate.simulate_swap(true, amount).unwrap();
+        separate.simulate_swap(false, amount).unwrap();
+        let both = fixture();
+        both.quote_both_directions(amount).unwrap();
+        assert_eq!(both.provider.calls.get() + 1, separate.provider.calls.get());
+    }
+
+    #[test]
+    fn test_quote_two_sided() {
+        let math = fixture();
+
+        //each side equals an independent simulation, for random pairs of sizes up to 2e18
+        let mut seed = 0x9e3779b97f4a7c15_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            U256::from(seed % 2_000_000_000_000_000_000)
+        };
+        for _ in 0..50 {
+            let (amount_0, amount_1) = (next(), next());
+            let quote = math.quote_two_sided(amount_0, amount_1).unwrap();
+            assert_eq!(
+                quote.zero_for_one,
+                math.simulate_swap_with_limit(true, amount_0, U256::ZERO)
+                    .unwrap()
+            );
+            assert_eq!(
+                quote.one_for_zero,
+                math.simulate_swap_with_limit(false, amount_1, U256::ZERO)
+                    .unwrap()
+            );
+            assert_eq!(quote.mid_sqrt_price_x96, math.sqrt_price_x96);
+        }
+
+        //both sides stay within word 0 and share its read
+        let (amount_0, amount_1) = (
+            uint!(100000000000000000_U256),
+            uint!(50000000000000000_U256),
+        );
+        let separate = fixture();
+        separate.simulate_swap(true, amount_0).unwrap();
+        separate.simulate_swap(false, amount_1).unwrap();
+        let both = fixture();
+        both.quote_two_sided(amount_0, amount_1).unwrap();
+        assert!(both.provider.calls.get() < separate.provider.calls.get());
+
+        //small sizes pay the fee on both sides, 0.3% each
+        let quote = math
+            .quote_two_sided(uint!(1000000000_U256), uint!(1000000000_U256))
+            .unwrap();
+        let spread_pips = quote.spread_pips.unwrap();
+        assert!((6000..6020).contains(&spread_pips), "{spread_pips}");
+        let quote = math.quote_two_sided(amount_0, amount_1).unwrap();
+        assert!(quote.spread_pips.unwrap() > spread_pips);
+
+        //nothing received on one side leaves the spread undefined
+        let quote = math.quote_two_sided(U256::ZERO, amount_1).unwrap();
+        assert_eq!(
+            quote.zero_for_one,
+            SwapResult {
+                sqrt_price_x96: math.sqrt_price_x96,
+                tick: math.tick,
+                liquidity: math.liquidity,
+                ..Default::default()
+            }
+        );
+        assert_eq!(quote.spread_pips, None);
+    }
+
+    #[test]
+    fn test_spot_price() {
+        let at = |sqrt_price_x96| Math {
+            sqrt_price_x96,
+            ..Math::<()>::default()
+        };
+
+        //USDC/WETH, USDC is token0 with 6 decimals
+        let math = at(uint!(1744244129640337381386292603617838_U256));
+        let price = math.price_token0_in_token1(6, 18);
+        assert!((price - 0.0004846803050257336).abs() < 1e-18);
+        assert_eq!(
+            math.price_token0_in_token1_decimal(6, 18).unwrap(),
+            "0.0004846803050257335883327160063504904004321876310447949473473814921024849473249488\
+             083809824164690706206841300722941960360400040086916487018400204395403538349209639213\
+             288625114728347398340702056884765625"
+        );
+
+        //WETH/USDT, WETH is token0 with 18 decimals
+        let math = at(uint!(3598751819609688046946419_U256));
+        let price = math.price_token0_in_token1(18, 6);
+        assert!((price - 2063.2156694440187).abs() < 1e-9);
+        assert!(math
+            .price_token0_in_token1_decimal(18, 6)
+            .unwrap()
+            .starts_with("2063.215669444018480018326861195031817323962984109738993362933036597"));
+
+        //the square of the largest price does not fit in 256 bits
+        let math = at(MAX_SQRT_RATIO - RUINT_ONE);
+        assert!((math.price_token0_in_token1(18, 18) / 3.402567868363881e38 - 1.0).abs() < 1e-12);
+        assert!(math
+            .price_token0_in_token1_decimal(18, 18)
+            .unwrap()
+            .starts_with("340256786836388094070642339899681172762.184831912254825631508231530591"));
+        let math = at(MIN_SQRT_RATIO);
+        assert!(math
+            .price_token0_in_token1_decimal(18, 18)
+            .unwrap()
+            .starts_with("0.000000000000000000000000000000000000002938956808774311200056"));
+
+        //whole numbers have no fractional part
+        let math = at(Q96);
+        assert_eq!(math.price_token0_in_token1(18, 18), 1.0);
+        assert_eq!(math.price_token0_in_token1_decimal(18, 18).unwrap(), "1");
+        assert_eq!(
+            math.price_token0_in_token1_decimal(6, 18).unwrap(),
+            "0.000000000001"
+        );
+        assert_eq!(
+            math.price_token0_in_token1_decimal(18, 6).unwrap(),
+            "1000000000000"
+        );
+        assert_eq!(
+            at(U256::ZERO)
+                .price_token0_in_token1_decimal(6, 18)
+                .unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_depth_chart() {
+        let math = fixture();
+
+        let points = math.depth_chart(true, 2).unwrap();
+        assert_eq!(
+            points[0],
+            DepthPoint {
+                tick: 6600,
+                sqrt_price_x96: get_sqrt_ratio_at_tick(6600).unwrap(),
+                amount_in: uint!(50034871589563148_U256),
+                amount_out: uint!(98465544646028615_U256),
+                liquidity: 3_500_000_000_000_000_000,
+            }
+        );
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].tick, 4200);
+        assert_eq!(points[1].liquidity, 1_500_000_000_000_000_000);
+
+        //600 is the last tick to the left, 7800, 9000 and 14400 to the right
+        for (zero_for_one, ticks) in [
+            (true, vec![6600, 4200, 600]),
+            (false, vec![7800, 9000, 14400]),
+        ] {
+            let points = math.depth_chart(zero_for_one, 10).unwrap();
+            assert_eq!(
+                points.iter().map(|point| point.tick).collect::<Vec<_>>(),
+                ticks
+            );
+
+            //every point costs the same as swapping to its price
+            for point in &points {
+                let to_price = math.simulate_swap_to_price(point.sqrt_price_x96).unwrap();
+                assert_eq!((point.amount_in, point.amount_out), to_price);
+            }
+        }
+
+        assert!(math.depth_chart(true, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_slippage_surface() {
+        let math = fixture();
+        let sizes = [
+            uint!(100000000000000000_U256),
+            U256::ZERO,
+            uint!(1000000000000000_U256),
+            uint!(1000000000000000000000000000000_U256),
+            uint!(500000000000000000_U256),
+            uint!(1000000000000000000000000_U256),
+            uint!(1000000000000000000_U256),
+        ];
+        let surface = math.slippage_surface(&sizes).unwrap();
+
+        for (zero_for_one, points) in [
+            (true, &surface.zero_for_one),
+            (false, &surface.one_for_zero),
+        ] {
+            assert_eq!(points.len(), sizes.len());
+            for (point, &size) in points.iter().zip(&sizes) {
+                assert_eq!(point.amount_in, size);
+                let result = math
+                    .simulate_swap_with_limit(zero_for_one, size, U256::ZERO)
+                    .unwrap();
+                assert_eq!(point.effective_price_x96, result.execution_price_x96);
+                assert_eq!(
+                    point.exhausted,
+                    result.fill_status == FillStatus::LiquidityExhausted
+                );
+                //pips round down like basis points, up to where they saturate
+                if point.impact_pips < u32::MAX {
+                    assert_eq!(
+                        point.impact_pips / 100,
+                        math.price_impact(zero_for_one, size).unwrap().impact_bps
+                    );
+                }
+            }
+
+            //impact never decreases with size
+            let mut sorted = points.clone();
+            sorted.sort_by_key(|point| point.amount_in);
+            for (point, next) in sorted.iter().zip(&sorted[1..]) {
+                assert!(point.impact_pips <= next.impact_pips);
+            }
+
+            //a zero size has no impact and no price
+            assert_eq!(points[1].impact_pips, 0);
+            assert_eq!(points[1].effective_price_x96, U256::ZERO);
+            assert!(!points[1].exhausted);
+
+            //sizes past the liquidity stop at the price bound with the same impact and price
+            assert!(points[3].exhausted && points[5].exhausted);
+            assert!(!points[2].exhausted);
+            assert_eq!(
+                points[3],
+                SurfacePoint {
+                    amount_in: sizes[3],
+                    ..points[5].clone()
+                }
+            );
+            assert_eq!(
+                points[3].impact_pips,
+                impact_pips(math.sqrt_price_x96, default_sqrt_price_limit(zero_for_one))
+            );
+            assert!(points[2].impact_pips < points[3].impact_pips);
+        }
+
+        let chart_points = surface.to_chart_points();
+        assert_eq!(chart_points.len(), 2 * sizes.len());
+        assert!(chart_points[..sizes.len()]
+            .iter()
+            .all(|point| point.zero_for_one));
+        assert_eq!(chart_points[0].size, 1e17);
+        assert_eq!(
+            chart_points[sizes.len()].impact_pips,
+            surface.one_for_zero[0].impact_pips as f64
+        );
+        assert!(chart_points[3].exhausted);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&surface).unwrap();
+            assert_eq!(
+                serde_json::from_str::<SlippageSurface>(&json).unwrap(),
+                surface
+            );
+        }
+
+        //positions mirrored around tick 0 move the price by nearly the same ratio either way, the
+        // impacts differ by at most (r - 1)^2 for a move by r
+        let math = Math {
+            liquidity: 1_500_000_000_000_000_000,
+            sqrt_price_x96: get_sqrt_ratio_at_tick(0).unwrap(),
+            tick: 0,
+            provider: TestProvider::new(
+                &[
+                    (-6000, 500_000_000_000_000_000),
+                    (-600, 1_000_000_000_000_000_000),
+                    (600, -1_000_000_000_000_000_000),
+                    (6000, -500_000_000_000_000_000),
+                ],
+                60,
+            ),
+            ..fixture()
+        };
+        let sizes = [
+            uint!(1000000000000000_U256),
+            uint!(10000000000000000_U256),
+            uint!(100000000000000000_U256),
+            uint!(1000000000000000000_U256),
+            uint!(1000000000000000000000_U256),
+        ];
+        let surface = math.slippage_surface(&sizes).unwrap();
+        for (down, up) in surface.zero_for_one.iter().zip(&surface.one_for_zero) {
+            assert_eq!(down.exhausted, up.exhausted);
+            let price_error = down.effective_price_x96.abs_diff(up.effective_price_x96);
+            assert!(price_error * U256::from(1_000_000_000) <= down.effective_price_x96);
+            let (down, up) = (down.impact_pips as u64, up.impact_pips as u64);
+            assert!(
+                down.abs_diff(up) <= up * up / PIPS_DENOMINATOR + 1,
+                "{down} {up}"
+            );
+        }
+        assert!(surface.zero_for_one[4].exhausted);
+    }
+
+    #[test]
+    fn test_simulate_swap_to_price() {
+        let math = fixture();
+
+        //down to 6600, same as a limited swap stopping there
+        let result = math.simulate_swap_to_price(get_sqrt_ratio_at_tick(6600).unwrap());
+        assert_eq!(
+            result.unwrap(),
+            (uint!(50034871589563148_U256), uint!(98465544646028615_U256))
+        );
+
+        //down to a price inside tick 4657, across 6600 and 4200
+        let result = math.simulate_swap_to_price(uint!(100000000000000000000000000000_U256));
+        assert_eq!(
+            result.unwrap(),
+            (
+                uint!(160384967119184105_U256),
+                uint!(291617140928995374_U256)
+            )
+        );
+
+        //up to 8400, across 7800
+        let limit = get_sqrt_ratio_at_tick(8400).unwrap();
+        let (amount_in, amount_out) = math.simulate_swap_to_price(limit).unwrap();
+        assert_eq!(amount_in, uint!(270837225051747076_U256));
+        assert_eq!(amount_out, uint!(126658737210739991_U256));
+
+        //amounts are rounded up in the pool's favor, so swapping the returned input reaches at
+        // least the target
+        let result = math
+            .simulate_swap_with_limit(false, amount_in, U256::ZERO)
+            .unwrap();
+        assert!(result.sqrt_price_x96 >= limit);
+        assert!(result.amount_out >= amount_out);
+
+        //the current price needs no swap
+        let result = math.simulate_swap_to_price(math.sqrt_price_x96);
+        assert_eq!(result.unwrap(), (U256::ZERO, U256::ZERO));
+
+        //targets outside [MIN_SQRT_RATIO, MAX_SQRT_RATIO)
+        for target in [MIN_SQRT_RATIO - RUINT_ONE, MAX_SQRT_RATIO, U256::MAX] {
+            let result = math.simulate_swap_to_price(target);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::SqrtPriceOutOfRange { sqrt_price_x96 } if sqrt_price_x96 == target
+            ));
+        }
+    }
+
+    #[test]
+    fn test_amount_to_move_price() {
+        let math = fixture();
+
+        //1% down lands inside tick 6899 and 1% up inside 7100, neither on an initialized tick
+        let cases = [
+            (
+                true,
+                100,
+                uint!(111867390367246184595006994567_U256),
+                uint!(12462604059655424_U256),
+            ),
+            (
+                false,
+                100,
+                uint!(112991713997865244624049470635_U256),
+                uint!(24846632081420701_U256),
+            ),
+            (
+                true,
+                1,
+                uint!(112425335925020134651684693703_U256),
+                uint!(123699840025569_U256),
+            ),
+            //across 7800 and 9000
+            (
+                false,
+                5000,
+                uint!(137699238722713771708177333472_U256),
+                uint!(510809936167259307_U256),
+            ),
+        ];
+        for (zero_for_one, bps, target, amount_in) in cases {
+            assert_eq!(
+                target_sqrt_price(math.sqrt_price_x96, zero_for_one, bps).unwrap(),
+                target
+            );
+            assert_eq!(
+                math.amount_to_move_price(zero_for_one, bps).unwrap(),
+                amount_in
+            );
+
+            //the rounding keeps the move within bps
+            assert!(impact_bps(math.sqrt_price_x96, target).unwrap() < bps);
+        }
+
+        //zero bps is the current price
+        assert_eq!(math.amount_to_move_price(true, 0).unwrap(), U256::ZERO);
+        assert_eq!(math.amount_to_move_price(false, 0).unwrap(), U256::ZERO);
+
+        //targets past the price bounds, a price can't fall by 100% and 1% up from 50 ticks below
+        // MAX_TICK is past MAX_SQRT_RATIO
+        for bps in [10_000, u32::MAX] {
+            let result = math.amount_to_move_price(true, bps);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::SqrtPriceOutOfRange { sqrt_price_x96 } if sqrt_price_x96 == U256::ZERO
+            ));
+        }
+        let math = Math {
+            sqrt_price_x96: get_sqrt_ratio_at_tick(MAX_TICK - 50).unwrap(),
+            tick: MAX_TICK - 50,
+            ..math
+        };
+        let result = math.amount_to_move_price(false, 100);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SqrtPriceOutOfRange { .. }
+        ));
+    }
+
+    #[test]
+    fn test_input_to_reach_tick() {
+        let math = fixture();
+
+        //between and onto initialized ticks in both directions
+        for target_tick in [600, 4200, 5400, 6960, 7020, 7800, 8400, 14400] {
+            let amount_in = math.input_to_reach_tick(target_tick).unwrap();
+            let zero_for_one = target_tick < math.tick;
+            let land = |amount_in| {
+                let result = math
+                    .simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO)
+                    .unwrap();
+                (
+                    result.fill_status,
+                    get_tick_at_sqrt_ratio(result.sqrt_price_x96).unwrap(),
+                )
+            };
+            assert_eq!(land(amount_in), (FillStatus::FullyFilled, target_tick));
+
+            //moving up, one wei less stops short of the target tick
+            if !zero_for_one {
+                assert!(land(amount_in - RUINT_ONE).1 < target_tick);
+            }
+        }
+        assert_eq!(
+            math.input_to_reach_tick(6600).unwrap(),
+            uint!(50034871589563148_U256)
+        );
+
+        //between 6600 and 4200 the last wei of the price walk overshoots
+        assert_eq!(
+            math.input_to_reach_tick(5400).unwrap(),
+            math.simulate_swap_to_price(get_sqrt_ratio_at_tick(5400).unwrap())
+                .unwrap()
+                .0
+                - RUINT_ONE
+        );
+
+        //beyond 600 and 14400 there is no liquidity to stop the price
+        for target_tick in [60, 20040] {
+            let result = math.input_to_reach_tick(target_tick);
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::TickNotReachable { tick } if tick == target_tick
+            ));
+        }
+
+        //the current tick needs no swap
+        let math = Math { tick: 6960, ..math };
+        assert_eq!(math.input_to_reach_tick(6960).unwrap(), U256::ZERO);
+
+        let result = math.input_to_reach_tick(6990);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::TickNotAligned {
+                tick: 6990,
+                spacing: 60
+            }
+        ));
+        let result = math.input_to_reach_tick(MIN_TICK - 1);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::TickOutOfRange { tick } if tick == MIN_TICK - 1
+        ));
+    }
+
+    #[test]
+    fn test_find_input_for_output() {
+        let math = fixture();
+        let desired_out = uint!(200000000000000000_U256);
+        let max_amount_in = uint!(1000000000000000000_U256);
+
+        //with no tolerance the search lands on the smallest filling input
+        for zero_for_one in [true, false] {
+            let exact = math
+                .simulate_swap_exact_output(zero_for_one, desired_out)
+                .unwrap();
+            let result =
+                math.find_input_for_output(zero_for_one, desired_out, U256::ZERO, max_amount_in);
+            assert_eq!(result.unwrap(), exact);
+        }
+
+        //a tolerance trades precision for fewer probes but still fills
+        let tolerance = uint!(1000000_U256);
+        let amount_in = math
+            .find_input_for_output(true, desired_out, tolerance, max_amount_in)
+            .unwrap();
+        let exact = uint!(105365365874132610_U256);
+        assert!(amount_in >= exact && amount_in - exact <= tolerance);
+        assert!(math.simulate_swap(true, amount_in).unwrap() >= desired_out);
+
+        //every probe walks a prefix of the max_amount_in path, so the whole search reads no more
+        // than a single simulation of max_amount_in
+        math.provider.calls.set(0);
+        math.simulate_swap(true, max_amount_in).unwrap();
+        let single = math.provider.calls.replace(0);
+        math.find_input_for_output(true, desired_out, U256::ZERO, max_amount_in)
+            .unwrap();
+        assert!(math.provider.calls.get() <= single);
+
+        //zero output needs zero input
+        let result = math.find_input_for_output(true, U256::ZERO, U256::ZERO, U256::ZERO);
+        assert_eq!(result.unwrap(), U256::ZERO);
+
+        //the maximum input is not enough
+        let result = math.find_input_for_output(true, desired_out, U256::ZERO, exact - RUINT_ONE);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::OutputNotReachable
+        ));
+    }
+
+    #[test]
+    fn test_price_impact() {
+        let math = fixture();
+
+        //zero for one across 6600
+        let result = math
+            .price_impact(true, uint!(100000000000000000_U256))
+            .unwrap();
+        assert_eq!(
+            result,
+            PriceImpact {
+                sqrt_price_before: math.sqrt_price_x96,
+                sqrt_price_after: uint!(105336183152565106141868868423_U256),
+                impact_bps: 1222,
+                execution_price_x96: uint!(41570167484041161350608850957_U256),
+            }
+        );
+
+        //one for zero across 7800 and 9000
+        let result = math
+            .price_impact(false, uint!(500000000000000000_U256))
+            .unwrap();
+        assert_eq!(
+            result.sqrt_price_after,
+            uint!(136845356697423612935110424544_U256)
+        );
+        assert_eq!(result.impact_bps, 4814);
+        assert_eq!(
+            result.execution_price_x96,
+            uint!(184709330645165231749755434060_U256)
+        );
+
+        //draining the pool to the price bound prices only the consumed input
+        let result = math
+            .price_impact(true, uint!(1000000000000000000_U256))
+            .unwrap();
+        assert_eq!(result.sqrt_price_after, MIN_SQRT_RATIO + RUINT_ONE);
+        assert_eq!(result.impact_bps, 9999);
+        assert_eq!(
+            result.execution_price_x96,
+            uint!(51326024802423179434185626528_U256)
+        );
+
+        //the upper bound saturates the impact
+        let result = math
+            .price_impact(false, uint!(1000000000000000000000000000000_U256))
+            .unwrap();
+        assert_eq!(result.sqrt_price_after, MAX_SQRT_RATIO - RUINT_ONE);
+        assert_eq!(result.impact_bps, u32::MAX);
+
+        //no input, no impact and no execution price
+        let result = math.price_impact(true, U256::ZERO).unwrap();
+        assert_eq!(result.sqrt_price_after, result.sqrt_price_before);
+        assert_eq!(result.impact_bps, 0);
+        assert_eq!(result.execution_price_x96, U256::ZERO);
+    }
+
+    #[test]
+    fn test_simulate_swap_many() {
+        let math = fixture();
+
+        //unsorted, with duplicates, zeros, amounts ending on the same step and amounts that drain
+        // the pool into empty words
+        let amounts = [
+            uint!(300000000000000000_U256),
+            U256::ZERO,
+            uint!(1_U256),
+            uint!(100000000000000000_U256),
+            uint!(50034871589563148_U256),
+            uint!(50034871589563149_U256),
+            uint!(1000000000000000000_U256),
+            uint!(100000000000000000_U256),
+            uint!(190000000000000000_U256),
+            uint!(400000000000000000_U256),
+            uint!(500000000000000000_U256),
+            uint!(2000000000000000000_U256),
+        ];
+
+        for zero_for_one in [true, false] {
+            math.provider.calls.set(0);
+            let results = math.simulate_swap_many(zero_for_one, &amounts).unwrap();
+            let batch_calls = math.provider.calls.replace(0);
+
+            for (amount_in, result) in amounts.iter().zip(&results) {
+                let expected = math.simulate_swap(zero_for_one, *amount_in).unwrap();
+                assert_eq!(*result, expected, "{zero_for_one} {amount_in}");
+            }
+            assert!(batch_calls < math.provider.calls.get());
+        }
+
+        //the same walk as simulate_swap for a single amount
+        let results = math.simulate_swap_many(true, &[uint!(300000000000000000_U256)]);
+        assert_eq!(results.unwrap(), vec![uint!(483831005796314856_U256)]);
+
+        assert!(math.simulate_swap_many(true, &[]).unwrap().is_empty());
+        assert_eq!(
+            math.simulate_swap_many(true, &[U256::ZERO]).unwrap(),
+            vec![U256::ZERO]
+        );
+    }
+
+    #[test]
+    fn test_swap_targets_usable_ticks() {
+        for tick_spacing in [1, 10, 60, 200] {
+            let math = Math {
+                fee: 3000,
+                liquidity: 1_000_000_000_000_000_000,
+                sqrt_price_x96: Q96,
+                tick_spacing,
+                provider: TestProvider::new(
+                    &[
+                        (-100 * tick_spacing, 1_000_000_000_000_000_000),
+                        (100 * tick_spacing, -1_000_000_000_000_000_000),
+                    ],
+                    tick_spacing,
+                ),
+                max_steps: 10_000,
+                ..Default::default()
+            };
+
+            //draining the pool targets multiples of the spacing up to the usable ticks, then the
+            //price bounds to reach the limit
+            for zero_for_one in [true, false] {
+                let mut steps = math.swap_steps(zero_for_one, I256::MAX);
+                for step in &mut steps {
+                    let tick_next = step.unwrap().step.tick_next;
+                    assert!(
+                        tick_next == MIN_TICK
+                            || tick_next == MAX_TICK
+                            || (tick_next % tick_spacing == 0
+                                && (min_usable_tick(tick_spacing)..=max_usable_tick(tick_spacing))
+                                    .contains(&tick_next)),
+                        "spacing {tick_spacing} targeted {tick_next}"
+                    );
+                }
+                assert_eq!(
+                    steps.finish().state().sqrt_price_x96,
+                    default_sqrt_price_limit(zero_for_one)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_sqrt_cache() {
+        let math = fixture();
+
+        //the pool's own spacing, a finer one covering every tick it steps to and a coarser one
+        //covering only some
+        for cache_spacing in [60, 10, 200] {
+            let cache = Arc::new(SqrtRatioCache::new(cache_spacing).unwrap());
+            let cached = fixture().with_sqrt_cache(cache);
+
+            for zero_for_one in [true, false] {
+                for amount in [
+                    uint!(100000000000000000_U256),
+                    uint!(1000000000000000000_U256),
+                    U256::MAX >> 1usize,
+                ] {
+                    for amount_specified in [u256_to_i256(amount), -u256_to_i256(amount)] {
+                        assert_eq!(
+                            cached.swap_steps(zero_for_one, amount_specified).finish(),
+                            math.swap_steps(zero_for_one, amount_specified).finish(),
+                            "cache spacing {cache_spacing}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_steps() {
+        //an empty bitmap makes every step stop at a word boundary, spacing 1 needs thousands of
+        // them to reach the price bound
+        let mut math = Math {
+            fee: 3000,
+            fee_protocol: 0,
+            liquidity: 1_000_000,
+            sqrt_price_x96: get_sqrt_ratio_at_tick(0).unwrap(),
+            tick: 0,
+            tick_spacing: 1,
+            provider: TestProvider::default(),
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        };
+        let amount_in = uint!(1000000000000000000000000000000_U256);
+
+        //the default leaves room to drain the pool down to the price bound
+        let result = math.simulate_swap_with_limit(true, amount_in, U256::ZERO);
+        assert_eq!(result.unwrap().sqrt_price_x96, MIN_SQRT_RATIO + RUINT_ONE);
+        assert_eq!(math.provider.calls.replace(0), 3467);
+
+        //a smaller budget stops the walk through the empty words
+        math.max_steps = 3;
+        match math.simulate_swap(true, amount_in).unwrap_err() {
+            UniswapV3MathError::MaxStepsExceeded { steps, remaining } => {
+                assert_eq!(steps, 3);
+                assert!(remaining > I256::ZERO && remaining < u256_to_i256(amount_in));
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert_eq!(math.provider.calls.get(), 3);
+
+        //batch quotes share the limit
+        let result = math.simulate_swap_many(true, &[RUINT_ONE, amount_in]);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::MaxStepsExceeded { steps: 3, .. }
+        ));
+
+        //crossing 6600 and 4200 takes three steps
+        let mut math = fixture();
+        let amount_in = uint!(300000000000000000_U256);
+        let expected = math.simulate_swap(true, amount_in).unwrap();
+        math.max_steps = 3;
+        assert_eq!(math.simulate_swap(true, amount_in).unwrap(), expected);
+        math.max_steps = 2;
+        assert!(matches!(
+            math.simulate_swap(true, amount_in).unwrap_err(),
+            UniswapV3MathError::MaxStepsExceeded { steps: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_tick_spacing() {
+        //the public tick_spacing field is checked before the swap loop divides by it
+        for tick_spacing in [0, -60] {
+            let math = Math {
+                tick_spacing,
+                ..fixture()
+            };
+            assert!(matches!(
+                math.simulate_swap(true, uint!(100000000000000000_U256)).unwrap_err(),
+                UniswapV3MathError::InvalidTickSpacing { tick_spacing: spacing } if spacing == tick_spacing
+            ));
+        }
+    }
+
+    #[test]
+    fn test_corrupt_liquidity_net() {
+        let math = fixture();
+
+        //6600 removes more liquidity than is active when crossed to the left
+        let mut corrupt = fixture();
+        corrupt
+            .provider
+            .liquidity_nets
+            .insert(6600, 5_000_000_000_000_000_000);
+        let result = corrupt.simulate_swap(true, uint!(300000000000000000_U256));
+        match result.unwrap_err() {
+            UniswapV3MathError::LiquidityUnderflow {
+                tick,
+                liquidity,
+                liquidity_net,
+            } => {
+                assert_eq!(tick, 6600);
+                assert_eq!(liquidity, math.liquidity);
+                assert_eq!(liquidity_net, 5_000_000_000_000_000_000);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+
+        //i128::MIN is reported rather than overflowing on negation
+        let mut corrupt = fixture();
+        corrupt.provider.liquidity_nets.insert(7800, i128::MIN);
+        let result = corrupt.simulate_swap(false, uint!(500000000000000000_U256));
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::LiquidityUnderflow {
+                tick: 7800,
+                liquidity_net: i128::MIN,
+                ..
+            }
+        ));
+
+        //adding 7800's net to liquidity above 2**127 overflows u128
+        let mut corrupt = fixture();
+        corrupt.liquidity = (1 << 127) + 1;
+        corrupt.provider.liquidity_nets.insert(7800, i128::MAX);
+        let result = corrupt.simulate_swap_to_price(get_sqrt_ratio_at_tick(8000).unwrap());
+        match result.unwrap_err() {
+            UniswapV3MathError::LiquidityOverflow {
+                tick,
+                liquidity,
+                liquidity_net,
+            } => {
+                assert_eq!(tick, 7800);
+                assert_eq!(liquidity, (1 << 127) + 1);
+                assert_eq!(liquidity_net, i128::MAX);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+
+        //the same data is fine when the inconsistent tick is never crossed
+        let result = corrupt.simulate_swap_to_price(get_sqrt_ratio_at_tick(7700).unwrap());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_simulate_swap_across_word_boundary() {
+        //with spacing 10, 2550 is the last bit of word 0 and 2570 the second bit of word 1
+        let provider = TestProvider::new(
+            &[
+                (0, 1_000_000_000_000_000_000),
+                (2550, 2_000_000_000_000_000_000),
+                (2570, -2_000_000_000_000_000_000),
+                (5000, -1_000_000_000_000_000_000),
+            ],
+            10,
+        );
+        let math = |tick, sqrt_price_x96| Math {
+            fee: 3000,
+            fee_protocol: 0,
+            liquidity: 1_000_000_000_000_000_000,
+            sqrt_price_x96,
+            tick,
+            tick_spacing: 10,
+            provider: provider.clone(),
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        };
+
+        //one for zero from word 0 into word 1, across both ticks
+        let math_up = math(2000, uint!(87560223330309670419052682234_U256));
+        let (result, trace) = math_up
+            .simulate_swap_traced(false, uint!(100000000000000000_U256))
+            .unwrap();
+        assert_eq!(result.amount_out, uint!(75064645836244164_U256));
+        assert_eq!(result.tick, 3689);
+        assert_eq!(result.liquidity, 1_000_000_000_000_000_000);
+        let crossed = trace
+            .iter()
+            .filter(|entry| entry.liquidity_before != entry.liquidity_after)
+            .map(|entry| entry.step.tick_next)
+            .collect::<Vec<_>>();
+        assert_eq!(crossed, vec![2550, 2570]);
+
+        //zero for one from word 1 back into word 0
+        let math_down = math(3000, uint!(92049301871182272007977915190_U256));
+        let result = math_down.simulate_swap(true, uint!(100000000000000000_U256));
+        assert_eq!(result.unwrap(), uint!(120970158546685178_U256));
+    }
+
+    // Reads words in batches and records the shape of every call
+    struct BatchProvider {
+        inner: TestProvider,
+        words_per_batch: usize,
+        batches: RefCell<Vec<Vec<i16>>>,
+    }
+
+    impl TicksProvider for BatchProvider {
+        fn get_word_at_position(&self, _: i16) -> Result<U256, UniswapV3MathError> {
+            panic!("words should be read in batches");
+        }
+
+        fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+            self.inner.get_liquidity_net_at_tick(tick)
+        }
+
+        fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+            self.batches.borrow_mut().push(positions.to_vec());
+            positions
+                .iter()
+                .map(|position| self.inner.get_word_at_position(*position))
+                .collect()
+        }
+
+        fn words_per_batch(&self) -> usize {
+            self.words_per_batch
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap_batched_words() {
+        let math = fixture();
+        let batched = |words_per_batch| Math {
+            fee: math.fee,
+            fee_protocol: math.fee_protocol,
+            liquidity: math.liquidity,
+            sqrt_price_x96: math.sqrt_price_x96,
+            tick: math.tick,
+            tick_spacing: math.tick_spacing,
+            provider: BatchProvider {
+                inner: math.provider.clone(),
+                words_per_batch,
+                batches: RefCell::default(),
+            },
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        };
+
+        //draining the pool walks every word down to MIN_TICK, or up to MAX_TICK
+        let amounts = [
+            uint!(100000000000000000_U256),
+            uint!(500000000000000000_U256),
+            uint!(1000000000000000000_U256),
+        ];
+
+        for zero_for_one in [true, false] {
+            for amount_in in amounts {
+                let expected = math.simulate_swap(zero_for_one, amount_in).unwrap();
+
+                let batched_math = batched(4);
+                let result = batched_math.simulate_swap(zero_for_one, amount_in);
+                assert_eq!(result.unwrap(), expected);
+
+                //each batch starts at the next unread word and continues in the swap direction
+                let batches = batched_math.provider.batches.borrow();
+                let mut next = None;
+                for batch in batches.iter() {
+                    assert!(!batch.is_empty() && batch.len() <= 4);
+                    if let Some(next) = next {
+                        assert_eq!(batch[0], next);
+                    }
+                    for pair in batch.windows(2) {
+                        let step = if zero_for_one { -1 } else { 1 };
+                        assert_eq!(pair[1], pair[0] + step);
+                    }
+                    next = Some(batch[batch.len() - 1] + if zero_for_one { -1 } else { 1 });
+                }
+            }
+        }
+
+        //the full walk down reads the 59 words from 0 to the MIN_TICK word 4 at a time
+        let batched_math = batched(4);
+        batched_math
+            .simulate_swap(true, uint!(1000000000000000000_U256))
+            .unwrap();
+        let batches = batched_math.provider.batches.borrow();
+        assert_eq!(batches.len(), 15);
+        assert_eq!(batches[14], vec![-56, -57, -58]);
+        assert_eq!(
+            position(calculate_compressed(MIN_TICK, math.tick_spacing)).0,
+            -58
+        );
+    }
+
+    #[test]
+    fn test_provider_wrappers() {
+        let math = fixture();
+        let amount_in = uint!(10000000000000000000_U256);
+        let expected = math.simulate_swap(true, amount_in).unwrap();
+
+        let borrowed: Math<&TestProvider> = math.with_provider(&math.provider);
+        assert_eq!(borrowed.simulate_swap(true, amount_in).unwrap(), expected);
+
+        //TestProvider counts calls through a Cell, so share the same ticks from a Sync provider
+        let shared = Arc::new(providers::MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+        let arc: Math<Arc<providers::MemoryTicksProvider>> = math.with_provider(shared.clone());
+        assert_eq!(arc.simulate_swap(true, amount_in).unwrap(), expected);
+
+        let boxed: Math<Box<TestProvider>> = math.with_provider(Box::new(fixture().provider));
+        assert_eq!(boxed.simulate_swap(true, amount_in).unwrap(), expected);
+
+        let dynamic: Math<Box<dyn TicksProvider>> =
+            math.with_provider(Box::new(fixture().provider));
+        assert_eq!(dynamic.simulate_swap(true, amount_in).unwrap(), expected);
+        assert_eq!(
+            dynamic
+                .simulate_swap_exact_output(true, uint!(200000000000000000_U256))
+                .unwrap(),
+            uint!(105365365874132610_U256)
+        );
+    }
+
+    #[test]
+    fn test_provider_error_round_trip() {
+        #[derive(Debug, PartialEq)]
+        struct RequestFailed {
+            request_id: u64,
+        }
+
+        impl std::fmt::Display for RequestFailed {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "request {} failed", self.request_id)
+            }
+        }
+
+        impl std::error::Error for RequestFailed {}
+
+        struct FailingProvider;
+
+        impl TicksProvider for FailingProvider {
+            fn get_word_at_position(&self, _position: i16) -> Result<U256, UniswapV3MathError> {
+                Err(UniswapV3MathError::provider(RequestFailed {
+                    request_id: 42,
+                }))
+            }
+
+            fn get_liquidity_net_at_tick(&self, _tick: i32) -> Result<i128, UniswapV3MathError> {
+                Ok(0)
+            }
+        }
+
+        let math = fixture().with_provider(FailingProvider);
+
+        let err = math
+            .simulate_swap(true, uint!(1000000000000000000_U256))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Provider error: request 42 failed");
+        let UniswapV3MathError::Provider(source) = err else {
+            panic!("expected a provider error, got {err:?}");
+        };
+        assert_eq!(
+            source.downcast_ref::<RequestFailed>(),
+            Some(&RequestFailed { request_id: 42 })
+        );
+    }
+
+    #[test]
+    fn test_apply_swap() {
+        //pairs that cross ticks without draining the pool in either direction
+        let amounts = [
+            (
+                uint!(100000000000000000_U256),
+                uint!(150000000000000000_U256),
+            ),
+            (
+                uint!(12345678901234567_U256),
+                uint!(250000000000000000_U256),
+            ),
+            (uint!(1_U256), uint!(200000000000000000_U256)),
+        ];
+
+        for zero_for_one in [true, false] {
+            for (a, b) in amounts {
+                let combined = fixture()
+                    .simulate_swap_with_limit(zero_for_one, a + b, U256::ZERO)
+                    .unwrap();
+
+                let mut math = fixture();
+                let first = math
+                    .simulate_swap_with_limit(zero_for_one, a, U256::ZERO)
+                    .unwrap();
+                math.apply_swap(&first);
+                assert_eq!(math.sqrt_price_x96, first.sqrt_price_x96);
+                assert_eq!(math.tick, first.tick);
+                assert_eq!(math.liquidity, first.liquidity);
+                let second = math
+                    .simulate_swap_with_limit(zero_for_one, b, U256::ZERO)
+                    .unwrap();
+
+                //splitting a swap only loses to rounding, which favours the pool
+                let split_out = first.amount_out + second.amount_out;
+                assert!(split_out <= combined.amount_out);
+                assert!(combined.amount_out - split_out <= uint!(2_U256));
+                assert_eq!(second.liquidity, combined.liquidity);
+                assert_eq!(second.tick, combined.tick);
+            }
+        }
+    }
+
+    //amounts from the mint and burn cases of the v3-core pool tests, on a pool initialized at a
+    //price of 1:10 with the medium fee tier; these are unit test expectations, not Mint or Burn
+    //events from a deployed pool
+    fn mint_test_pool() -> Math<()> {
+        Math {
+            fee: 3000,
+            fee_protocol: 0,
+            liquidity: 0,
+            sqrt_price_x96: uint!(25054144837504793118641380156_U256),
+            tick: -23028,
+            tick_spacing: 60,
+            provider: (),
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        }
+    }
+
+    #[test]
+    fn test_simulate_mint() {
+        let mut math = mint_test_pool();
+        let (min_tick, max_tick) = (-887220, 887220);
+
+        let initial = math.simulate_mint(min_tick, max_tick, 3161).unwrap();
+        assert_eq!(initial.amount_0, uint!(9996_U256));
+        assert_eq!(initial.amount_1, uint!(1000_U256));
+        assert_eq!(initial.liquidity, 3161);
+        math.liquidity = initial.liquidity;
+
+        //above the current price, token0 only
+        let above = math.simulate_mint(-22980, 0, 10000).unwrap();
+        assert_eq!(above.amount_0, uint!(21549_U256));
+        assert_eq!(above.amount_1, U256::ZERO);
+        assert_eq!(above.liquidity, 3161);
+
+        let above = math
+            .simulate_mint(max_tick - 60, max_tick, 1 << 102)
+            .unwrap();
+        assert_eq!(above.amount_0, uint!(828011525_U256));
+        assert_eq!(above.amount_1, U256::ZERO);
+
+        //below the current price, token1 only
+        let below = math.simulate_mint(-46080, -23040, 10000).unwrap();
+        assert_eq!(below.amount_0, U256::ZERO);
+        assert_eq!(below.amount_1, uint!(2162_U256));
+        assert_eq!(below.liquidity, 3161);
+
+        let below = math
+            .simulate_mint(min_tick, min_tick + 60, 1 << 102)
+            .unwrap();
+        assert_eq!(below.amount_0, U256::ZERO);
+        assert_eq!(below.amount_1, uint!(828011520_U256));
+
+        //including the current price, both tokens and the active liquidity grows
+        let inside = math
+            .simulate_mint(min_tick + 60, max_tick - 60, 100)
+            .unwrap();
+        assert_eq!(inside.amount_0, uint!(317_U256));
+        assert_eq!(inside.amount_1, uint!(32_U256));
+        assert_eq!(inside.liquidity, 3261);
+
+        assert!(matches!(
+            math.simulate_mint(60, 60, 1),
+            Err(UniswapV3MathError::InvalidTickRange {
+                tick_lower: 60,
+                tick_upper: 60
+            })
+        ));
+        assert!(matches!(
+            math.simulate_mint(-30, 60, 1),
+            Err(UniswapV3MathError::TickNotAligned {
+                tick: -30,
+                spacing: 60
+            })
+        ));
+        assert!(matches!(
+            math.simulate_mint(min_tick - 60, 0, 1),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick == min_tick - 60
+        ));
+    }
+
+    #[test]
+    fn test_simulate_burn() {
+        let mut math = mint_test_pool();
+        math.liquidity = 3161;
+
+        //burns round down where mints round up
+        let burn = math.simulate_burn(-240, 0, 10000).unwrap();
+        assert_eq!(burn.amount_0, uint!(120_U256));
+        assert_eq!(burn.amount_1, U256::ZERO);
+        assert_eq!(
+            math.simulate_mint(-240, 0, 10000).unwrap().amount_0,
+            uint!(121_U256)
+        );
+
+        let burn = math.simulate_burn(-887220, 887220, 3161).unwrap();
+        assert_eq!(burn.amount_0, uint!(9995_U256));
+        assert_eq!(burn.amount_1, uint!(999_U256));
+        assert_eq!(burn.liquidity, 0);
+
+        assert!(matches!(
+            math.simulate_burn(-887220, 887220, 3162),
+            Err(UniswapV3MathError::LiquiditySub)
+        ));
+    }
+
+    #[test]
+    fn test_skip_empty_words() {
+        //reports the words holding initialized ticks, which lets the swap skip the rest
+        struct Hinted(TestProvider);
+
+        impl TicksProvider for Hinted {
+            fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+                self.0.get_word_at_position(position)
+            }
+
+            fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+                self.0.get_liquidity_net_at_tick(tick)
+            }
+
+            fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+                let positions = self.0.words.keys();
+                Some(*positions.clone().min().unwrap()..=*positions.max().unwrap())
+            }
+        }
+
+        //a single position, the price starting inside it or above it
+        for (tick_spacing, tick) in [(1, 1100), (1, 5000), (60, 1140), (60, -3000)] {
+            let liquidity = if tick < 1200 {
+                1_000_000_000_000_000_000
+            } else {
+                0
+            };
+            let ticks = [
+                (960, 1_000_000_000_000_000_000),
+                (1200, -1_000_000_000_000_000_000),
+            ];
+            let math = Math {
+                fee: 3000,
+                fee_protocol: 0,
+                liquidity: if tick < 960 { 0 } else { liquidity },
+                sqrt_price_x96: get_sqrt_ratio_at_tick(tick).unwrap(),
+                tick,
+                tick_spacing,
+                provider: TestProvider::new(&ticks, tick_spacing),
+                max_steps: 100_000,
+                sqrt_ratio_cache: None,
+                occupancy: None,
+            };
+            let hinted = math.with_provider(Hinted(TestProvider::new(&ticks, tick_spacing)));
+
+            let limits = |zero_for_one| {
+                [
+                    default_sqrt_price_limit(zero_for_one),
+                    //on a word boundary and inside a word, past the position either way
+                    get_sqrt_ratio_at_tick(if zero_for_one { -256 * 120 } else { 256 * 120 })
+                        .unwrap(),
+                    get_sqrt_ratio_at_tick(if zero_for_one { -40017 } else { 40017 }).unwrap()
+                        + RUINT_ONE,
+                ]
+            };
+            for zero_for_one in [true, false] {
+                for limit in limits(zero_for_one) {
+                    if (zero_for_one && limit >= math.sqrt_price_x96)
+                        || (!zero_for_one && limit <= math.sqrt_price_x96)
+                    {
+                        continue;
+                    }
+                    math.provider.calls.set(0);
+                    hinted.provider.0.calls.set(0);
+
+                    let amount_in = uint!(1000000000000000000000_U256);
+                    let expected = math.simulate_swap_with_limit(zero_for_one, amount_in, limit);
+                    let result = hinted.simulate_swap_with_limit(zero_for_one, amount_in, limit);
+                    assert_eq!(result.unwrap(), expected.unwrap());
+                    assert!(hinted.provider.0.calls.get() <= math.provider.calls.get());
+                }
+            }
+        }
+
+        //draining at spacing 1 walks every empty word down to MIN_TICK without the hint, and
+        //stops after the two words holding the position and the crossed tick with it
+        let math = Math {
+            fee: 3000,
+            fee_protocol: 0,
+            liquidity: 1_000_000_000_000_000_000,
+            sqrt_price_x96: get_sqrt_ratio_at_tick(1100).unwrap(),
+            tick: 1100,
+            tick_spacing: 1,
+            provider: TestProvider::new(
+                &[
+                    (960, 1_000_000_000_000_000_000),
+                    (1200, -1_000_000_000_000_000_000),
+                ],
+                1,
+            ),
+            max_steps: 100_000,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        };
+        math.simulate_swap(true, uint!(1000000000000000000000_U256))
+            .unwrap();
+        assert_eq!(math.provider.calls.get(), 3472);
+        let hinted = math.with_provider(Hinted(math.provider.clone()));
+        hinted.provider.0.calls.set(0);
+        hinted
+            .simulate_swap(true, uint!(1000000000000000000000_U256))
+            .unwrap();
+        assert_eq!(hinted.provider.0.calls.get(), 3);
+    }
 }
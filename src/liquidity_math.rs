@@ -3,7 +3,7 @@ use crate::error::UniswapV3MathError;
 // returns (uint128 z)
 pub fn add_delta(x: u128, y: i128) -> Result<u128, UniswapV3MathError> {
     if y < 0 {
-        let z = x.overflowing_sub(-y as u128);
+        let z = x.overflowing_sub(y.unsigned_abs());
 
         if z.1 {
             Err(UniswapV3MathError::LiquiditySub)
@@ -20,10 +20,31 @@ pub fn add_delta(x: u128, y: i128) -> Result<u128, UniswapV3MathError> {
     }
 }
 
+// Applies the liquidity_net of a crossed tick in the direction of the swap. The pool negates
+// liquidity_net when moving left (zero_for_one) before calling addDelta; here the direction only
+// selects between add and sub on the magnitude so i128::MIN never has to be negated.
+pub fn apply_liquidity_net(
+    liquidity: u128,
+    liquidity_net: i128,
+    zero_for_one: bool,
+) -> Result<u128, UniswapV3MathError> {
+    let delta = liquidity_net.unsigned_abs();
+
+    if (liquidity_net < 0) != zero_for_one {
+        liquidity
+            .checked_sub(delta)
+            .ok_or(UniswapV3MathError::LiquiditySub)
+    } else {
+        liquidity
+            .checked_add(delta)
+            .ok_or(UniswapV3MathError::LiquidityAdd)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
-    use crate::liquidity_math::add_delta;
+    use crate::liquidity_math::{add_delta, apply_liquidity_net};
 
     #[test]
     fn test_add_delta() {
@@ -50,5 +71,55 @@ mod test {
         // 3 + -4 underflows
         let result = add_delta(3, -4);
         assert_eq!(result.err().unwrap().to_string(), "Liquidity Sub");
+
+        // i128::MIN does not overflow on negation
+        let result = add_delta(1 << 127, i128::MIN);
+        assert_eq!(result.unwrap(), 0);
+
+        let result = add_delta((1 << 127) - 1, i128::MIN);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Sub");
+
+        // i128::MAX fits as long as the result stays within u128
+        let result = add_delta(1 << 127, i128::MAX);
+        assert_eq!(result.unwrap(), u128::MAX);
+
+        let result = add_delta((1 << 127) + 1, i128::MAX);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Add");
+    }
+
+    #[test]
+    fn test_apply_liquidity_net() {
+        // moving right adds liquidity_net, moving left subtracts it
+        assert_eq!(apply_liquidity_net(10, 5, false).unwrap(), 15);
+        assert_eq!(apply_liquidity_net(10, 5, true).unwrap(), 5);
+        assert_eq!(apply_liquidity_net(10, -5, false).unwrap(), 5);
+        assert_eq!(apply_liquidity_net(10, -5, true).unwrap(), 15);
+
+        // i128::MIN is applied as a 2**127 magnitude in both directions
+        assert_eq!(apply_liquidity_net(1 << 127, i128::MIN, false).unwrap(), 0);
+        assert_eq!(
+            apply_liquidity_net((1 << 127) - 1, i128::MIN, true).unwrap(),
+            u128::MAX
+        );
+        let result = apply_liquidity_net(1 << 127, i128::MIN, true);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Add");
+        let result = apply_liquidity_net((1 << 127) - 1, i128::MIN, false);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Sub");
+
+        // i128::MIN + 1 and i128::MAX share the same magnitude
+        assert_eq!(
+            apply_liquidity_net(u128::MAX, i128::MIN + 1, false).unwrap(),
+            1 << 127
+        );
+        assert_eq!(
+            apply_liquidity_net(u128::MAX, i128::MAX, true).unwrap(),
+            1 << 127
+        );
+        let result = apply_liquidity_net(1 << 127, i128::MAX, false);
+        assert_eq!(result.unwrap(), u128::MAX);
+        let result = apply_liquidity_net((1 << 127) + 1, i128::MAX, false);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Add");
+        let result = apply_liquidity_net(0, i128::MAX, true);
+        assert_eq!(result.err().unwrap().to_string(), "Liquidity Sub");
     }
 }
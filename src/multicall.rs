@@ -0,0 +1,215 @@
+// Multicall3 aggregate3 calls to a pool's tickBitmap and ticks getters, for providers that read many
+// words or liquidity nets in a single eth_call. Only the ABI encoding lives here, sending the call
+// is up to the provider.
+
+use alloy_primitives::{address, Address, I256};
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, utils::u256_to_i256};
+
+// Multicall3, deployed at the same address on most chains
+pub const MULTICALL3: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+// aggregate3((address,bool,bytes)[])
+const AGGREGATE3_SELECTOR: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+// tickBitmap(int16)
+const TICK_BITMAP_SELECTOR: [u8; 4] = [0x53, 0x39, 0xc2, 0x96];
+// ticks(int24)
+const TICKS_SELECTOR: [u8; 4] = [0xf3, 0x0d, 0xba, 0x93];
+
+// Calldata of pool.tickBitmap(word_pos)
+pub fn tick_bitmap_call(word_pos: i16) -> Vec<u8> {
+    signed_call(TICK_BITMAP_SELECTOR, word_pos.into())
+}
+
+// Calldata of pool.ticks(tick)
+pub fn ticks_call(tick: i32) -> Vec<u8> {
+    signed_call(TICKS_SELECTOR, tick)
+}
+
+// Calldata of aggregate3 running every call against target with allowFailure set, so that
+// decode_aggregate3 can tell which call failed
+pub fn encode_aggregate3(target: Address, calls: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = AGGREGATE3_SELECTOR.to_vec();
+    push_word(&mut data, U256::from(32));
+    push_word(&mut data, U256::from(calls.len()));
+
+    // Offsets of the (target, allowFailure, callData) tuples from the end of the array length
+    let mut offset = calls.len() * 32;
+    for call in calls {
+        push_word(&mut data, U256::from(offset));
+        offset += 4 * 32 + padded_len(call.len());
+    }
+
+    for call in calls {
+        push_word(&mut data, U256::from_be_slice(target.as_slice()));
+        push_word(&mut data, U256::from(1));
+        push_word(&mut data, U256::from(3 * 32));
+        push_word(&mut data, U256::from(call.len()));
+        data.extend_from_slice(call);
+        data.resize(data.len() + padded_len(call.len()) - call.len(), 0);
+    }
+
+    data
+}
+
+// Return data of each call out of aggregate3's (bool success, bytes returnData)[], in call order.
+// Fails with MulticallFailed for the first call that reverted and with OnchainProvider if data
+// isn't a well formed result.
+pub fn decode_aggregate3(data: &[u8]) -> Result<Vec<Vec<u8>>, UniswapV3MathError> {
+    let array = offset_at(data, 0, 0)?;
+    let len = usize_at(data, array)?;
+    let elements = array + 32;
+
+    (0..len)
+        .map(|index| {
+            let element = offset_at(data, elements, elements + index * 32)?;
+            if word_at(data, element)? == U256::ZERO {
+                return Err(UniswapV3MathError::MulticallFailed { index });
+            }
+
+            let bytes = offset_at(data, element, element + 32)?;
+            let len = usize_at(data, bytes)?;
+            (bytes + 32)
+                .checked_add(len)
+                .and_then(|end| data.get(bytes + 32..end))
+                .map(<[u8]>::to_vec)
+                .ok_or(UniswapV3MathError::OnchainProvider)
+        })
+        .collect()
+}
+
+// The word returned by tickBitmap
+pub fn decode_tick_bitmap(data: &[u8]) -> Result<U256, UniswapV3MathError> {
+    word_at(data, 0)
+}
+
+// liquidityNet out of the Tick.Info returned by ticks, its second value
+pub fn decode_liquidity_net(data: &[u8]) -> Result<i128, UniswapV3MathError> {
+    let liquidity_net = u256_to_i256(word_at(data, 32)?);
+    i128::try_from(liquidity_net).map_err(|_| UniswapV3MathError::OnchainProvider)
+}
+
+fn signed_call(selector: [u8; 4], argument: i32) -> Vec<u8> {
+    let mut data = selector.to_vec();
+    data.extend_from_slice(&I256::try_from(argument).unwrap().to_be_bytes::<32>());
+    data
+}
+
+fn push_word(data: &mut Vec<u8>, word: U256) {
+    data.extend_from_slice(&word.to_be_bytes::<32>());
+}
+
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(32) * 32
+}
+
+fn word_at(data: &[u8], at: usize) -> Result<U256, UniswapV3MathError> {
+    at.checked_add(32)
+        .and_then(|end| data.get(at..end))
+        .map(U256::from_be_slice)
+        .ok_or(UniswapV3MathError::OnchainProvider)
+}
+
+fn usize_at(data: &[u8], at: usize) -> Result<usize, UniswapV3MathError> {
+    usize::try_from(word_at(data, at)?).map_err(|_| UniswapV3MathError::OnchainProvider)
+}
+
+// The offset stored at `at`, from base
+fn offset_at(data: &[u8], base: usize, at: usize) -> Result<usize, UniswapV3MathError> {
+    base.checked_add(usize_at(data, at)?)
+        .ok_or(UniswapV3MathError::OnchainProvider)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::hex;
+
+    use super::*;
+
+    const POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+    #[test]
+    fn test_encode_aggregate3() {
+        //encoded independently with a reference ABI encoder
+        let expected = hex::decode(concat!(
+            "82ad56cb",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "0000000000000000000000000000000000000000000000000000000000000100",
+            "00000000000000000000000088e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000060",
+            "0000000000000000000000000000000000000000000000000000000000000024",
+            "5339c296ffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "ffffffff00000000000000000000000000000000000000000000000000000000",
+            "00000000000000000000000088e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000060",
+            "0000000000000000000000000000000000000000000000000000000000000024",
+            "f30dba93ffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "fff2764c00000000000000000000000000000000000000000000000000000000",
+        ))
+        .unwrap();
+
+        let calls = [tick_bitmap_call(-1), ticks_call(-887220)];
+        assert_eq!(encode_aggregate3(POOL, &calls), expected);
+        assert_eq!(encode_aggregate3(POOL, &[]).len(), 4 + 2 * 32);
+    }
+
+    #[test]
+    fn test_decode_aggregate3() {
+        //(true, tickBitmap's word) and (true, ticks' Tick.Info with liquidityNet -5e17)
+        let mut data = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "00000000000000000000000000000000000000000000000000000000000000c0",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000100000000000000000000000000000000000000000000000005",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000040",
+            "0000000000000000000000000000000000000000000000000000000000000100",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "fffffffffffffffffffffffffffffffffffffffffffffffff90fa4a62c4e0000",
+        ))
+        .unwrap();
+        data.resize(data.len() + 6 * 32, 0);
+
+        let results = decode_aggregate3(&data).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            decode_tick_bitmap(&results[0]).unwrap(),
+            U256::from(1) << 200usize | U256::from(5)
+        );
+        assert_eq!(
+            decode_liquidity_net(&results[1]).unwrap(),
+            -500_000_000_000_000_000
+        );
+
+        //a reverted call is reported by index
+        let mut failed = data.clone();
+        failed[8 * 32 + 31] = 0;
+        assert!(matches!(
+            decode_aggregate3(&failed),
+            Err(UniswapV3MathError::MulticallFailed { index: 1 })
+        ));
+
+        //truncated or out of range offsets are rejected instead of panicking
+        for len in [0, 31, 4 * 32, data.len() - 1] {
+            assert!(matches!(
+                decode_aggregate3(&data[..len]),
+                Err(UniswapV3MathError::OnchainProvider)
+            ));
+        }
+        let mut bad_offset = data.clone();
+        bad_offset[2 * 32..3 * 32].fill(0xff);
+        assert!(matches!(
+            decode_aggregate3(&bad_offset),
+            Err(UniswapV3MathError::OnchainProvider)
+        ));
+    }
+}
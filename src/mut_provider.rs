@@ -0,0 +1,235 @@
+use std::ops::RangeInclusive;
+
+use alloy_primitives::I256;
+use reth_primitives::U256;
+
+use crate::{
+    default_sqrt_price_limit,
+    error::UniswapV3MathError,
+    utils::{i256_to_u256, u256_to_i256},
+    CurrentState, Math, TicksProvider,
+};
+
+// Counterpart of TicksProvider whose reads take &mut self, for providers that memoize or record
+// what they read without a RwLock or RefCell. Every TicksProvider is one, so the _mut simulations
+// accept existing providers unchanged. Moving a provider over means implementing this trait in
+// place of TicksProvider and calling simulate_swap_mut and simulate_swap_exact_output_mut on a
+// mutable Math; the other simulations keep requiring TicksProvider.
+pub trait TicksProviderMut {
+    fn get_word_at_position(&mut self, position: i16) -> Result<U256, UniswapV3MathError>;
+
+    fn get_liquidity_net_at_tick(&mut self, tick: i32) -> Result<i128, UniswapV3MathError>;
+
+    // Same as TicksProvider::initialized_word_range
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        None
+    }
+}
+
+impl<T: TicksProvider + ?Sized> TicksProviderMut for T {
+    fn get_word_at_position(&mut self, position: i16) -> Result<U256, UniswapV3MathError> {
+        TicksProvider::get_word_at_position(self, position)
+    }
+
+    fn get_liquidity_net_at_tick(&mut self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        TicksProvider::get_liquidity_net_at_tick(self, tick)
+    }
+
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        TicksProvider::initialized_word_range(self)
+    }
+}
+
+impl<Provider> Math<Provider>
+where
+    Provider: TicksProviderMut,
+{
+    // Same as simulate_swap, with mutable access to the provider inside the swap loop
+    pub fn simulate_swap_mut(
+        &mut self,
+        zero_for_one: bool,
+        amount_in: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if amount_in == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let current_state = self.swap_mut(
+            zero_for_one,
+            u256_to_i256(amount_in),
+            default_sqrt_price_limit(zero_for_one),
+        )?;
+
+        Ok(i256_to_u256(-current_state.amount_calculated))
+    }
+
+    // Same as simulate_swap_exact_output, with mutable access to the provider inside the swap loop
+    pub fn simulate_swap_exact_output_mut(
+        &mut self,
+        zero_for_one: bool,
+        amount_out: U256,
+    ) -> Result<U256, UniswapV3MathError> {
+        if amount_out == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        let current_state = self.swap_mut(
+            zero_for_one,
+            -u256_to_i256(amount_out),
+            default_sqrt_price_limit(zero_for_one),
+        )?;
+
+        // The walk hit the price limit before the full output could be produced, what it did
+        // produce is all the pool has in that direction
+        if current_state.amount_specified_remaining != I256::ZERO {
+            return Err(UniswapV3MathError::InsufficientLiquidity {
+                requested: amount_out,
+                available: amount_out - i256_to_u256(-current_state.amount_specified_remaining),
+            });
+        }
+
+        Ok(i256_to_u256(current_state.amount_calculated))
+    }
+
+    // Drives the same steps as Math::swap on a copy of the pool state, so the provider can be
+    // borrowed mutably alongside it
+    fn swap_mut(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<CurrentState, UniswapV3MathError> {
+        let pool = self.with_provider(());
+        let provider = &mut self.provider;
+        let mut current_state = pool.initial_state(amount_specified);
+
+        while current_state.in_progress(sqrt_price_limit_x96) {
+            if let Some(word_pos) = pool.begin_step(&mut current_state, zero_for_one)? {
+                let skipped_to = pool.skip_empty_words(
+                    &mut current_state,
+                    word_pos,
+                    zero_for_one,
+                    sqrt_price_limit_x96,
+                    || pool.initialized_words(provider.initialized_word_range()),
+                )?;
+                match skipped_to {
+                    Some(word_pos) => current_state.load_word(word_pos, U256::ZERO),
+                    None => {
+                        let word = if pool.known_empty_word(word_pos) {
+                            U256::ZERO
+                        } else {
+                            provider.get_word_at_position(word_pos)?
+                        };
+                        current_state.load_word(word_pos, word);
+                    }
+                }
+            }
+
+            let step = pool.compute_step(&mut current_state, zero_for_one, sqrt_price_limit_x96)?;
+
+            let liquidity_net = match current_state.crossed_tick(&step) {
+                Some(tick) => Some(provider.get_liquidity_net_at_tick(tick)?),
+                None => None,
+            };
+
+            pool.finish_step(&mut current_state, step, liquidity_net, zero_for_one)?;
+        }
+
+        Ok(current_state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use ruint::uint;
+
+    use super::*;
+    use crate::test::fixture;
+
+    // Memoizes and records its reads through &mut self, with no interior mutability
+    #[derive(Default)]
+    struct RecordingProvider {
+        words: HashMap<i16, U256>,
+        liquidity_nets: HashMap<i32, i128>,
+        word_reads: Vec<i16>,
+        liquidity_net_reads: Vec<i32>,
+    }
+
+    impl TicksProviderMut for RecordingProvider {
+        fn get_word_at_position(&mut self, position: i16) -> Result<U256, UniswapV3MathError> {
+            self.word_reads.push(position);
+            Ok(self.words.get(&position).copied().unwrap_or_default())
+        }
+
+        fn get_liquidity_net_at_tick(&mut self, tick: i32) -> Result<i128, UniswapV3MathError> {
+            self.liquidity_net_reads.push(tick);
+            Ok(self.liquidity_nets.get(&tick).copied().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn test_simulate_swap_mut() {
+        let math = fixture();
+        let mut recording = math.with_provider(RecordingProvider {
+            words: math.provider.words.clone(),
+            liquidity_nets: math.provider.liquidity_nets.clone(),
+            ..Default::default()
+        });
+        //TicksProvider implementations go through the blanket impl
+        let mut immutable = fixture();
+
+        //amounts within one range, across several ticks and draining the pool across words
+        let amounts = [
+            U256::ZERO,
+            uint!(100000000000000000_U256),
+            uint!(300000000000000000_U256),
+            uint!(500000000000000000_U256),
+            uint!(1000000000000000000_U256),
+        ];
+
+        for zero_for_one in [true, false] {
+            for amount in amounts {
+                let expected = math.simulate_swap(zero_for_one, amount).unwrap();
+                let result = recording.simulate_swap_mut(zero_for_one, amount);
+                assert_eq!(result.unwrap(), expected);
+                let result = immutable.simulate_swap_mut(zero_for_one, amount);
+                assert_eq!(result.unwrap(), expected);
+
+                let expected = math.simulate_swap_exact_output(zero_for_one, amount);
+                for result in [
+                    recording.simulate_swap_exact_output_mut(zero_for_one, amount),
+                    immutable.simulate_swap_exact_output_mut(zero_for_one, amount),
+                ] {
+                    match &expected {
+                        Ok(expected) => assert_eq!(result.unwrap(), *expected),
+                        Err(UniswapV3MathError::InsufficientLiquidity {
+                            requested,
+                            available,
+                        }) => assert!(matches!(
+                            result.unwrap_err(),
+                            UniswapV3MathError::InsufficientLiquidity {
+                                requested: mut_requested,
+                                available: mut_available,
+                            } if mut_requested == *requested && mut_available == *available
+                        )),
+                        Err(err) => panic!("unexpected error {err:?}"),
+                    }
+                }
+            }
+        }
+
+        //the provider saw every read of the swaps
+        let mut recording = math.with_provider(RecordingProvider {
+            words: math.provider.words.clone(),
+            liquidity_nets: math.provider.liquidity_nets.clone(),
+            ..Default::default()
+        });
+        recording
+            .simulate_swap_mut(true, uint!(300000000000000000_U256))
+            .unwrap();
+        assert_eq!(recording.provider.word_reads, vec![0]);
+        assert_eq!(recording.provider.liquidity_net_reads, vec![6600, 4200]);
+    }
+}
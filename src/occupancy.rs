@@ -0,0 +1,447 @@
+use std::ops::RangeInclusive;
+
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    tick_bitmap::position,
+    tick_math::{calculate_compressed, max_usable_tick, min_usable_tick},
+    TicksProvider,
+};
+
+// One bit per bitmap word over a tick spacing's usable words, set when the word may hold an
+// initialized tick. 6932 words at spacing 1 take under 1KB, and spacings of 10 and up under 100
+// bytes. A summary may claim a word is occupied when it isn't, which only costs a read, but never
+// that an occupied word is empty: keep it current with update_word, which MemoryTicksProvider's
+// flip_tick, apply_mint and apply_burn call, and check it with verify_against. Math::with_occupancy
+// has the swap loop load provably empty words as zero instead of reading them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccupancySummary {
+    tick_spacing: i32,
+    first_word: i16,
+    last_word: i16,
+    bits: Vec<u64>,
+}
+
+impl OccupancySummary {
+    // A summary with every word empty
+    pub fn new(tick_spacing: i32) -> Result<Self, UniswapV3MathError> {
+        if tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing });
+        }
+
+        let word = |tick: i32| position(calculate_compressed(tick, tick_spacing)).0;
+        let (first_word, last_word) = (
+            word(min_usable_tick(tick_spacing)),
+            word(max_usable_tick(tick_spacing)),
+        );
+        let words = (last_word as i32 - first_word as i32 + 1) as usize;
+
+        Ok(OccupancySummary {
+            tick_spacing,
+            first_word,
+            last_word,
+            bits: vec![0; words.div_ceil(64)],
+        })
+    }
+
+    // Reads the words provider reports in initialized_word_range, or every usable word when it
+    // can't tell which are empty, in one batch
+    pub fn from_provider(
+        provider: &impl TicksProvider,
+        tick_spacing: i32,
+    ) -> Result<Self, UniswapV3MathError> {
+        let mut summary = OccupancySummary::new(tick_spacing)?;
+
+        let positions = summary.words_to_read(provider);
+        let words = provider.get_words(&positions)?;
+        if words.len() != positions.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: positions.len(),
+                got: words.len(),
+            });
+        }
+        for (word_pos, word) in positions.into_iter().zip(words) {
+            summary.update_word(word_pos, word == U256::ZERO);
+        }
+
+        Ok(summary)
+    }
+
+    pub fn tick_spacing(&self) -> i32 {
+        self.tick_spacing
+    }
+
+    // Positions of the words that can hold a usable tick
+    pub fn word_range(&self) -> RangeInclusive<i16> {
+        self.first_word..=self.last_word
+    }
+
+    // Whether word_pos is known to hold no initialized tick. Words past the usable ticks never do.
+    pub fn is_empty(&self, word_pos: i16) -> bool {
+        match self.index(word_pos) {
+            Some(index) => self.bits[index / 64] & (1 << (index % 64)) == 0,
+            None => true,
+        }
+    }
+
+    // Whether any word from from_word to to_word, both included and in either order, may hold an
+    // initialized tick
+    pub fn any_initialized_within(&self, from_word: i16, to_word: i16) -> bool {
+        let (low, high) = (from_word.min(to_word), from_word.max(to_word));
+        let (low, high) = (low.max(self.first_word), high.min(self.last_word));
+        (low..=high).any(|word_pos| !self.is_empty(word_pos))
+    }
+
+    // First word from from on that may hold an initialized tick, towards lower words when
+    // zero_for_one like the swap and towards higher ones otherwise
+    pub fn nearest_occupied_word(&self, from: i16, zero_for_one: bool) -> Option<i16> {
+        if zero_for_one {
+            (self.first_word..=from.min(self.last_word))
+                .rev()
+                .find(|word_pos| !self.is_empty(*word_pos))
+        } else {
+            (from.max(self.first_word)..=self.last_word).find(|word_pos| !self.is_empty(*word_pos))
+        }
+    }
+
+    // First and last words that may hold an initialized tick, in the form of
+    // TicksProvider::initialized_word_range
+    pub fn occupied_range(&self) -> RangeInclusive<i16> {
+        match (
+            self.nearest_occupied_word(self.first_word, false),
+            self.nearest_occupied_word(self.last_word, true),
+        ) {
+            (Some(first), Some(last)) => first..=last,
+            #[allow(clippy::reversed_empty_ranges)]
+            _ => 1..=0,
+        }
+    }
+
+    // Records whether word_pos is empty after a change to the bitmap. Words past the usable ticks
+    // can't be initialized and are ignored.
+    pub fn update_word(&mut self, word_pos: i16, now_empty: bool) {
+        if let Some(index) = self.index(word_pos) {
+            if now_empty {
+                self.bits[index / 64] &= !(1 << (index % 64));
+            } else {
+                self.bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+    }
+
+    // Reads every word the summary claims is empty, within provider's initialized_word_range when
+    // it has one, and fails on the first that isn't
+    pub fn verify_against(&self, provider: &impl TicksProvider) -> Result<(), UniswapV3MathError> {
+        let positions = self
+            .words_to_read(provider)
+            .into_iter()
+            .filter(|word_pos| self.is_empty(*word_pos))
+            .collect::<Vec<_>>();
+        let words = provider.get_words(&positions)?;
+        if words.len() != positions.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: positions.len(),
+                got: words.len(),
+            });
+        }
+
+        match positions
+            .into_iter()
+            .zip(words)
+            .find(|(_, word)| *word != U256::ZERO)
+        {
+            Some((word_pos, _)) => Err(UniswapV3MathError::StaleOccupancy { word_pos }),
+            None => Ok(()),
+        }
+    }
+
+    fn index(&self, word_pos: i16) -> Option<usize> {
+        (self.first_word..=self.last_word)
+            .contains(&word_pos)
+            .then(|| (word_pos as i32 - self.first_word as i32) as usize)
+    }
+
+    // Usable words, narrowed to provider's initialized words when it knows them
+    fn words_to_read(&self, provider: &impl TicksProvider) -> Vec<i16> {
+        match provider.initialized_word_range() {
+            Some(initialized) => {
+                (*initialized.start()).max(self.first_word)
+                    ..=(*initialized.end()).min(self.last_word)
+            }
+            None => self.word_range(),
+        }
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, sync::Arc};
+
+    use ruint::uint;
+
+    use super::*;
+    use crate::{mut_provider::TicksProviderMut, providers::MemoryTicksProvider, Math};
+
+    // Counts word reads and, unlike MemoryTicksProvider, can't tell which words are empty
+    struct CountingProvider {
+        inner: MemoryTicksProvider,
+        word_reads: Cell<usize>,
+    }
+
+    impl TicksProvider for CountingProvider {
+        fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+            self.word_reads.set(self.word_reads.get() + 1);
+            self.inner.get_word_at_position(position)
+        }
+
+        fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+            self.inner.get_liquidity_net_at_tick(tick)
+        }
+    }
+
+    // Positions at spacing 60 with ticks in words -10, -4, 0, 5 and 10, two of them inside word 0
+    const POSITIONS: [(i32, i32, u128); 4] = [
+        (-153600, 153600, 1_000_000_000_000_000_000),
+        (-61440, 76800, 500_000_000_000_000_000),
+        (600, 14400, 2_000_000_000_000_000_000),
+        (6600, 7800, 2_000_000_000_000_000_000),
+    ];
+
+    // Pool at tick 7000 on POSITIONS, minted into a provider with the summary kept alongside
+    fn sparse() -> (Math<MemoryTicksProvider>, OccupancySummary) {
+        let mut provider = MemoryTicksProvider::default();
+        let mut summary = OccupancySummary::new(60).unwrap();
+        for (tick_lower, tick_upper, liquidity) in POSITIONS {
+            provider
+                .apply_mint(tick_lower, tick_upper, liquidity, 60, Some(&mut summary))
+                .unwrap();
+        }
+
+        let math = Math::builder()
+            .fee(3000)
+            .liquidity(5_500_000_000_000_000_000)
+            .sqrt_price_x96(uint!(112430957613446531369126327961_U256))
+            .tick(7000)
+            .tick_spacing(60)
+            .provider(provider)
+            .build()
+            .unwrap();
+        (math, summary)
+    }
+
+    fn counting(math: &Math<MemoryTicksProvider>) -> Math<CountingProvider> {
+        math.with_provider(CountingProvider {
+            inner: math.provider.clone(),
+            word_reads: Cell::new(0),
+        })
+    }
+
+    #[test]
+    fn test_occupancy_summary() {
+        let (math, summary) = sparse();
+        assert_eq!(summary.tick_spacing(), 60);
+        assert_eq!(summary.word_range(), -58..=57);
+        assert_eq!(summary.occupied_range(), -10..=10);
+
+        //153600 / 60 is the first tick of word 10, -61440 / 60 the first of word -4
+        for word_pos in [-10, -4, 0, 5, 10] {
+            assert!(!summary.is_empty(word_pos));
+        }
+        for word_pos in [-11, -9, -1, 1, 4, 6, 9, 11, i16::MIN, i16::MAX] {
+            assert!(summary.is_empty(word_pos));
+        }
+
+        assert!(summary.any_initialized_within(1, 5));
+        assert!(summary.any_initialized_within(5, 1));
+        assert!(!summary.any_initialized_within(1, 4));
+        assert!(!summary.any_initialized_within(11, i16::MAX));
+        assert!(summary.any_initialized_within(i16::MIN, i16::MAX));
+
+        assert_eq!(summary.nearest_occupied_word(0, true), Some(0));
+        assert_eq!(summary.nearest_occupied_word(-1, true), Some(-4));
+        assert_eq!(summary.nearest_occupied_word(-11, true), None);
+        assert_eq!(summary.nearest_occupied_word(1, false), Some(5));
+        assert_eq!(summary.nearest_occupied_word(i16::MIN, false), Some(-10));
+        assert_eq!(summary.nearest_occupied_word(i16::MAX, true), Some(10));
+        assert_eq!(summary.nearest_occupied_word(11, false), None);
+
+        //scanning the provider, with and without initialized_word_range, gives the same summary
+        assert_eq!(
+            OccupancySummary::from_provider(&math.provider, 60).unwrap(),
+            summary
+        );
+        let counting = counting(&math);
+        assert_eq!(
+            OccupancySummary::from_provider(&counting.provider, 60).unwrap(),
+            summary
+        );
+        assert_eq!(counting.provider.word_reads.get(), 116);
+
+        //and keeps agreeing as positions are burned, down to nothing
+        let (mut provider, mut summary) = (math.provider.clone(), summary);
+        for (tick_lower, tick_upper, liquidity) in POSITIONS.into_iter().rev() {
+            provider
+                .apply_burn(tick_lower, tick_upper, liquidity, 60, Some(&mut summary))
+                .unwrap();
+            assert_eq!(
+                OccupancySummary::from_provider(&provider, 60).unwrap(),
+                summary
+            );
+        }
+        assert!(summary.occupied_range().is_empty());
+        assert_eq!(summary, OccupancySummary::new(60).unwrap());
+
+        //spacing 1 takes 6932 bits
+        let summary = OccupancySummary::new(1).unwrap();
+        assert_eq!(summary.word_range(), -3466..=3465);
+        assert_eq!(summary.bits.len(), 109);
+        assert!(matches!(
+            OccupancySummary::new(0).unwrap_err(),
+            UniswapV3MathError::InvalidTickSpacing { tick_spacing: 0 }
+        ));
+    }
+
+    #[test]
+    fn test_swap_with_occupancy() {
+        let (math, summary) = sparse();
+        let summary = Arc::new(summary);
+        let without = counting(&math);
+        let with = counting(&math).with_occupancy(summary.clone());
+
+        //within word 0, across the empty words to the outer positions and past all of them
+        for zero_for_one in [true, false] {
+            for amount_in in [
+                uint!(100000000000000000_U256),
+                uint!(10000000000000000000_U256),
+                uint!(1000000000000000000000_U256),
+                uint!(100000000000000000000000_U256),
+            ] {
+                without.provider.word_reads.set(0);
+                with.provider.word_reads.set(0);
+                let expected =
+                    without.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                let result = with.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                assert_eq!(result.unwrap(), expected.unwrap());
+
+                let (reads_without, reads_with) = (
+                    without.provider.word_reads.get(),
+                    with.provider.word_reads.get(),
+                );
+                assert!(reads_with <= reads_without);
+                if reads_without > 1 {
+                    assert!(
+                        reads_with < reads_without,
+                        "{reads_with} reads with the summary, {reads_without} without"
+                    );
+                }
+
+                //the _mut loop consults it too
+                with.provider.word_reads.set(0);
+                let mut with_mut = counting(&math).with_occupancy(summary.clone());
+                assert_eq!(
+                    TicksProviderMut::initialized_word_range(&with_mut.provider),
+                    None
+                );
+                let result = with_mut.simulate_swap_mut(zero_for_one, amount_in).unwrap();
+                assert_eq!(
+                    result,
+                    without.simulate_swap(zero_for_one, amount_in).unwrap()
+                );
+                assert_eq!(with_mut.provider.word_reads.get(), reads_with);
+            }
+        }
+
+        //a summary for another spacing is ignored
+        let other = counting(&math).with_occupancy(Arc::new(OccupancySummary::new(10).unwrap()));
+        let amount_in = uint!(10000000000000000000_U256);
+        assert_eq!(
+            other.simulate_swap(true, amount_in).unwrap(),
+            without.simulate_swap(true, amount_in).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_initialized_tick() {
+        let (math, summary) = sparse();
+        let without = counting(&math);
+        let with = counting(&math).with_occupancy(Arc::new(summary));
+
+        let ticks = [-153600, -61440, 600, 6600, 7800, 14400, 76800, 153600];
+        for tick in [
+            -200000, -153600, -100000, -61441, 0, 7000, 7800, 14399, 76800, 200000,
+        ] {
+            for zero_for_one in [true, false] {
+                let expected = if zero_for_one {
+                    ticks.iter().rev().find(|t| **t <= tick).copied()
+                } else {
+                    ticks.iter().find(|t| **t > tick).copied()
+                };
+                without.provider.word_reads.set(0);
+                with.provider.word_reads.set(0);
+                assert_eq!(
+                    without.next_initialized_tick(tick, zero_for_one).unwrap(),
+                    expected
+                );
+                assert_eq!(
+                    with.next_initialized_tick(tick, zero_for_one).unwrap(),
+                    expected
+                );
+                assert!(with.provider.word_reads.get() <= 2);
+                assert!(with.provider.word_reads.get() <= without.provider.word_reads.get());
+            }
+        }
+
+        //nothing left before the usable ticks end, every usable word read without the summary and
+        // only the one the search starts in with it
+        without.provider.word_reads.set(0);
+        assert_eq!(without.next_initialized_tick(153600, false).unwrap(), None);
+        assert_eq!(without.provider.word_reads.get(), 48);
+        with.provider.word_reads.set(0);
+        assert_eq!(with.next_initialized_tick(153600, false).unwrap(), None);
+        assert_eq!(with.provider.word_reads.get(), 1);
+    }
+
+    #[test]
+    fn test_verify_against() {
+        let (math, summary) = sparse();
+        summary.verify_against(&math.provider).unwrap();
+        summary.verify_against(&counting(&math).provider).unwrap();
+
+        //claiming an empty word is occupied only costs reads
+        let mut conservative = summary.clone();
+        conservative.update_word(1, false);
+        conservative.verify_against(&math.provider).unwrap();
+        let result = math
+            .clone()
+            .with_occupancy(Arc::new(conservative))
+            .simulate_swap(false, uint!(10000000000000000000_U256));
+        assert_eq!(
+            result.unwrap(),
+            math.simulate_swap(false, uint!(10000000000000000000_U256))
+                .unwrap()
+        );
+
+        //claiming an occupied word is empty is caught
+        let mut stale = summary.clone();
+        stale.update_word(5, true);
+        for result in [
+            stale.verify_against(&math.provider),
+            stale.verify_against(&counting(&math).provider),
+        ] {
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::StaleOccupancy { word_pos: 5 }
+            ));
+        }
+
+        //so is a tick flipped without passing the summary
+        let mut provider = math.provider.clone();
+        provider.flip_tick(-3840, 60, None).unwrap();
+        assert!(matches!(
+            summary.verify_against(&provider).unwrap_err(),
+            UniswapV3MathError::StaleOccupancy { word_pos: -1 }
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, TicksProvider};
+
+// Tick data that can be read at any block, what BlockCachedProvider fetches misses from. Any
+// closure building a TicksProvider for a block is one, e.g.
+// |block| RpcTicksProvider::new(client.clone(), pool, block.into()).
+pub trait BlockTicksProvider {
+    fn get_word_at_block(&self, block: u64, position: i16) -> Result<U256, UniswapV3MathError>;
+
+    fn get_liquidity_net_at_block(&self, block: u64, tick: i32)
+        -> Result<i128, UniswapV3MathError>;
+}
+
+impl<F, P> BlockTicksProvider for F
+where
+    F: Fn(u64) -> P,
+    P: TicksProvider,
+{
+    fn get_word_at_block(&self, block: u64, position: i16) -> Result<U256, UniswapV3MathError> {
+        self(block).get_word_at_position(position)
+    }
+
+    fn get_liquidity_net_at_block(
+        &self,
+        block: u64,
+        tick: i32,
+    ) -> Result<i128, UniswapV3MathError> {
+        self(block).get_liquidity_net_at_tick(tick)
+    }
+}
+
+// Memoizes words and liquidity nets under the block they were read at. As a TicksProvider it
+// serves the current block, which set_block moves, dropping what was cached for other blocks
+// unless keep_history is set. Values are only ever served for the block they were read at, so a
+// new block never sees stale data. A simulation running while set_block may be called should go
+// through at_block to stay on one block.
+#[derive(Debug)]
+pub struct BlockCachedProvider<Provider> {
+    provider: Provider,
+    block: AtomicU64,
+    keep_history: bool,
+    words: RwLock<HashMap<(u64, i16), U256>>,
+    liquidity_nets: RwLock<HashMap<(u64, i32), i128>>,
+}
+
+impl<Provider> BlockCachedProvider<Provider>
+where
+    Provider: BlockTicksProvider,
+{
+    pub fn new(provider: Provider, block: u64) -> Self {
+        BlockCachedProvider {
+            provider,
+            block: AtomicU64::new(block),
+            keep_history: false,
+            words: RwLock::default(),
+            liquidity_nets: RwLock::default(),
+        }
+    }
+
+    // Keeps the values of past blocks when set_block moves on, for at_block queries of them
+    pub fn keep_history(mut self, keep_history: bool) -> Self {
+        self.keep_history = keep_history;
+        self
+    }
+
+    pub fn inner(&self) -> &Provider {
+        &self.provider
+    }
+
+    pub fn block(&self) -> u64 {
+        self.block.load(Ordering::Acquire)
+    }
+
+    pub fn set_block(&self, block: u64) {
+        self.block.store(block, Ordering::Release);
+        if !self.keep_history {
+            retain_block(&self.words, block);
+            retain_block(&self.liquidity_nets, block);
+        }
+    }
+
+    // A TicksProvider reading at block whatever set_block does in the meantime
+    pub fn at_block(&self, block: u64) -> BlockPinnedProvider<'_, Provider> {
+        BlockPinnedProvider { cache: self, block }
+    }
+
+    fn get<K, V>(
+        &self,
+        cache: &RwLock<HashMap<(u64, K), V>>,
+        block: u64,
+        key: K,
+        read: impl FnOnce(u64, K) -> Result<V, UniswapV3MathError>,
+    ) -> Result<V, UniswapV3MathError>
+    where
+        K: Eq + Hash + Copy,
+        V: Copy,
+    {
+        let cached = cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(block, key))
+            .copied();
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+
+        let value = read(block, key)?;
+        cache
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((block, key), value);
+        Ok(value)
+    }
+
+    fn get_word(&self, block: u64, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.get(&self.words, block, position, |block, position| {
+            self.provider.get_word_at_block(block, position)
+        })
+    }
+
+    fn get_liquidity_net(&self, block: u64, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.get(&self.liquidity_nets, block, tick, |block, tick| {
+            self.provider.get_liquidity_net_at_block(block, tick)
+        })
+    }
+}
+
+fn retain_block<K, V>(cache: &RwLock<HashMap<(u64, K), V>>, block: u64) {
+    cache
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .retain(|(entry_block, _), _| *entry_block == block);
+}
+
+impl<Provider> TicksProvider for BlockCachedProvider<Provider>
+where
+    Provider: BlockTicksProvider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.get_word(self.block(), position)
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.get_liquidity_net(self.block(), tick)
+    }
+}
+
+// BlockCachedProvider::at_block
+#[derive(Debug)]
+pub struct BlockPinnedProvider<'a, Provider> {
+    cache: &'a BlockCachedProvider<Provider>,
+    block: u64,
+}
+
+impl<Provider> BlockPinnedProvider<'_, Provider> {
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+}
+
+impl<Provider> TicksProvider for BlockPinnedProvider<'_, Provider>
+where
+    Provider: BlockTicksProvider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.cache.get_word(self.block, position)
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.cache.get_liquidity_net(self.block, tick)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+
+    use reth_primitives::U256;
+
+    use super::*;
+    use crate::{providers::MemoryTicksProvider, sqrt_price_math::Q96, Math};
+
+    // Ticks of a pool with 1e18 liquidity around tick 0 and a range below it whose liquidity grows
+    // with the block, so swaps reaching it tell the blocks apart
+    fn pool_at(block: u64) -> MemoryTicksProvider {
+        let liquidity = (block as i128 + 1) * 100_000_000_000_000_000;
+        MemoryTicksProvider::from_initialized_ticks(
+            &[
+                (-1800, liquidity),
+                (-1200, -liquidity),
+                (-600, 1_000_000_000_000_000_000),
+                (600, -1_000_000_000_000_000_000),
+            ],
+            60,
+        )
+        .unwrap()
+    }
+
+    fn net_at(block: u64) -> i128 {
+        -(block as i128 + 1) * 100_000_000_000_000_000
+    }
+
+    #[test]
+    fn test_block_cached_provider() {
+        let reads = AtomicUsize::new(0);
+        let cache = BlockCachedProvider::new(
+            |block| {
+                reads.fetch_add(1, Ordering::Relaxed);
+                pool_at(block)
+            },
+            1,
+        );
+
+        assert_eq!(cache.get_liquidity_net_at_tick(-1200).unwrap(), net_at(1));
+        assert_eq!(cache.get_liquidity_net_at_tick(-1200).unwrap(), net_at(1));
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+
+        //a new block never serves what was read at the old one
+        for block in 2..10 {
+            cache.set_block(block);
+            assert_eq!(
+                cache.get_liquidity_net_at_tick(-1200).unwrap(),
+                net_at(block)
+            );
+            assert_eq!(cache.liquidity_nets.read().unwrap().len(), 1);
+        }
+
+        //without history, past blocks are read again
+        let reads_before = reads.load(Ordering::Relaxed);
+        let result = cache.at_block(1).get_liquidity_net_at_tick(-1200);
+        assert_eq!(result.unwrap(), net_at(1));
+        assert_eq!(reads.load(Ordering::Relaxed), reads_before + 1);
+
+        //with history, they stay cached
+        let cache = cache.keep_history(true);
+        cache.at_block(1).get_word_at_position(-1).unwrap();
+        cache.set_block(10);
+        let reads_before = reads.load(Ordering::Relaxed);
+        let result = cache.at_block(1).get_liquidity_net_at_tick(-1200);
+        assert_eq!(result.unwrap(), net_at(1));
+        assert_eq!(
+            cache.at_block(1).get_word_at_position(-1).unwrap(),
+            pool_at(1).get_word_at_position(-1).unwrap()
+        );
+        assert_eq!(reads.load(Ordering::Relaxed), reads_before);
+    }
+
+    #[test]
+    fn test_block_cached_provider_threads() {
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let cache = BlockCachedProvider::new(pool_at, 0);
+        assert_sync(&cache);
+        let math = Math::builder()
+            .fee(3000)
+            .liquidity(1_000_000_000_000_000_000)
+            .sqrt_price_x96(Q96)
+            .tick_spacing(60)
+            .build()
+            .unwrap();
+        //enough to leave the range around tick 0 and cross into the block dependent one
+        let amount_in = U256::from(100_000_000_000_000_000_u128);
+        assert_ne!(
+            math.with_provider(pool_at(0))
+                .simulate_swap(true, amount_in)
+                .unwrap(),
+            math.with_provider(pool_at(1))
+                .simulate_swap(true, amount_in)
+                .unwrap()
+        );
+
+        //simulations pinned to a block match that block's pool while set_block keeps moving
+        std::thread::scope(|scope| {
+            for thread in 0..4_u64 {
+                let (cache, math) = (&cache, &math);
+                scope.spawn(move || {
+                    for block in (0..50).map(|i| thread * 50 + i) {
+                        let pinned = math.with_provider(cache.at_block(block));
+                        let expected = math.with_provider(pool_at(block));
+                        assert_eq!(
+                            pinned.simulate_swap(true, amount_in).unwrap(),
+                            expected.simulate_swap(true, amount_in).unwrap()
+                        );
+                    }
+                });
+            }
+            for block in 0..200 {
+                cache.set_block(block);
+            }
+        });
+    }
+}
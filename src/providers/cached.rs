@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, TicksProvider};
+
+// Memoizes the words and liquidity nets read through another provider, so repeated simulations
+// against the same block only read each of them once. Errors are not cached. The cache has no
+// notion of blocks, call clear once the underlying state moves on.
+#[derive(Debug, Default)]
+pub struct CachedTicksProvider<Provider> {
+    provider: Provider,
+    words: RwLock<HashMap<i16, U256>>,
+    liquidity_nets: RwLock<HashMap<i32, i128>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<Provider> CachedTicksProvider<Provider> {
+    pub fn new(provider: Provider) -> Self {
+        CachedTicksProvider {
+            provider,
+            words: RwLock::default(),
+            liquidity_nets: RwLock::default(),
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &Provider {
+        &self.provider
+    }
+
+    pub fn into_inner(self) -> Provider {
+        self.provider
+    }
+
+    // Drops every cached value. The hit and miss counters keep counting across clears.
+    pub fn clear(&self) {
+        self.words
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.liquidity_nets
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    // Reads answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    // Reads passed through to the underlying provider
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    // Looks key up in cache, reading and storing it on a miss
+    fn get_one<K, V>(
+        &self,
+        cache: &RwLock<HashMap<K, V>>,
+        key: K,
+        read: impl FnOnce(K) -> Result<V, UniswapV3MathError>,
+    ) -> Result<V, UniswapV3MathError>
+    where
+        K: Eq + Hash + Copy,
+        V: Copy,
+    {
+        let cached = cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .copied();
+        if let Some(value) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = read(key)?;
+        cache
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, value);
+        Ok(value)
+    }
+
+    // Looks keys up in cache, reading the missing ones in a single batch
+    fn get_many<K, V>(
+        &self,
+        cache: &RwLock<HashMap<K, V>>,
+        keys: &[K],
+        read: impl FnOnce(&[K]) -> Result<Vec<V>, UniswapV3MathError>,
+    ) -> Result<Vec<V>, UniswapV3MathError>
+    where
+        K: Eq + Hash + Copy,
+        V: Copy,
+    {
+        let mut values = {
+            let cache = cache.read().unwrap_or_else(PoisonError::into_inner);
+            keys.iter()
+                .map(|key| cache.get(key).copied())
+                .collect::<Vec<_>>()
+        };
+
+        let missing = keys
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        self.hits
+            .fetch_add((keys.len() - missing.len()) as u64, Ordering::Relaxed);
+
+        if !missing.is_empty() {
+            self.misses
+                .fetch_add(missing.len() as u64, Ordering::Relaxed);
+            let read_values = read(&missing)?;
+            if read_values.len() != missing.len() {
+                return Err(UniswapV3MathError::ProviderBatchLength {
+                    expected: missing.len(),
+                    got: read_values.len(),
+                });
+            }
+
+            cache
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend(missing.iter().copied().zip(read_values.iter().copied()));
+
+            let mut read_values = read_values.into_iter();
+            for value in values.iter_mut().filter(|value| value.is_none()) {
+                *value = read_values.next();
+            }
+        }
+
+        Ok(values.into_iter().flatten().collect())
+    }
+}
+
+impl<Provider> TicksProvider for CachedTicksProvider<Provider>
+where
+    Provider: TicksProvider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.get_one(&self.words, position, |position| {
+            self.provider.get_word_at_position(position)
+        })
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.get_one(&self.liquidity_nets, tick, |tick| {
+            self.provider.get_liquidity_net_at_tick(tick)
+        })
+    }
+
+    fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+        self.get_many(&self.words, positions, |missing| {
+            self.provider.get_words(missing)
+        })
+    }
+
+    fn get_liquidity_nets(&self, ticks: &[i32]) -> Result<Vec<i128>, UniswapV3MathError> {
+        self.get_many(&self.liquidity_nets, ticks, |missing| {
+            self.provider.get_liquidity_nets(missing)
+        })
+    }
+
+    fn words_per_batch(&self) -> usize {
+        self.provider.words_per_batch()
+    }
+
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        self.provider.initialized_word_range()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reth_primitives::U256;
+
+    use super::CachedTicksProvider;
+    use crate::{error::UniswapV3MathError, test::fixture, TicksProvider};
+
+    // Drops the last word of every batch
+    struct ShortBatchProvider;
+
+    impl TicksProvider for ShortBatchProvider {
+        fn get_word_at_position(&self, _position: i16) -> Result<U256, UniswapV3MathError> {
+            Ok(U256::ZERO)
+        }
+
+        fn get_liquidity_net_at_tick(&self, _tick: i32) -> Result<i128, UniswapV3MathError> {
+            Ok(0)
+        }
+
+        fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+            Ok(vec![U256::ZERO; positions.len().saturating_sub(1)])
+        }
+    }
+
+    #[test]
+    fn test_cached_provider() {
+        let math = fixture();
+        let cached = math.with_provider(CachedTicksProvider::new(&math.provider));
+
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        //quotes up to 0.5e18, enough to drain the pool in the zero_for_one direction
+        let quotes = (0..100)
+            .map(|_| {
+                (
+                    next() % 2 == 0,
+                    U256::from(next() % 500_000_000_000_000_000),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut cached_amounts = vec![];
+        for &(zero_for_one, amount_in) in &quotes {
+            cached_amounts.push(cached.simulate_swap(zero_for_one, amount_in).unwrap());
+        }
+        let cached_calls = math.provider.calls.replace(0);
+
+        for (&(zero_for_one, amount_in), cached_amount) in quotes.iter().zip(cached_amounts) {
+            assert_eq!(
+                math.simulate_swap(zero_for_one, amount_in).unwrap(),
+                cached_amount
+            );
+        }
+        let raw_calls = math.provider.calls.get();
+
+        assert_eq!(cached.provider.misses(), cached_calls as u64);
+        assert!(cached_calls * 10 <= raw_calls);
+
+        //cleared values are read again
+        let misses = cached.provider.misses();
+        cached.provider.clear();
+        cached.provider.get_word_at_position(0).unwrap();
+        assert_eq!(cached.provider.misses(), misses + 1);
+
+        //batches only read what is missing
+        let before = math.provider.calls.get();
+        let words = cached.provider.get_words(&[0, 1, -1]).unwrap();
+        assert_eq!(words, math.provider.get_words(&[0, 1, -1]).unwrap());
+        assert_eq!(math.provider.calls.get() - before, 2 + 3);
+        assert_eq!(cached.provider.misses(), misses + 3);
+    }
+
+    #[test]
+    fn test_cached_provider_short_batch() {
+        let cached = CachedTicksProvider::new(ShortBatchProvider);
+        assert!(matches!(
+            cached.get_words(&[0, 1, -1]).unwrap_err(),
+            UniswapV3MathError::ProviderBatchLength {
+                expected: 3,
+                got: 2
+            }
+        ));
+        //nothing of the failed batch is cached
+        assert!(matches!(
+            cached.get_words(&[0]).unwrap_err(),
+            UniswapV3MathError::ProviderBatchLength {
+                expected: 1,
+                got: 0
+            }
+        ));
+    }
+}
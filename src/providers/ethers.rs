@@ -0,0 +1,96 @@
+use std::future::Future;
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockId, H256},
+};
+use reth_primitives::U256;
+use tokio::runtime::Handle;
+
+use crate::{
+    async_provider::AsyncTicksProvider,
+    error::UniswapV3MathError,
+    storage::{liquidity_net_from_word, tick_bitmap_slot, ticks_slot},
+    TicksProvider,
+};
+
+// Same as RpcTicksProvider on top of an ethers Middleware: reads a pool's tick data with
+// eth_getStorageAt at a fixed block. The sync TicksProvider needs a runtime handle attached with
+// with_handle and fails with BlockingInRuntime on a current_thread runtime's thread.
+pub struct EthersTicksProvider<M> {
+    middleware: M,
+    pool: Address,
+    block_id: BlockId,
+    handle: Option<Handle>,
+}
+
+impl<M> EthersTicksProvider<M>
+where
+    M: Middleware,
+{
+    pub fn new(middleware: M, pool: Address, block_id: BlockId) -> Self {
+        EthersTicksProvider {
+            middleware,
+            pool,
+            block_id,
+            handle: None,
+        }
+    }
+
+    pub fn with_handle(mut self, handle: Handle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.block_id
+    }
+
+    async fn get_storage_at(&self, slot: U256) -> Result<U256, UniswapV3MathError> {
+        let value = self
+            .middleware
+            .get_storage_at(
+                self.pool,
+                H256(slot.to_be_bytes::<32>()),
+                Some(self.block_id),
+            )
+            .await
+            .map_err(UniswapV3MathError::provider)?;
+        Ok(U256::from_be_bytes(value.0))
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> Result<F::Output, UniswapV3MathError> {
+        super::block_on(self.handle.as_ref(), future)
+    }
+}
+
+impl<M> AsyncTicksProvider for EthersTicksProvider<M>
+where
+    M: Middleware,
+{
+    async fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.get_storage_at(tick_bitmap_slot(position)).await
+    }
+
+    async fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        let word = self.get_storage_at(ticks_slot(tick)).await?;
+        Ok(liquidity_net_from_word(word))
+    }
+}
+
+impl<M> TicksProvider for EthersTicksProvider<M>
+where
+    M: Middleware,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.block_on(AsyncTicksProvider::get_word_at_position(self, position))?
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.block_on(AsyncTicksProvider::get_liquidity_net_at_tick(self, tick))?
+    }
+}
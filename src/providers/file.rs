@@ -0,0 +1,212 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, snapshot::PoolSnapshot, Math, TicksProvider};
+
+// A PoolSnapshot kept in a JSON file, for backtesting against recorded pools. Snapshots are
+// validated when loaded and before they are saved.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileSnapshotProvider {
+    snapshot: PoolSnapshot,
+}
+
+impl FileSnapshotProvider {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, UniswapV3MathError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| snapshot_file_error(path, error))?;
+        let snapshot: PoolSnapshot = serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| snapshot_file_error(path, error))?;
+        snapshot.validate()?;
+
+        Ok(FileSnapshotProvider { snapshot })
+    }
+
+    pub fn save(path: impl AsRef<Path>, snapshot: &PoolSnapshot) -> Result<(), UniswapV3MathError> {
+        snapshot.validate()?;
+
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|error| snapshot_file_error(path, error))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, snapshot)
+            .map_err(|error| snapshot_file_error(path, error))?;
+        writer
+            .flush()
+            .map_err(|error| snapshot_file_error(path, error))
+    }
+
+    pub fn snapshot(&self) -> &PoolSnapshot {
+        &self.snapshot
+    }
+
+    pub fn into_snapshot(self) -> PoolSnapshot {
+        self.snapshot
+    }
+
+    // The recorded pool on top of the loaded snapshot
+    pub fn math(&self) -> Math<&PoolSnapshot> {
+        self.snapshot.math()
+    }
+}
+
+impl TicksProvider for FileSnapshotProvider {
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.snapshot.get_word_at_position(position)
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.snapshot.get_liquidity_net_at_tick(tick)
+    }
+
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        self.snapshot.initialized_word_range()
+    }
+}
+
+fn snapshot_file_error(
+    path: &Path,
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> UniswapV3MathError {
+    UniswapV3MathError::SnapshotFile {
+        path: path.to_path_buf(),
+        source: error.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use ruint::uint;
+
+    use super::*;
+    use crate::{
+        providers::MemoryTicksProvider,
+        test::fixture,
+        tick_math::{MAX_TICK, MIN_TICK},
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn test_file_snapshot_round_trip() {
+        let math = fixture();
+        let math = math.with_provider(MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+
+        for (name, snapshot) in [
+            ("complete", PoolSnapshot::from_math(&math)),
+            (
+                "recorded",
+                PoolSnapshot::record(&math, MIN_TICK..=MAX_TICK).unwrap(),
+            ),
+        ] {
+            let path = temp_path(name);
+            FileSnapshotProvider::save(&path, &snapshot).unwrap();
+            let loaded = FileSnapshotProvider::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.snapshot(), &snapshot);
+
+            let replay = loaded.math();
+            for zero_for_one in [true, false] {
+                for amount_in in [
+                    uint!(1000000000000000_U256),
+                    uint!(300000000000000000_U256),
+                    uint!(1000000000000000000_U256),
+                ] {
+                    let expected =
+                        math.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                    let result =
+                        replay.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                    assert_eq!(result.unwrap(), expected.unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_file_snapshot_errors() {
+        let math = fixture();
+        let math = math.with_provider(MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+        let snapshot = PoolSnapshot::from_math(&math);
+
+        let path = temp_path("missing");
+        let result = FileSnapshotProvider::load(&path);
+        let Err(UniswapV3MathError::SnapshotFile {
+            path: error_path,
+            source,
+        }) = result
+        else {
+            panic!("expected a snapshot file error");
+        };
+        assert_eq!(error_path, path);
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+        let path = temp_path("garbage");
+        std::fs::write(&path, "{\"fee\": ").unwrap();
+        let result = FileSnapshotProvider::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SnapshotFile { .. }
+        ));
+
+        let mut missing_net = snapshot.clone();
+        missing_net.liquidity_nets.remove(0);
+        let mut unaligned = snapshot.clone();
+        unaligned.liquidity_nets[0].0 += 1;
+        let mut unset_bit = snapshot.clone();
+        unset_bit.words[0].1 = U256::ZERO;
+        unset_bit.liquidity_nets = vec![(600, 1)];
+        let mut unsorted = snapshot.clone();
+        unsorted.liquidity_nets.swap(0, 1);
+        let mut price_mismatch = snapshot.clone();
+        price_mismatch.tick += 100;
+
+        //inconsistent snapshots are rejected on save and on load, with the same error
+        let rejections = |invalid: &PoolSnapshot| {
+            let path = temp_path("invalid");
+            let save_error = FileSnapshotProvider::save(&path, invalid).unwrap_err();
+            std::fs::write(&path, serde_json::to_string(invalid).unwrap()).unwrap();
+            let load_error = FileSnapshotProvider::load(&path).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+            [save_error, load_error]
+        };
+        for error in rejections(&missing_net) {
+            assert!(matches!(
+                error,
+                UniswapV3MathError::MissingLiquidityNet { tick: 600 }
+            ));
+        }
+        for error in rejections(&unaligned) {
+            assert!(matches!(
+                error,
+                UniswapV3MathError::TickNotAligned { tick: 601, .. }
+            ));
+        }
+        for error in rejections(&unset_bit)
+            .into_iter()
+            .chain(rejections(&unsorted))
+        {
+            assert!(matches!(error, UniswapV3MathError::InvalidSnapshot { .. }));
+        }
+        for error in rejections(&price_mismatch) {
+            assert!(matches!(
+                error,
+                UniswapV3MathError::TickPriceMismatch { .. }
+            ));
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    occupancy::OccupancySummary,
+    tick_bitmap::position,
+    tick_math::{calculate_compressed, validate_tick, validate_tick_range},
+    utils::RUINT_ONE,
+    TicksProvider,
+};
+
+// Tick data held in memory, for tests and offline simulations. Words that are not present read as
+// empty, ticks that are not present fail unless missing_ticks_as_zero is set.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryTicksProvider {
+    pub words: HashMap<i16, U256>,
+    pub liquidity_nets: HashMap<i32, i128>,
+    pub missing_ticks_as_zero: bool,
+    // liquidityGross of the ticks apply_mint initialized. Ticks that were initialized some other way
+    // have positions apply_burn doesn't know about, so it never uninitializes them.
+    pub liquidity_gross: HashMap<i32, u128>,
+}
+
+impl MemoryTicksProvider {
+    pub fn from_words_and_nets(
+        words: impl IntoIterator<Item = (i16, U256)>,
+        liquidity_nets: impl IntoIterator<Item = (i32, i128)>,
+    ) -> Self {
+        MemoryTicksProvider {
+            words: words.into_iter().collect(),
+            liquidity_nets: liquidity_nets.into_iter().collect(),
+            missing_ticks_as_zero: false,
+            liquidity_gross: HashMap::new(),
+        }
+    }
+
+    // Builds the bitmap from (tick, liquidity_net) pairs. Ticks listed more than once, like the
+    // shared boundary of two positions, have their nets added up.
+    pub fn from_initialized_ticks(
+        ticks: &[(i32, i128)],
+        tick_spacing: i32,
+    ) -> Result<Self, UniswapV3MathError> {
+        let mut provider = MemoryTicksProvider::default();
+
+        for &(tick, liquidity_net) in ticks {
+            validate_tick(tick, tick_spacing)?;
+
+            let (word_pos, bit_pos) = position(calculate_compressed(tick, tick_spacing));
+            *provider.words.entry(word_pos).or_default() |= RUINT_ONE << bit_pos as usize;
+
+            let net = provider.liquidity_nets.entry(tick).or_default();
+            *net = net
+                .checked_add(liquidity_net)
+                .ok_or(UniswapV3MathError::LiquidityAdd)?;
+        }
+
+        Ok(provider)
+    }
+
+    // Flips tick's bit like TickBitmap.flipTick and records in occupancy whether its word is now
+    // empty. Returns whether tick is initialized afterwards. Only the bitmap changes.
+    pub fn flip_tick(
+        &mut self,
+        tick: i32,
+        tick_spacing: i32,
+        occupancy: Option<&mut OccupancySummary>,
+    ) -> Result<bool, UniswapV3MathError> {
+        validate_tick(tick, tick_spacing)?;
+
+        let (word_pos, bit_pos) = position(calculate_compressed(tick, tick_spacing));
+        let word = self.words.entry(word_pos).or_default();
+        *word ^= RUINT_ONE << bit_pos as usize;
+        let (initialized, now_empty) = (word.bit(bit_pos as usize), *word == U256::ZERO);
+        if now_empty {
+            self.words.remove(&word_pos);
+        }
+        if let Some(occupancy) = occupancy {
+            occupancy.update_word(word_pos, now_empty);
+        }
+
+        Ok(initialized)
+    }
+
+    // Adds a position's liquidity to its ticks like the pool's mint, initializing the ticks it is the
+    // first to use. Pool liquidity is left to the caller.
+    pub fn apply_mint(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        tick_spacing: i32,
+        mut occupancy: Option<&mut OccupancySummary>,
+    ) -> Result<(), UniswapV3MathError> {
+        self.modify_ticks(tick_lower, tick_upper, liquidity, tick_spacing, true)?;
+        for tick in [tick_lower, tick_upper] {
+            if !self.is_initialized(tick, tick_spacing) {
+                self.flip_tick(tick, tick_spacing, occupancy.as_deref_mut())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Removes a position's liquidity from its ticks like the pool's burn, uninitializing the ticks
+    // apply_mint initialized once nothing uses them. Pool liquidity is left to the caller.
+    pub fn apply_burn(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        tick_spacing: i32,
+        mut occupancy: Option<&mut OccupancySummary>,
+    ) -> Result<(), UniswapV3MathError> {
+        self.modify_ticks(tick_lower, tick_upper, liquidity, tick_spacing, false)?;
+        for tick in [tick_lower, tick_upper] {
+            if self.liquidity_gross.get(&tick) == Some(&0) {
+                self.liquidity_gross.remove(&tick);
+                self.liquidity_nets.remove(&tick);
+                self.flip_tick(tick, tick_spacing, occupancy.as_deref_mut())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_initialized(&self, tick: i32, tick_spacing: i32) -> bool {
+        let (word_pos, bit_pos) = position(calculate_compressed(tick, tick_spacing));
+        self.words
+            .get(&word_pos)
+            .is_some_and(|word| word.bit(bit_pos as usize))
+    }
+
+    // Updates the liquidity_net and tracked liquidity_gross of both ticks, or neither when either
+    // would overflow
+    fn modify_ticks(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        tick_spacing: i32,
+        mint: bool,
+    ) -> Result<(), UniswapV3MathError> {
+        validate_tick_range(tick_lower, tick_upper, tick_spacing)?;
+        let delta = i128::try_from(liquidity).map_err(|_| UniswapV3MathError::LiquidityAdd)?;
+
+        let mut updates = vec![];
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            let initialized = self.is_initialized(tick, tick_spacing);
+            let liquidity_net = match self.liquidity_nets.get(&tick) {
+                Some(liquidity_net) => *liquidity_net,
+                None if !initialized || self.missing_ticks_as_zero => 0,
+                None => return Err(UniswapV3MathError::MissingLiquidityNet { tick }),
+            };
+            //the lower tick gains liquidity_net on a mint and the upper one loses it
+            let liquidity_net = if mint != upper {
+                liquidity_net.checked_add(delta)
+            } else {
+                liquidity_net.checked_sub(delta)
+            }
+            .ok_or(if mint {
+                UniswapV3MathError::LiquidityAdd
+            } else {
+                UniswapV3MathError::LiquiditySub
+            })?;
+
+            // Ticks this provider didn't initialize keep no liquidity_gross, it can't be known
+            let liquidity_gross = match (self.liquidity_gross.get(&tick), initialized) {
+                (Some(gross), _) if mint => Some(
+                    gross
+                        .checked_add(liquidity)
+                        .ok_or(UniswapV3MathError::LiquidityAdd)?,
+                ),
+                (Some(gross), _) => Some(
+                    gross
+                        .checked_sub(liquidity)
+                        .ok_or(UniswapV3MathError::LiquiditySub)?,
+                ),
+                (None, false) if mint => Some(liquidity),
+                (None, false) => return Err(UniswapV3MathError::LiquiditySub),
+                (None, true) => None,
+            };
+            updates.push((tick, liquidity_net, liquidity_gross));
+        }
+
+        for (tick, liquidity_net, liquidity_gross) in updates {
+            self.liquidity_nets.insert(tick, liquidity_net);
+            if let Some(liquidity_gross) = liquidity_gross {
+                self.liquidity_gross.insert(tick, liquidity_gross);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TicksProvider for MemoryTicksProvider {
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        Ok(self.words.get(&position).copied().unwrap_or_default())
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        match self.liquidity_nets.get(&tick) {
+            Some(liquidity_net) => Ok(*liquidity_net),
+            None if self.missing_ticks_as_zero => Ok(0),
+            None => Err(UniswapV3MathError::MissingLiquidityNet { tick }),
+        }
+    }
+
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        let mut positions = self
+            .words
+            .iter()
+            .filter(|(_, word)| **word != U256::ZERO)
+            .map(|(position, _)| *position);
+        let first = positions.next();
+
+        Some(match first {
+            Some(first) => positions.fold(first..=first, |range, position| {
+                (*range.start()).min(position)..=(*range.end()).max(position)
+            }),
+            #[allow(clippy::reversed_empty_ranges)]
+            None => 1..=0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::{test::fixture, tick_math::MAX_TICK, Math};
+
+    #[test]
+    fn test_from_initialized_ticks() {
+        let provider = MemoryTicksProvider::from_initialized_ticks(
+            &[(-60, 10), (0, 5), (15300, -5), (15360, -10), (-60, 1)],
+            60,
+        )
+        .unwrap();
+
+        //compressed -1 is the last bit of word -1, 255 the last of word 0 and 256 the first of word 1
+        assert_eq!(
+            provider.get_word_at_position(-1).unwrap(),
+            RUINT_ONE << 255usize
+        );
+        assert_eq!(
+            provider.get_word_at_position(0).unwrap(),
+            RUINT_ONE | (RUINT_ONE << 255usize)
+        );
+        assert_eq!(provider.get_word_at_position(1).unwrap(), RUINT_ONE);
+        assert_eq!(provider.get_word_at_position(2).unwrap(), U256::ZERO);
+        assert_eq!(provider.initialized_word_range(), Some(-1..=1));
+        let empty = MemoryTicksProvider::default().initialized_word_range();
+        assert!(empty.unwrap().is_empty());
+
+        //repeated ticks add up
+        assert_eq!(provider.get_liquidity_net_at_tick(-60).unwrap(), 11);
+        assert_eq!(provider.get_liquidity_net_at_tick(15360).unwrap(), -10);
+
+        let result = provider.get_liquidity_net_at_tick(120);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::MissingLiquidityNet { tick: 120 }
+        ));
+
+        let provider = MemoryTicksProvider {
+            missing_ticks_as_zero: true,
+            ..provider
+        };
+        assert_eq!(provider.get_liquidity_net_at_tick(120).unwrap(), 0);
+
+        //ticks must be on the spacing and within bounds
+        let result = MemoryTicksProvider::from_initialized_ticks(&[(-30, 1)], 60);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::TickNotAligned {
+                tick: -30,
+                spacing: 60
+            }
+        ));
+        let result = MemoryTicksProvider::from_initialized_ticks(&[(MAX_TICK + 1, 1)], 1);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::TickOutOfRange { tick } if tick == MAX_TICK + 1
+        ));
+
+        let result = MemoryTicksProvider::from_initialized_ticks(&[(0, i128::MAX), (0, 1)], 1);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::LiquidityAdd
+        ));
+    }
+
+    #[test]
+    fn test_apply_mint_burn() {
+        let mut provider = MemoryTicksProvider::default();
+        provider.apply_mint(-120, 60, 100, 60, None).unwrap();
+        provider.apply_mint(60, 600, 100, 60, None).unwrap();
+        assert_eq!(provider.get_liquidity_net_at_tick(-120).unwrap(), 100);
+        //60 is the upper tick of one position and the lower of the other, its net cancels out
+        assert_eq!(provider.get_liquidity_net_at_tick(60).unwrap(), 0);
+        assert_eq!(provider.get_liquidity_net_at_tick(600).unwrap(), -100);
+
+        //and it stays initialized until both are burned, like liquidityGross in the pool
+        provider.apply_burn(-120, 60, 100, 60, None).unwrap();
+        assert_eq!(
+            provider.get_word_at_position(0).unwrap(),
+            (RUINT_ONE << 1usize) | (RUINT_ONE << 10usize)
+        );
+        assert_eq!(provider.get_word_at_position(-1).unwrap(), U256::ZERO);
+        assert_eq!(provider.get_liquidity_net_at_tick(60).unwrap(), 100);
+        provider.apply_burn(60, 600, 100, 60, None).unwrap();
+        assert_eq!(provider, MemoryTicksProvider::default());
+
+        //ticks from elsewhere keep their bits, their other positions aren't known
+        let mut provider =
+            MemoryTicksProvider::from_initialized_ticks(&[(0, 50), (60, -50)], 60).unwrap();
+        provider.apply_mint(0, 60, 10, 60, None).unwrap();
+        provider.apply_burn(0, 60, 10, 60, None).unwrap();
+        assert_eq!(
+            provider,
+            MemoryTicksProvider::from_initialized_ticks(&[(0, 50), (60, -50)], 60).unwrap()
+        );
+
+        //nothing changes when a tick can't be updated
+        let result = provider.apply_burn(-60, 0, 10, 60, None);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::LiquiditySub
+        ));
+        let result = provider.apply_mint(0, 60, u128::MAX, 60, None);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::LiquidityAdd
+        ));
+        let result = provider.apply_mint(60, 0, 10, 60, None);
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::InvalidTickRange { .. }
+        ));
+        assert_eq!(
+            provider,
+            MemoryTicksProvider::from_initialized_ticks(&[(0, 50), (60, -50)], 60).unwrap()
+        );
+
+        //flipping twice is a no-op
+        assert!(provider.flip_tick(120, 60, None).unwrap());
+        assert!(!provider.flip_tick(120, 60, None).unwrap());
+        assert_eq!(
+            provider.get_word_at_position(0).unwrap(),
+            RUINT_ONE | (RUINT_ONE << 1usize)
+        );
+    }
+
+    #[test]
+    fn test_memory_provider_simulation() {
+        let expected = fixture();
+        let ticks = expected
+            .provider
+            .liquidity_nets
+            .iter()
+            .map(|(tick, liquidity_net)| (*tick, *liquidity_net))
+            .collect::<Vec<_>>();
+
+        let provider = MemoryTicksProvider::from_initialized_ticks(&ticks, 60).unwrap();
+        assert_eq!(provider.words, expected.provider.words);
+
+        let from_words = MemoryTicksProvider::from_words_and_nets(
+            provider.words.clone(),
+            provider.liquidity_nets.clone(),
+        );
+        assert_eq!(from_words, provider);
+
+        let math = Math {
+            fee: expected.fee,
+            fee_protocol: expected.fee_protocol,
+            liquidity: expected.liquidity,
+            sqrt_price_x96: expected.sqrt_price_x96,
+            tick: expected.tick,
+            tick_spacing: expected.tick_spacing,
+            provider,
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        };
+
+        for zero_for_one in [true, false] {
+            for amount_in in [
+                uint!(100000000000000000_U256),
+                uint!(500000000000000000_U256),
+                uint!(1000000000000000000_U256),
+            ] {
+                assert_eq!(
+                    math.simulate_swap(zero_for_one, amount_in).unwrap(),
+                    expected.simulate_swap(zero_for_one, amount_in).unwrap()
+                );
+            }
+        }
+    }
+}
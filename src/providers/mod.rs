@@ -0,0 +1,94 @@
+// TicksProvider implementations
+mod block_cached;
+mod cached;
+#[cfg(feature = "ethers")]
+mod ethers;
+#[cfg(feature = "serde")]
+mod file;
+mod memory;
+#[cfg(feature = "reth-db")]
+mod reth;
+#[cfg(feature = "revm")]
+mod revm;
+#[cfg(feature = "rpc")]
+mod rpc;
+#[cfg(feature = "serde")]
+mod subgraph;
+
+pub use block_cached::{BlockCachedProvider, BlockPinnedProvider, BlockTicksProvider};
+pub use cached::CachedTicksProvider;
+#[cfg(feature = "ethers")]
+pub use ethers::EthersTicksProvider;
+#[cfg(feature = "serde")]
+pub use file::FileSnapshotProvider;
+pub use memory::MemoryTicksProvider;
+#[cfg(feature = "reth-db")]
+pub use reth::RethTicksProvider;
+#[cfg(feature = "revm")]
+pub use revm::RevmTicksProvider;
+#[cfg(feature = "rpc")]
+pub use rpc::{MulticallRpcTicksProvider, RpcTicksProvider};
+#[cfg(feature = "serde")]
+pub use subgraph::{SubgraphTicks, UnalignedTicks};
+
+#[cfg(any(feature = "rpc", feature = "ethers"))]
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+#[cfg(any(feature = "rpc", feature = "ethers"))]
+use crate::error::UniswapV3MathError;
+
+// Runs an async provider's read on the handle attached with with_handle, for its sync
+// TicksProvider. On a multi-thread runtime's worker the worker is handed over to blocking code
+// first, so the sync path also works from async tasks. A current_thread runtime has no other
+// thread to run its tasks on and fails with BlockingInRuntime instead of panicking.
+#[cfg(any(feature = "rpc", feature = "ethers"))]
+fn block_on<F: std::future::Future>(
+    handle: Option<&Handle>,
+    future: F,
+) -> Result<F::Output, UniswapV3MathError> {
+    let handle = handle.ok_or(UniswapV3MathError::MissingRuntimeHandle)?;
+    if Handle::try_current()
+        .is_ok_and(|current| current.runtime_flavor() == RuntimeFlavor::CurrentThread)
+    {
+        return Err(UniswapV3MathError::BlockingInRuntime);
+    }
+
+    Ok(tokio::task::block_in_place(|| handle.block_on(future)))
+}
+
+#[cfg(all(test, any(feature = "rpc", feature = "ethers")))]
+mod test {
+    use tokio::runtime::Handle;
+
+    use super::block_on;
+    use crate::error::UniswapV3MathError;
+
+    #[test]
+    fn test_block_on() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert_eq!(block_on(Some(runtime.handle()), async { 1 }).unwrap(), 1);
+        assert!(matches!(
+            block_on(None, async { 1 }).unwrap_err(),
+            UniswapV3MathError::MissingRuntimeHandle
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_block_on_multi_thread_task() {
+        //the sync path called straight from an async task
+        let handle = Handle::current();
+        assert_eq!(block_on(Some(&handle), async { 1 }).unwrap(), 1);
+        let handle = Handle::current();
+        let result = tokio::task::spawn_blocking(move || block_on(Some(&handle), async { 2 }));
+        assert_eq!(result.await.unwrap().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_block_on_current_thread_task() {
+        let handle = Handle::current();
+        assert!(matches!(
+            block_on(Some(&handle), async { 1 }).unwrap_err(),
+            UniswapV3MathError::BlockingInRuntime
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use alloy_primitives::{Address, B256};
+use reth_primitives::{BlockNumber, U256};
+use reth_provider::{StateProvider, StateProviderBox, StateProviderFactory};
+
+use crate::{
+    error::UniswapV3MathError,
+    storage::{liquidity_net_from_word, tick_bitmap_slot, ticks_slot},
+    TicksProvider,
+};
+
+// Reads a pool's tick data from a reth state provider, so simulations inside a node or an ExEx
+// need no network hop. Storage the state doesn't have reads as zero.
+#[derive(Debug)]
+pub struct RethTicksProvider<State> {
+    state: State,
+    pool: Address,
+}
+
+impl<State> RethTicksProvider<State>
+where
+    State: StateProvider,
+{
+    pub fn new(state: State, pool: Address) -> Self {
+        RethTicksProvider { state, pool }
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    fn storage(&self, slot: U256) -> Result<U256, UniswapV3MathError> {
+        let value = self
+            .state
+            .storage(self.pool, B256::from(slot))
+            .map_err(UniswapV3MathError::provider)?;
+        Ok(value.unwrap_or_default())
+    }
+}
+
+impl RethTicksProvider<StateProviderBox> {
+    // Opens the state at block_number, or the latest state when None
+    pub fn from_factory(
+        factory: &impl StateProviderFactory,
+        pool: Address,
+        block_number: Option<BlockNumber>,
+    ) -> Result<Self, UniswapV3MathError> {
+        let state = match block_number {
+            Some(block_number) => factory.history_by_block_number(block_number),
+            None => factory.latest(),
+        }
+        .map_err(UniswapV3MathError::provider)?;
+
+        Ok(RethTicksProvider::new(state, pool))
+    }
+}
+
+impl<State> TicksProvider for RethTicksProvider<State>
+where
+    State: StateProvider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.storage(tick_bitmap_slot(position))
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        let word = self.storage(ticks_slot(tick))?;
+        Ok(liquidity_net_from_word(word))
+    }
+}
+
+#[cfg(all(test, feature = "reth-db-test-utils"))]
+mod test {
+    use alloy_primitives::{address, Address, B256};
+    use reth_primitives::U256;
+    use reth_provider::test_utils::{ExtendedAccount, MockEthProvider};
+    use ruint::uint;
+
+    use super::RethTicksProvider;
+    use crate::TicksProvider;
+
+    const POOL: Address = address!("88e6a0c2ddd26feeb64f039a2c41296fcb3f5640");
+
+    #[test]
+    fn test_reth_provider() {
+        //slots worked out by hand from keccak256(abi.encode(key, slot)), negative keys sign extended
+        let word_minus_1_slot =
+            uint!(0x63187d71e139eee983a88d0737447c7451979b3dbb75903c76b5fe430d36588e_U256);
+        let tick_minus_600_slot =
+            uint!(0x4fad0605d72150cd0356aa715f173545037cc44027ed9e05328ba670878f2080_U256);
+        let tick_600_slot =
+            uint!(0x87ab7a5a3e42e9ce80c9bfd4beffdff114c758e71396b4ddbbdcc38c72c3a00c_U256);
+
+        //Tick.Info's first word, liquidityNet as two's complement above liquidityGross
+        let storage = [
+            (word_minus_1_slot, U256::from(1) << 246usize),
+            (
+                tick_minus_600_slot,
+                U256::from(2_000_u128) << 128usize | U256::from(3_000),
+            ),
+            (
+                tick_600_slot,
+                U256::from(-2_000_i128 as u128) << 128usize | U256::from(2_000),
+            ),
+        ];
+        let factory = MockEthProvider::default();
+        factory.add_account(
+            POOL,
+            ExtendedAccount::new(1, U256::ZERO).extend_storage(
+                storage
+                    .into_iter()
+                    .map(|(slot, value)| (B256::from(slot), value)),
+            ),
+        );
+
+        let provider = RethTicksProvider::from_factory(&factory, POOL, None).unwrap();
+        assert_eq!(provider.pool(), POOL);
+        assert_eq!(
+            provider.get_word_at_position(-1).unwrap(),
+            U256::from(1) << 246usize
+        );
+        assert_eq!(provider.get_liquidity_net_at_tick(-600).unwrap(), 2_000);
+        assert_eq!(provider.get_liquidity_net_at_tick(600).unwrap(), -2_000);
+
+        //unset storage is zero
+        assert_eq!(provider.get_word_at_position(0).unwrap(), U256::ZERO);
+        assert_eq!(provider.get_liquidity_net_at_tick(60).unwrap(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use alloy_primitives::Address;
+use reth_primitives::U256;
+use revm::DatabaseRef;
+
+use crate::{
+    error::UniswapV3MathError,
+    storage::{liquidity_net_from_word, tick_bitmap_slot, ticks_slot},
+    TicksProvider,
+};
+
+// Reads a pool's tick data from the storage of a revm database, e.g. a local fork, without going
+// over RPC. Storage the database doesn't have reads as zero like it does for the EVM.
+#[derive(Debug, Clone, Copy)]
+pub struct RevmTicksProvider<'a, DB> {
+    db: &'a DB,
+    pool: Address,
+}
+
+impl<'a, DB> RevmTicksProvider<'a, DB>
+where
+    DB: DatabaseRef,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    pub fn new(db: &'a DB, pool: Address) -> Self {
+        RevmTicksProvider { db, pool }
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    fn storage(&self, slot: U256) -> Result<U256, UniswapV3MathError> {
+        self.db
+            .storage_ref(self.pool, slot)
+            .map_err(UniswapV3MathError::provider)
+    }
+}
+
+impl<DB> TicksProvider for RevmTicksProvider<'_, DB>
+where
+    DB: DatabaseRef,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.storage(tick_bitmap_slot(position))
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        let word = self.storage(ticks_slot(tick))?;
+        Ok(liquidity_net_from_word(word))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, Address};
+    use reth_primitives::U256;
+    use revm::db::InMemoryDB;
+    use ruint::uint;
+
+    use super::RevmTicksProvider;
+    use crate::TicksProvider;
+
+    const POOL: Address = address!("88e6a0c2ddd26feeb64f039a2c41296fcb3f5640");
+
+    // Tick.Info's first word, liquidityNet as two's complement above liquidityGross
+    fn tick_word(liquidity_gross: u128, liquidity_net: i128) -> U256 {
+        U256::from(liquidity_net as u128) << 128usize | U256::from(liquidity_gross)
+    }
+
+    #[test]
+    fn test_revm_provider() {
+        //slots worked out by hand from keccak256(abi.encode(key, slot)), negative keys sign extended
+        let word_minus_1_slot =
+            uint!(0x63187d71e139eee983a88d0737447c7451979b3dbb75903c76b5fe430d36588e_U256);
+        let word_0_slot =
+            uint!(0x54cdd369e4e8a8515e52ca72ec816c2101831ad1f18bf44102ed171459c9b4f8_U256);
+        let tick_minus_600_slot =
+            uint!(0x4fad0605d72150cd0356aa715f173545037cc44027ed9e05328ba670878f2080_U256);
+        let tick_600_slot =
+            uint!(0x87ab7a5a3e42e9ce80c9bfd4beffdff114c758e71396b4ddbbdcc38c72c3a00c_U256);
+
+        let mut db = InMemoryDB::default();
+        let storage = [
+            (word_minus_1_slot, U256::from(1) << 246usize),
+            (word_0_slot, U256::from(1) << 10usize),
+            (tick_minus_600_slot, tick_word(3_000, 2_000)),
+            (tick_600_slot, tick_word(2_000, -2_000)),
+        ];
+        for (slot, value) in storage {
+            db.insert_account_storage(POOL, slot, value).unwrap();
+        }
+
+        let provider = RevmTicksProvider::new(&db, POOL);
+        assert_eq!(provider.pool(), POOL);
+        assert_eq!(
+            provider.get_word_at_position(-1).unwrap(),
+            U256::from(1) << 246usize
+        );
+        assert_eq!(
+            provider.get_word_at_position(0).unwrap(),
+            U256::from(1) << 10usize
+        );
+        assert_eq!(provider.get_liquidity_net_at_tick(-600).unwrap(), 2_000);
+        assert_eq!(provider.get_liquidity_net_at_tick(600).unwrap(), -2_000);
+
+        //unset storage is zero, in particular the positive twins of the negative keys
+        assert_eq!(provider.get_word_at_position(1).unwrap(), U256::ZERO);
+        assert_eq!(provider.get_liquidity_net_at_tick(-60).unwrap(), 0);
+        assert_eq!(provider.get_liquidity_net_at_tick(60).unwrap(), 0);
+
+        //another pool's storage is not read
+        let other = RevmTicksProvider::new(&db, Address::ZERO);
+        assert_eq!(other.get_word_at_position(0).unwrap(), U256::ZERO);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock, PoisonError, RwLock,
+    },
+};
+
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::Provider;
+use alloy_rpc_types::{BlockId, TransactionRequest};
+use reth_primitives::U256;
+use tokio::runtime::Handle;
+
+use crate::{
+    async_provider::AsyncTicksProvider,
+    error::UniswapV3MathError,
+    multicall::{
+        decode_aggregate3, decode_liquidity_net, decode_tick_bitmap, encode_aggregate3,
+        tick_bitmap_call, ticks_call, MULTICALL3,
+    },
+    storage::{liquidity_net_from_word, tick_bitmap_slot, ticks_slot},
+    tick_bitmap::initialized_ticks,
+    TicksProvider,
+};
+
+// Reads a pool's tick data with eth_getStorageAt at a fixed block, so repeated simulations see the
+// same state. The provider is async; to use it through the sync TicksProvider, attach a runtime
+// handle with with_handle. Sync reads from a current_thread runtime's thread fail with
+// BlockingInRuntime.
+pub struct RpcTicksProvider<P> {
+    provider: P,
+    pool: Address,
+    block_id: BlockId,
+    handle: Option<Handle>,
+    requests: AtomicUsize,
+}
+
+impl<P> RpcTicksProvider<P>
+where
+    P: Provider,
+{
+    pub fn new(provider: P, pool: Address, block_id: BlockId) -> Self {
+        RpcTicksProvider {
+            provider,
+            pool,
+            block_id,
+            handle: None,
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn with_handle(mut self, handle: Handle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.block_id
+    }
+
+    // JSON-RPC requests sent so far
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    async fn get_storage_at(&self, slot: U256) -> Result<U256, UniswapV3MathError> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.provider
+            .get_storage_at(self.pool, slot)
+            .block_id(self.block_id)
+            .await
+            .map_err(UniswapV3MathError::provider)
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> Result<F::Output, UniswapV3MathError> {
+        super::block_on(self.handle.as_ref(), future)
+    }
+}
+
+impl<P> AsyncTicksProvider for RpcTicksProvider<P>
+where
+    P: Provider,
+{
+    async fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.get_storage_at(tick_bitmap_slot(position)).await
+    }
+
+    async fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        let word = self.get_storage_at(ticks_slot(tick)).await?;
+        Ok(liquidity_net_from_word(word))
+    }
+}
+
+impl<P> TicksProvider for RpcTicksProvider<P>
+where
+    P: Provider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.block_on(AsyncTicksProvider::get_word_at_position(self, position))?
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.block_on(AsyncTicksProvider::get_liquidity_net_at_tick(self, tick))?
+    }
+}
+
+const DEFAULT_MULTICALL_WORDS_PER_BATCH: usize = 4;
+const DEFAULT_MULTICALL_MAX_CALLS: usize = 500;
+
+// RpcTicksProvider reading through Multicall3's aggregate3. Each window of words the swap loop
+// prefetches (words_per_batch) is one eth_call, followed by one for the liquidity_nets of every
+// initialized tick in those words, which crossings are then served from. Calls beyond max_calls
+// are split over several eth_calls, results keep the requested order. Without a multicall contract
+// at the block it falls back to one eth_getStorageAt per word or liquidity_net.
+pub struct MulticallRpcTicksProvider<P> {
+    rpc: RpcTicksProvider<P>,
+    multicall: Address,
+    tick_spacing: i32,
+    words_per_batch: usize,
+    max_calls: usize,
+    multicall_available: OnceLock<bool>,
+    liquidity_nets: RwLock<HashMap<i32, i128>>,
+}
+
+impl<P> MulticallRpcTicksProvider<P>
+where
+    P: Provider,
+{
+    pub fn new(provider: P, pool: Address, block_id: BlockId, tick_spacing: i32) -> Self {
+        MulticallRpcTicksProvider {
+            rpc: RpcTicksProvider::new(provider, pool, block_id),
+            multicall: MULTICALL3,
+            tick_spacing,
+            words_per_batch: DEFAULT_MULTICALL_WORDS_PER_BATCH,
+            max_calls: DEFAULT_MULTICALL_MAX_CALLS,
+            multicall_available: OnceLock::new(),
+            liquidity_nets: RwLock::default(),
+        }
+    }
+
+    pub fn with_handle(mut self, handle: Handle) -> Self {
+        self.rpc = self.rpc.with_handle(handle);
+        self
+    }
+
+    // For chains where Multicall3 lives at another address
+    pub fn with_multicall(mut self, multicall: Address) -> Self {
+        self.multicall = multicall;
+        self
+    }
+
+    pub fn with_words_per_batch(mut self, words_per_batch: usize) -> Self {
+        self.words_per_batch = words_per_batch.max(1);
+        self
+    }
+
+    // Most calls sent in one eth_call, to stay under the node's gas and response size limits
+    pub fn with_max_calls(mut self, max_calls: usize) -> Self {
+        self.max_calls = max_calls.max(1);
+        self
+    }
+
+    pub fn pool(&self) -> Address {
+        self.rpc.pool
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.rpc.block_id
+    }
+
+    // JSON-RPC requests sent so far, including the one checking for the multicall contract
+    pub fn requests(&self) -> usize {
+        self.rpc.requests()
+    }
+
+    async fn multicall_available(&self) -> Result<bool, UniswapV3MathError> {
+        if let Some(available) = self.multicall_available.get() {
+            return Ok(*available);
+        }
+
+        self.rpc.requests.fetch_add(1, Ordering::Relaxed);
+        let code = self
+            .rpc
+            .provider
+            .get_code_at(self.multicall)
+            .block_id(self.rpc.block_id)
+            .await
+            .map_err(UniswapV3MathError::provider)?;
+        Ok(*self.multicall_available.get_or_init(|| !code.is_empty()))
+    }
+
+    async fn aggregate(&self, calls: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, UniswapV3MathError> {
+        let mut results = Vec::with_capacity(calls.len());
+        for chunk in calls.chunks(self.max_calls) {
+            let tx = TransactionRequest::default()
+                .to(self.multicall)
+                .input(Bytes::from(encode_aggregate3(self.rpc.pool, chunk)).into());
+            self.rpc.requests.fetch_add(1, Ordering::Relaxed);
+            let data = self
+                .rpc
+                .provider
+                .call(tx)
+                .block(self.rpc.block_id)
+                .await
+                .map_err(UniswapV3MathError::provider)?;
+            results.extend(decode_aggregate3(&data)?);
+        }
+
+        if results.len() != calls.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: calls.len(),
+                got: results.len(),
+            });
+        }
+        Ok(results)
+    }
+
+    async fn get_words_async(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+        if !self.multicall_available().await? {
+            let mut words = Vec::with_capacity(positions.len());
+            for position in positions {
+                words.push(self.rpc.get_storage_at(tick_bitmap_slot(*position)).await?);
+            }
+            return Ok(words);
+        }
+
+        let calls = positions.iter().map(|position| tick_bitmap_call(*position));
+        let words = self
+            .aggregate(calls.collect())
+            .await?
+            .iter()
+            .map(|data| decode_tick_bitmap(data))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The swap loop reads the liquidity_net of each tick it crosses in these words
+        let ticks = positions
+            .iter()
+            .zip(&words)
+            .flat_map(|(&position, &word)| initialized_ticks(position, word, self.tick_spacing))
+            .collect::<Vec<_>>();
+        self.get_liquidity_nets_async(&ticks).await?;
+
+        Ok(words)
+    }
+
+    async fn get_liquidity_nets_async(
+        &self,
+        ticks: &[i32],
+    ) -> Result<Vec<i128>, UniswapV3MathError> {
+        let mut missing = {
+            let cached = self
+                .liquidity_nets
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            ticks
+                .iter()
+                .filter(|tick| !cached.contains_key(tick))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        missing.sort_unstable();
+        missing.dedup();
+
+        if !missing.is_empty() {
+            let liquidity_nets = if self.multicall_available().await? {
+                let calls = missing.iter().map(|tick| ticks_call(*tick));
+                self.aggregate(calls.collect())
+                    .await?
+                    .iter()
+                    .map(|data| decode_liquidity_net(data))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                let mut liquidity_nets = Vec::with_capacity(missing.len());
+                for tick in &missing {
+                    let word = self.rpc.get_storage_at(ticks_slot(*tick)).await?;
+                    liquidity_nets.push(liquidity_net_from_word(word));
+                }
+                liquidity_nets
+            };
+
+            self.liquidity_nets
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend(missing.into_iter().zip(liquidity_nets));
+        }
+
+        let cached = self
+            .liquidity_nets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(ticks.iter().map(|tick| cached[tick]).collect())
+    }
+}
+
+impl<P> AsyncTicksProvider for MulticallRpcTicksProvider<P>
+where
+    P: Provider,
+{
+    async fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        Ok(self.get_words_async(&[position]).await?[0])
+    }
+
+    async fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        Ok(self.get_liquidity_nets_async(&[tick]).await?[0])
+    }
+}
+
+impl<P> TicksProvider for MulticallRpcTicksProvider<P>
+where
+    P: Provider,
+{
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.rpc
+            .block_on(AsyncTicksProvider::get_word_at_position(self, position))?
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.rpc
+            .block_on(AsyncTicksProvider::get_liquidity_net_at_tick(self, tick))?
+    }
+
+    fn get_words(&self, positions: &[i16]) -> Result<Vec<U256>, UniswapV3MathError> {
+        self.rpc.block_on(self.get_words_async(positions))?
+    }
+
+    fn get_liquidity_nets(&self, ticks: &[i32]) -> Result<Vec<i128>, UniswapV3MathError> {
+        self.rpc.block_on(self.get_liquidity_nets_async(ticks))?
+    }
+
+    fn words_per_batch(&self) -> usize {
+        self.words_per_batch
+    }
+}
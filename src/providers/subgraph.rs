@@ -0,0 +1,168 @@
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use reth_primitives::U256;
+use serde::{de, Deserialize, Deserializer};
+
+use super::MemoryTicksProvider;
+use crate::{error::UniswapV3MathError, TicksProvider};
+
+// What to do with subgraph ticks that are not a multiple of the tick spacing, which the pool can't
+// have initialized
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnalignedTicks {
+    #[default]
+    Error,
+    // Leave them out, SubgraphTicks::skipped_ticks lists them
+    Skip,
+}
+
+// Tick data from the Uniswap subgraph's ticks query, a JSON array of
+// { tickIdx, liquidityNet, liquidityGross } with the numbers as BigInt strings or plain numbers.
+// Ticks with no liquidityGross left are not initialized in the pool and are left out of the bitmap.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SubgraphTicks {
+    provider: MemoryTicksProvider,
+    skipped_ticks: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubgraphTick {
+    #[serde(deserialize_with = "string_or_number")]
+    tick_idx: i32,
+    #[serde(deserialize_with = "string_or_number")]
+    liquidity_net: i128,
+    #[serde(deserialize_with = "string_or_number")]
+    liquidity_gross: u128,
+}
+
+impl SubgraphTicks {
+    pub fn from_json(json: &str, tick_spacing: i32) -> Result<Self, UniswapV3MathError> {
+        SubgraphTicks::from_json_with(json, tick_spacing, UnalignedTicks::Error)
+    }
+
+    pub fn from_json_with(
+        json: &str,
+        tick_spacing: i32,
+        unaligned_ticks: UnalignedTicks,
+    ) -> Result<Self, UniswapV3MathError> {
+        if tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing });
+        }
+
+        let ticks: Vec<SubgraphTick> =
+            serde_json::from_str(json).map_err(UniswapV3MathError::provider)?;
+
+        let mut initialized_ticks = vec![];
+        let mut skipped_ticks = vec![];
+        for tick in ticks {
+            if tick.liquidity_gross == 0 {
+                continue;
+            }
+            if tick.tick_idx % tick_spacing != 0 && unaligned_ticks == UnalignedTicks::Skip {
+                skipped_ticks.push(tick.tick_idx);
+                continue;
+            }
+            initialized_ticks.push((tick.tick_idx, tick.liquidity_net));
+        }
+
+        Ok(SubgraphTicks {
+            provider: MemoryTicksProvider::from_initialized_ticks(
+                &initialized_ticks,
+                tick_spacing,
+            )?,
+            skipped_ticks,
+        })
+    }
+
+    // Unaligned ticks left out under UnalignedTicks::Skip, in the order they were listed
+    pub fn skipped_ticks(&self) -> &[i32] {
+        &self.skipped_ticks
+    }
+
+    pub fn into_inner(self) -> MemoryTicksProvider {
+        self.provider
+    }
+}
+
+impl TicksProvider for SubgraphTicks {
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        self.provider.get_word_at_position(position)
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.provider.get_liquidity_net_at_tick(tick)
+    }
+
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        self.provider.initialized_word_range()
+    }
+}
+
+// The subgraph returns BigInt fields as strings, accept those as well as plain JSON numbers
+fn string_or_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(value) => value.parse().map_err(de::Error::custom),
+        serde_json::Value::Number(value) => value.to_string().parse().map_err(de::Error::custom),
+        value => Err(de::Error::custom(format!(
+            "expected a number or a string, got {value}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subgraph_ticks() {
+        //strings and numbers mixed, a stale tick without liquidity left
+        let json = r#"[
+            {"tickIdx": "-120", "liquidityNet": "170141183460469231731687303715884105727", "liquidityGross": "170141183460469231731687303715884105727"},
+            {"tickIdx": 60, "liquidityNet": -5, "liquidityGross": 5},
+            {"tickIdx": "120", "liquidityNet": "0", "liquidityGross": "0"}
+        ]"#;
+        let ticks = SubgraphTicks::from_json(json, 60).unwrap();
+        assert_eq!(ticks.get_liquidity_net_at_tick(-120).unwrap(), i128::MAX);
+        assert_eq!(ticks.get_liquidity_net_at_tick(60).unwrap(), -5);
+        assert!(ticks.get_liquidity_net_at_tick(120).is_err());
+        assert_eq!(
+            ticks.get_word_at_position(-1).unwrap(),
+            U256::from(1) << 254usize
+        );
+        assert_eq!(ticks.get_word_at_position(0).unwrap(), U256::from(2));
+        assert_eq!(ticks.initialized_word_range(), Some(-1..=0));
+
+        //unaligned ticks fail unless skipped
+        let json = r#"[
+            {"tickIdx": "60", "liquidityNet": "5", "liquidityGross": "5"},
+            {"tickIdx": "61", "liquidityNet": "5", "liquidityGross": "5"}
+        ]"#;
+        assert!(matches!(
+            SubgraphTicks::from_json(json, 60).unwrap_err(),
+            UniswapV3MathError::TickNotAligned {
+                tick: 61,
+                spacing: 60
+            }
+        ));
+        let ticks = SubgraphTicks::from_json_with(json, 60, UnalignedTicks::Skip).unwrap();
+        assert_eq!(ticks.skipped_ticks(), &[61]);
+        assert_eq!(ticks.get_liquidity_net_at_tick(60).unwrap(), 5);
+
+        for json in [
+            r#"[{"tickIdx": "sixty", "liquidityNet": "5", "liquidityGross": "5"}]"#,
+            r#"[{"tickIdx": 60, "liquidityNet": null, "liquidityGross": "5"}]"#,
+            r#"[{"tickIdx": 60, "liquidityGross": "5"}]"#,
+        ] {
+            assert!(matches!(
+                SubgraphTicks::from_json(json, 60).unwrap_err(),
+                UniswapV3MathError::Provider(_)
+            ));
+        }
+    }
+}
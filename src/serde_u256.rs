@@ -0,0 +1,102 @@
+use reth_primitives::U256;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Serde adapter for U256 fields, used as #[serde(with = "serde_u256")]. Values are written as
+// decimal strings, or as 0x-prefixed hex with the serde-hex feature. Both forms are read back.
+
+pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    if cfg!(feature = "serde-hex") {
+        let hex = format!("{value:x}");
+        let digits = hex.trim_start_matches('0');
+        serializer.serialize_str(&format!(
+            "0x{}",
+            if digits.is_empty() { "0" } else { digits }
+        ))
+    } else {
+        serializer.serialize_str(&value.to_string())
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+// Same format for the bitmap words of a snapshot
+pub mod words {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        words: &[(i16, U256)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            words
+                .iter()
+                .map(|(position, word)| (position, AsString(*word))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(i16, U256)>, D::Error> {
+        let words = Vec::<(i16, AsString)>::deserialize(deserializer)?;
+        Ok(words
+            .into_iter()
+            .map(|(position, word)| (position, word.0))
+            .collect())
+    }
+}
+
+struct AsString(U256);
+
+impl Serialize for AsString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AsString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(AsString)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] U256);
+
+    #[test]
+    fn test_serde_u256() {
+        let value = Wrapper(uint!(79228162514264337593543950336_U256));
+
+        let json = serde_json::to_string(&value).unwrap();
+        if cfg!(feature = "serde-hex") {
+            assert_eq!(json, "\"0x1000000000000000000000000\"");
+        } else {
+            assert_eq!(json, "\"79228162514264337593543950336\"");
+        }
+        assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap(), value);
+
+        //either form is accepted regardless of the output format
+        let decimal = serde_json::from_str::<Wrapper>("\"79228162514264337593543950336\"");
+        assert_eq!(decimal.unwrap(), value);
+        let hex = serde_json::from_str::<Wrapper>("\"0x1000000000000000000000000\"");
+        assert_eq!(hex.unwrap(), value);
+
+        let zero = serde_json::to_string(&Wrapper(U256::ZERO)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Wrapper>(&zero).unwrap().0,
+            U256::ZERO
+        );
+
+        assert!(serde_json::from_str::<Wrapper>("\"-1\"").is_err());
+        assert!(serde_json::from_str::<Wrapper>("79228162514264337593543950336").is_err());
+    }
+}
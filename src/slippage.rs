@@ -0,0 +1,127 @@
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::{mul_div, mul_div_rounding_up},
+    sqrt_price_math::{Q192, Q96},
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    utils::RUINT_ONE,
+    BPS_DENOMINATOR,
+};
+
+// Least output to accept for quote with slippage_bps of tolerance, rounded down. A tolerance of
+// 100% or more accepts any output.
+pub fn min_amount_out(quote: U256, slippage_bps: u32) -> U256 {
+    if slippage_bps as u64 >= BPS_DENOMINATOR {
+        return U256::ZERO;
+    }
+
+    // The result is at most quote, so this can't overflow
+    mul_div(
+        quote,
+        U256::from(BPS_DENOMINATOR - slippage_bps as u64),
+        U256::from(BPS_DENOMINATOR),
+    )
+    .expect("min_amount_out is at most quote")
+}
+
+// sqrtPriceLimitX96 that lets the price move by at most slippage_bps from the current price, down
+// when zero_for_one and up otherwise. Limits past the price bounds are clamped to the furthest
+// limit the pool accepts.
+pub fn sqrt_price_limit_from_slippage(
+    current_sqrt_price_x96: U256,
+    slippage_bps: u32,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    let limit = target_sqrt_price(current_sqrt_price_x96, zero_for_one, slippage_bps)?;
+
+    Ok(if zero_for_one {
+        limit.max(MIN_SQRT_RATIO + RUINT_ONE)
+    } else {
+        limit.min(MAX_SQRT_RATIO - RUINT_ONE)
+    })
+}
+
+// sqrt_price_x96 * sqrt(1 -/+ bps/10000), rounded up when moving down and down when moving up so
+// the price never moves by more than bps. The square root of the factor is taken in Q96 so it never
+// overflows. A move down of 100% or more has no valid price and returns zero.
+pub(crate) fn target_sqrt_price(
+    sqrt_price_x96: U256,
+    zero_for_one: bool,
+    bps: u32,
+) -> Result<U256, UniswapV3MathError> {
+    let bps = U256::from(bps);
+    let denominator = U256::from(BPS_DENOMINATOR);
+
+    if zero_for_one {
+        if bps >= denominator {
+            return Ok(U256::ZERO);
+        }
+        let factor_x192 = mul_div_rounding_up(denominator - bps, Q192, denominator)?;
+        let mut sqrt_factor_x96 = factor_x192.root(2);
+        if sqrt_factor_x96 * sqrt_factor_x96 != factor_x192 {
+            sqrt_factor_x96 += RUINT_ONE;
+        }
+        mul_div_rounding_up(sqrt_price_x96, sqrt_factor_x96, Q96)
+    } else {
+        let factor_x192 = mul_div(denominator + bps, Q192, denominator)?;
+        mul_div(sqrt_price_x96, factor_x192.root(2), Q96)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::tick_math::get_sqrt_ratio_at_tick;
+
+    #[test]
+    fn test_min_amount_out() {
+        let quote = uint!(1000000000000000000_U256);
+        assert_eq!(min_amount_out(quote, 0), quote);
+        assert_eq!(min_amount_out(quote, 50), uint!(995000000000000000_U256));
+
+        //rounded down
+        assert_eq!(min_amount_out(uint!(999_U256), 50), uint!(994_U256));
+        assert_eq!(
+            min_amount_out(U256::MAX, 1),
+            U256::MAX - U256::MAX / uint!(10000_U256) - RUINT_ONE
+        );
+
+        for slippage_bps in [10_000, u32::MAX] {
+            assert_eq!(min_amount_out(quote, slippage_bps), U256::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_sqrt_price_limit_from_slippage() {
+        let sqrt_price_x96 = uint!(112430957613446531369126327961_U256);
+
+        //zero tolerance is the current price
+        for zero_for_one in [true, false] {
+            let limit = sqrt_price_limit_from_slippage(sqrt_price_x96, 0, zero_for_one);
+            assert_eq!(limit.unwrap(), sqrt_price_x96);
+        }
+
+        //1% moves the sqrt price by about 0.5%
+        let limit = sqrt_price_limit_from_slippage(sqrt_price_x96, 100, true).unwrap();
+        assert_eq!(limit, uint!(111867390367246184595006994567_U256));
+        let limit = sqrt_price_limit_from_slippage(sqrt_price_x96, 100, false).unwrap();
+        assert_eq!(limit, uint!(112991713997865244624049470635_U256));
+
+        //100% or more down, and anything past the bounds, is clamped
+        for slippage_bps in [10_000, u32::MAX] {
+            let limit = sqrt_price_limit_from_slippage(sqrt_price_x96, slippage_bps, true);
+            assert_eq!(limit.unwrap(), MIN_SQRT_RATIO + RUINT_ONE);
+        }
+        let near_max = get_sqrt_ratio_at_tick(887000).unwrap();
+        let limit = sqrt_price_limit_from_slippage(near_max, u32::MAX, false);
+        assert_eq!(limit.unwrap(), MAX_SQRT_RATIO - RUINT_ONE);
+        let near_min = MIN_SQRT_RATIO + RUINT_ONE;
+        let limit = sqrt_price_limit_from_slippage(near_min, 5000, true);
+        assert_eq!(limit.unwrap(), MIN_SQRT_RATIO + RUINT_ONE);
+        let limit = sqrt_price_limit_from_slippage(MAX_SQRT_RATIO - RUINT_ONE, 1, false);
+        assert_eq!(limit.unwrap(), MAX_SQRT_RATIO - RUINT_ONE);
+    }
+}
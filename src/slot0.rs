@@ -0,0 +1,151 @@
+use reth_primitives::U256;
+
+use crate::{error::UniswapV3MathError, utils::RUINT_ONE, Math};
+
+// Fields of the pool's slot0, packed into one storage word from the low bits up
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Slot0 {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub observation_index: u16,
+    pub observation_cardinality: u16,
+    pub observation_cardinality_next: u16,
+    pub fee_protocol: u8,
+    pub unlocked: bool,
+}
+
+// Unpacks the raw slot0 storage word: uint160 sqrtPriceX96, int24 tick, uint16 observationIndex,
+// uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked
+pub fn decode_slot0(word: U256) -> Slot0 {
+    let bits = |offset: usize, width: usize| (word >> offset) & ((RUINT_ONE << width) - RUINT_ONE);
+
+    // Move the int24 sign bit into the i32 sign bit and shift back to extend it
+    let tick = ((bits(160, 24).to::<u32>() << 8) as i32) >> 8;
+
+    Slot0 {
+        sqrt_price_x96: bits(0, 160),
+        tick,
+        observation_index: bits(184, 16).to::<u16>(),
+        observation_cardinality: bits(200, 16).to::<u16>(),
+        observation_cardinality_next: bits(216, 16).to::<u16>(),
+        fee_protocol: bits(232, 8).to::<u8>(),
+        unlocked: bits(240, 8) != U256::ZERO,
+    }
+}
+
+impl<Provider> Math<Provider> {
+    // Builds the pool state from a raw slot0 word, checked like Math::builder. fee and tick_spacing
+    // are taken as a deployed pool's, so fork fee tiers are accepted.
+    pub fn from_slot0(
+        slot0_word: U256,
+        liquidity: u128,
+        fee: u32,
+        tick_spacing: i32,
+        provider: Provider,
+    ) -> Result<Self, UniswapV3MathError> {
+        let slot0 = decode_slot0(slot0_word);
+
+        Math::builder()
+            .fee(fee)
+            .fee_protocol(slot0.fee_protocol)
+            .liquidity(liquidity)
+            .sqrt_price_x96(slot0.sqrt_price_x96)
+            .tick(slot0.tick)
+            .tick_spacing(tick_spacing)
+            .allow_custom_fee_tier()
+            .provider(provider)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::{test::fixture, tick_math::get_sqrt_ratio_at_tick};
+
+    //Synthetic words packed by hand from the field values asserted below, not read from any pool.
+    // They cover both tick signs and a set fee switch but say nothing about real storage layout
+    // beyond what the Solidity struct declares.
+    //tick 200000, unlocked, fee switch off
+    const SLOT0_POSITIVE_TICK: U256 =
+        uint!(0x10002d302d300f2030d4000000000000055ff74bf991114d22f9b287b822e_U256);
+    //tick -200000, unlocked, feeProtocol 4 for both tokens
+    const SLOT0_NEGATIVE_TICK: U256 =
+        uint!(0x14400b400b40007fcf2c000000000000000000002fa10ba2022b2319dc873_U256);
+
+    #[test]
+    fn test_decode_slot0() {
+        assert_eq!(
+            decode_slot0(SLOT0_POSITIVE_TICK),
+            Slot0 {
+                sqrt_price_x96: uint!(1744244129640337381386292603617838_U256),
+                tick: 200000,
+                observation_index: 242,
+                observation_cardinality: 723,
+                observation_cardinality_next: 723,
+                fee_protocol: 0,
+                unlocked: true,
+            }
+        );
+        assert_eq!(
+            decode_slot0(SLOT0_NEGATIVE_TICK),
+            Slot0 {
+                sqrt_price_x96: uint!(3598751819609688046946419_U256),
+                tick: -200000,
+                observation_index: 7,
+                observation_cardinality: 180,
+                observation_cardinality_next: 180,
+                fee_protocol: 0x44,
+                unlocked: true,
+            }
+        );
+
+        //the int24 bounds and -1 sign extend
+        for tick in [-1, -(1 << 23), (1 << 23) - 1] {
+            let word = U256::from(tick as u32 & 0xffffff) << 160usize;
+            assert_eq!(decode_slot0(word).tick, tick);
+        }
+        assert_eq!(decode_slot0(U256::ZERO), Slot0::default());
+    }
+
+    #[test]
+    fn test_from_slot0() {
+        let math = Math::from_slot0(SLOT0_NEGATIVE_TICK, 1_000_000, 3000, 60, ()).unwrap();
+        assert_eq!(math.sqrt_price_x96, uint!(3598751819609688046946419_U256));
+        assert_eq!(math.tick, -200000);
+        assert_eq!(math.fee_protocol, 0x44);
+        assert_eq!(math.liquidity, 1_000_000);
+
+        let expected = fixture();
+        let word = expected.sqrt_price_x96 | (U256::from(expected.tick) << 160usize);
+        let math = Math::from_slot0(
+            word,
+            expected.liquidity,
+            expected.fee,
+            expected.tick_spacing,
+            expected.provider.clone(),
+        )
+        .unwrap();
+        let amount_in = uint!(300000000000000000_U256);
+        assert_eq!(
+            math.simulate_swap(true, amount_in).unwrap(),
+            expected.simulate_swap(true, amount_in).unwrap()
+        );
+
+        //fields are validated like the builder
+        let word = get_sqrt_ratio_at_tick(100).unwrap() | (U256::from(200) << 160usize);
+        assert!(matches!(
+            Math::from_slot0(word, 0, 3000, 60, ()),
+            Err(UniswapV3MathError::TickPriceMismatch {
+                tick: 200,
+                price_tick: 100
+            })
+        ));
+        assert!(matches!(
+            Math::from_slot0(U256::ZERO, 0, 3000, 60, ()),
+            Err(UniswapV3MathError::SqrtPriceOutOfRange { .. })
+        ));
+    }
+}
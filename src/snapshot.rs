@@ -0,0 +1,429 @@
+use std::ops::RangeInclusive;
+
+use alloy_primitives::{keccak256, B256};
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    providers::MemoryTicksProvider,
+    tick_bitmap::{initialized_ticks, position},
+    tick_math::{calculate_compressed, max_usable_tick, min_usable_tick, validate_tick},
+    Math, TicksProvider,
+};
+
+// Pool state and tick data in a form that can be stored and loaded back, words and liquidity nets
+// sorted by position and tick. A snapshot is a TicksProvider itself, so recorded pools can be
+// replayed without the provider they were recorded from.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoolSnapshot {
+    pub fee: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fee_protocol: u8,
+    pub tick_spacing: i32,
+    pub liquidity: u128,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_u256"))]
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_u256::words"))]
+    pub words: Vec<(i16, U256)>,
+    pub liquidity_nets: Vec<(i32, i128)>,
+    // Word positions covered by record, reading outside of them fails. None when the snapshot holds
+    // every initialized word, missing words then read as empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recorded_words: Option<RangeInclusive<i16>>,
+}
+
+impl PoolSnapshot {
+    pub fn from_math(math: &Math<MemoryTicksProvider>) -> Self {
+        let mut words = math
+            .provider
+            .words
+            .iter()
+            .map(|(position, word)| (*position, *word))
+            .collect::<Vec<_>>();
+        words.sort_unstable();
+        let mut liquidity_nets = math
+            .provider
+            .liquidity_nets
+            .iter()
+            .map(|(tick, liquidity_net)| (*tick, *liquidity_net))
+            .collect::<Vec<_>>();
+        liquidity_nets.sort_unstable();
+
+        PoolSnapshot {
+            fee: math.fee,
+            fee_protocol: math.fee_protocol,
+            tick_spacing: math.tick_spacing,
+            liquidity: math.liquidity,
+            sqrt_price_x96: math.sqrt_price_x96,
+            tick: math.tick,
+            words,
+            liquidity_nets,
+            recorded_words: None,
+        }
+    }
+
+    // Reads every bitmap word covering tick_range from math's provider, and the liquidity_net of
+    // every initialized tick in them
+    pub fn record<P: TicksProvider>(
+        math: &Math<P>,
+        tick_range: RangeInclusive<i32>,
+    ) -> Result<Self, UniswapV3MathError> {
+        if math.tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing {
+                tick_spacing: math.tick_spacing,
+            });
+        }
+
+        let bound = |tick: i32| {
+            position(calculate_compressed(
+                tick.clamp(
+                    min_usable_tick(math.tick_spacing),
+                    max_usable_tick(math.tick_spacing),
+                ),
+                math.tick_spacing,
+            ))
+            .0
+        };
+        let recorded_words = bound(*tick_range.start())..=bound(*tick_range.end());
+
+        let positions = recorded_words.clone().collect::<Vec<_>>();
+        let words = math.provider.get_words(&positions)?;
+        if words.len() != positions.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: positions.len(),
+                got: words.len(),
+            });
+        }
+        let words = positions.into_iter().zip(words).collect::<Vec<_>>();
+
+        let ticks = words
+            .iter()
+            .flat_map(|&(word_pos, word)| initialized_ticks(word_pos, word, math.tick_spacing))
+            .collect::<Vec<_>>();
+        let liquidity_nets = math.provider.get_liquidity_nets(&ticks)?;
+        if liquidity_nets.len() != ticks.len() {
+            return Err(UniswapV3MathError::ProviderBatchLength {
+                expected: ticks.len(),
+                got: liquidity_nets.len(),
+            });
+        }
+
+        Ok(PoolSnapshot {
+            fee: math.fee,
+            fee_protocol: math.fee_protocol,
+            tick_spacing: math.tick_spacing,
+            liquidity: math.liquidity,
+            sqrt_price_x96: math.sqrt_price_x96,
+            tick: math.tick,
+            words,
+            liquidity_nets: ticks.into_iter().zip(liquidity_nets).collect(),
+            recorded_words: Some(recorded_words),
+        })
+    }
+
+    // The recorded pool on top of the snapshot itself
+    pub fn math(&self) -> Math<&PoolSnapshot> {
+        Math {
+            fee: self.fee,
+            fee_protocol: self.fee_protocol,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            tick_spacing: self.tick_spacing,
+            provider: self,
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        }
+    }
+
+    // Checks the pool state like MathBuilder::build, that words and liquidity nets are sorted without
+    // duplicates, and that the bits set in the words are exactly the ticks with a liquidity_net
+    pub fn validate(&self) -> Result<(), UniswapV3MathError> {
+        Math::builder()
+            .fee(self.fee)
+            .fee_protocol(self.fee_protocol)
+            .liquidity(self.liquidity)
+            .sqrt_price_x96(self.sqrt_price_x96)
+            .tick(self.tick)
+            .tick_spacing(self.tick_spacing)
+            .allow_custom_fee_tier()
+            .build()?;
+
+        if !self.words.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(UniswapV3MathError::InvalidSnapshot {
+                reason: "words are not sorted by position".to_string(),
+            });
+        }
+        if !self
+            .liquidity_nets
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0)
+        {
+            return Err(UniswapV3MathError::InvalidSnapshot {
+                reason: "liquidity nets are not sorted by tick".to_string(),
+            });
+        }
+
+        for &(tick, _) in &self.liquidity_nets {
+            validate_tick(tick, self.tick_spacing)?;
+            let (word_pos, bit_pos) = position(calculate_compressed(tick, self.tick_spacing));
+            if !self.get_word_at_position(word_pos)?.bit(bit_pos as usize) {
+                return Err(UniswapV3MathError::InvalidSnapshot {
+                    reason: format!("tick {tick} has a liquidity_net but is not initialized"),
+                });
+            }
+        }
+        for &(word_pos, word) in &self.words {
+            for bit_pos in (0..256usize).filter(|bit_pos| word.bit(*bit_pos)) {
+                let tick = (word_pos as i32 * 256 + bit_pos as i32) * self.tick_spacing;
+                self.get_liquidity_net_at_tick(tick)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // keccak256 of every field in a fixed big endian layout, independent of how the snapshot was
+    // serialized. Two snapshots hash the same exactly when they are equal.
+    pub fn state_hash(&self) -> B256 {
+        let mut preimage =
+            Vec::with_capacity(96 + self.words.len() * 34 + self.liquidity_nets.len() * 20);
+        preimage.extend_from_slice(&self.fee.to_be_bytes());
+        preimage.push(self.fee_protocol);
+        preimage.extend_from_slice(&self.tick_spacing.to_be_bytes());
+        preimage.extend_from_slice(&self.liquidity.to_be_bytes());
+        preimage.extend_from_slice(&self.sqrt_price_x96.to_be_bytes::<32>());
+        preimage.extend_from_slice(&self.tick.to_be_bytes());
+        preimage.extend_from_slice(&(self.words.len() as u64).to_be_bytes());
+        for (position, word) in &self.words {
+            preimage.extend_from_slice(&position.to_be_bytes());
+            preimage.extend_from_slice(&word.to_be_bytes::<32>());
+        }
+        preimage.extend_from_slice(&(self.liquidity_nets.len() as u64).to_be_bytes());
+        for (tick, liquidity_net) in &self.liquidity_nets {
+            preimage.extend_from_slice(&tick.to_be_bytes());
+            preimage.extend_from_slice(&liquidity_net.to_be_bytes());
+        }
+        match &self.recorded_words {
+            Some(recorded_words) => {
+                preimage.push(1);
+                preimage.extend_from_slice(&recorded_words.start().to_be_bytes());
+                preimage.extend_from_slice(&recorded_words.end().to_be_bytes());
+            }
+            None => preimage.push(0),
+        }
+        keccak256(preimage)
+    }
+
+    // Words outside recorded_words read as empty in the returned provider, use math to keep them
+    // failing
+    pub fn into_math(self) -> Math<MemoryTicksProvider> {
+        Math {
+            fee: self.fee,
+            fee_protocol: self.fee_protocol,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            tick_spacing: self.tick_spacing,
+            provider: MemoryTicksProvider::from_words_and_nets(self.words, self.liquidity_nets),
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        }
+    }
+}
+
+impl TicksProvider for PoolSnapshot {
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        match self
+            .words
+            .binary_search_by_key(&position, |(position, _)| *position)
+        {
+            Ok(i) => Ok(self.words[i].1),
+            Err(_) => match &self.recorded_words {
+                Some(recorded_words) if !recorded_words.contains(&position) => {
+                    Err(UniswapV3MathError::MissingWord { position })
+                }
+                _ => Ok(U256::ZERO),
+            },
+        }
+    }
+
+    fn get_liquidity_net_at_tick(&self, tick: i32) -> Result<i128, UniswapV3MathError> {
+        self.liquidity_nets
+            .binary_search_by_key(&tick, |(tick, _)| *tick)
+            .map(|i| self.liquidity_nets[i].1)
+            .map_err(|_| UniswapV3MathError::MissingLiquidityNet { tick })
+    }
+
+    // Only a complete snapshot can tell which words are empty, a recorded one has to fail on the
+    // words it doesn't cover
+    fn initialized_word_range(&self) -> Option<RangeInclusive<i16>> {
+        if self.recorded_words.is_some() {
+            return None;
+        }
+
+        let mut positions = self
+            .words
+            .iter()
+            .filter(|(_, word)| *word != U256::ZERO)
+            .map(|(position, _)| *position);
+        Some(match positions.next() {
+            Some(first) => first..=positions.next_back().unwrap_or(first),
+            #[allow(clippy::reversed_empty_ranges)]
+            None => 1..=0,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use ruint::uint;
+
+    use super::*;
+    use crate::{
+        test::fixture,
+        tick_math::{MAX_TICK, MIN_TICK},
+    };
+
+    #[test]
+    fn test_pool_snapshot_round_trip() {
+        let math = fixture();
+        let math = math.with_provider(MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+
+        let snapshot = PoolSnapshot::from_math(&math);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored = serde_json::from_str::<PoolSnapshot>(&json).unwrap();
+        assert_eq!(restored, snapshot);
+        assert_eq!(restored.state_hash(), snapshot.state_hash());
+
+        //every field is in the hash
+        let recorded = PoolSnapshot {
+            recorded_words: Some(-58..=57),
+            ..snapshot.clone()
+        };
+        let mut words = snapshot.clone();
+        words.words[0].1 ^= U256::from(1);
+        let mut liquidity_nets = snapshot.clone();
+        liquidity_nets.liquidity_nets[0].1 += 1;
+        for changed in [
+            PoolSnapshot {
+                fee_protocol: 4,
+                ..snapshot.clone()
+            },
+            PoolSnapshot {
+                tick: snapshot.tick + 1,
+                ..snapshot.clone()
+            },
+            recorded,
+            words,
+            liquidity_nets,
+        ] {
+            assert_ne!(changed.state_hash(), snapshot.state_hash());
+        }
+
+        let restored = restored.into_math();
+        for zero_for_one in [true, false] {
+            for amount_in in [
+                uint!(1000000000000000_U256),
+                uint!(300000000000000000_U256),
+                uint!(1000000000000000000_U256),
+            ] {
+                let expected = math.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                let result = restored.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                let (expected, result) = (expected.unwrap(), result.unwrap());
+                assert_eq!(result, expected);
+
+                //results serialize too, with the same U256 format
+                let json = serde_json::to_string(&result).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<crate::SwapResult>(&json).unwrap(),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_recorded_snapshot_replay() {
+        let math = fixture();
+        let math = math.with_provider(MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+
+        let snapshot = PoolSnapshot::record(&math, MIN_TICK..=MAX_TICK).unwrap();
+        assert_eq!(snapshot.recorded_words, Some(-58..=57));
+        assert_eq!(snapshot.words.len(), 116);
+        assert_eq!(
+            snapshot.liquidity_nets,
+            PoolSnapshot::from_math(&math).liquidity_nets
+        );
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored = serde_json::from_str::<PoolSnapshot>(&json).unwrap();
+        assert_eq!(restored, snapshot);
+
+        let replay = restored.math();
+        for zero_for_one in [true, false] {
+            for amount_in in [
+                uint!(1000000000000000_U256),
+                uint!(300000000000000000_U256),
+                uint!(1000000000000000000_U256),
+            ] {
+                let expected = math.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                let result = replay.simulate_swap_with_limit(zero_for_one, amount_in, U256::ZERO);
+                assert_eq!(result.unwrap(), expected.unwrap());
+            }
+        }
+
+        //all the fixture's ticks are in word 0, swaps that leave it fail instead of guessing
+        let snapshot = PoolSnapshot::record(&math, 0..=15000).unwrap();
+        assert_eq!(snapshot.recorded_words, Some(0..=0));
+        let replay = snapshot.math();
+        assert_eq!(
+            replay
+                .simulate_swap(true, uint!(300000000000000000_U256))
+                .unwrap(),
+            math.simulate_swap(true, uint!(300000000000000000_U256))
+                .unwrap()
+        );
+        let result = replay.simulate_swap(true, uint!(1000000000000000000_U256));
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::MissingWord { position: -1 }
+        ));
+
+        //the public tick_spacing field is checked before it is divided by
+        let math = Math {
+            tick_spacing: 0,
+            ..math
+        };
+        assert!(matches!(
+            PoolSnapshot::record(&math, MIN_TICK..=MAX_TICK).unwrap_err(),
+            UniswapV3MathError::InvalidTickSpacing { tick_spacing: 0 }
+        ));
+    }
+
+    #[test]
+    fn test_math_serde_skips_provider() {
+        let math = fixture();
+        let json = serde_json::to_value(&math).unwrap();
+        assert!(json.get("provider").is_none());
+        if !cfg!(feature = "serde-hex") {
+            assert_eq!(json["sqrt_price_x96"], "112430957613446531369126327961");
+        }
+
+        let restored = serde_json::from_value::<Math<MemoryTicksProvider>>(json).unwrap();
+        assert_eq!(restored.sqrt_price_x96, math.sqrt_price_x96);
+        assert_eq!(restored.tick, math.tick);
+        assert_eq!(restored.liquidity, math.liquidity);
+        assert_eq!(restored.provider, MemoryTicksProvider::default());
+    }
+}
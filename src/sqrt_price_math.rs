@@ -11,6 +11,8 @@ use ruint::uint;
 pub const MAX_U160: U256 =
     U256::from_limbs([18446744073709551615, 18446744073709551615, 4294967295, 0]);
 pub const Q96: U256 = U256::from_limbs([0, 4294967296, 0, 0]);
+pub const Q128: U256 = U256::from_limbs([0, 0, 1, 0]);
+pub const Q192: U256 = U256::from_limbs([0, 0, 0, 1]);
 pub const FIXED_POINT_96_RESOLUTION: usize = 96;
 
 // returns (sqrtQX96)
@@ -0,0 +1,291 @@
+// Storage layout of UniswapV3Pool, for providers that read pool state slot by slot. Mapping slots
+// are keccak256(abi.encode(key, slot)) with signed keys sign extended to 32 bytes; getting that
+// wrong for negative ticks silently reads empty storage.
+
+use alloy_primitives::{keccak256, Address, B256, I256};
+use reth_primitives::U256;
+
+use crate::utils::RUINT_ONE;
+
+// Slots of the pool's state variables, in declaration order
+pub const SLOT0_SLOT: u8 = 0;
+pub const FEE_GROWTH_GLOBAL_0_X128_SLOT: u8 = 1;
+pub const FEE_GROWTH_GLOBAL_1_X128_SLOT: u8 = 2;
+pub const PROTOCOL_FEES_SLOT: u8 = 3;
+pub const LIQUIDITY_SLOT: u8 = 4;
+pub const TICKS_SLOT: u8 = 5;
+pub const TICK_BITMAP_SLOT: u8 = 6;
+pub const POSITIONS_SLOT: u8 = 7;
+pub const OBSERVATIONS_SLOT: u8 = 8;
+
+// Offsets from ticks_slot of the words of Tick.Info. The first packs liquidityGross and
+// liquidityNet, the last tickCumulativeOutside, secondsPerLiquidityOutsideX128, secondsOutside and
+// initialized.
+pub const TICK_LIQUIDITY_OFFSET: u8 = 0;
+pub const TICK_FEE_GROWTH_OUTSIDE_0_X128_OFFSET: u8 = 1;
+pub const TICK_FEE_GROWTH_OUTSIDE_1_X128_OFFSET: u8 = 2;
+pub const TICK_OUTSIDE_OFFSET: u8 = 3;
+
+pub fn slot0_slot() -> U256 {
+    U256::from(SLOT0_SLOT)
+}
+
+pub fn liquidity_slot() -> U256 {
+    U256::from(LIQUIDITY_SLOT)
+}
+
+// Slot of tickBitmap[word_pos]
+pub fn tick_bitmap_slot(word_pos: i16) -> U256 {
+    signed_mapping_slot(word_pos.into(), TICK_BITMAP_SLOT)
+}
+
+// Slot of the first word of ticks[tick], add the TICK_*_OFFSET constants for the others
+pub fn ticks_slot(tick: i32) -> U256 {
+    signed_mapping_slot(tick, TICKS_SLOT)
+}
+
+// Key of positions for a position, keccak256(abi.encodePacked(owner, tick_lower, tick_upper)) with
+// the ticks packed as 3 byte int24
+pub fn position_key(owner: Address, tick_lower: i32, tick_upper: i32) -> B256 {
+    let mut preimage = [0u8; 26];
+    preimage[..20].copy_from_slice(owner.as_slice());
+    preimage[20..23].copy_from_slice(&tick_lower.to_be_bytes()[1..]);
+    preimage[23..].copy_from_slice(&tick_upper.to_be_bytes()[1..]);
+
+    keccak256(preimage)
+}
+
+// Slot of the first word of positions[key]
+pub fn positions_slot(key: B256) -> U256 {
+    mapping_slot(key, POSITIONS_SLOT)
+}
+
+// liquidityNet out of the first word of Tick.Info, the high 128 bits above liquidityGross
+pub fn liquidity_net_from_word(word: U256) -> i128 {
+    (word >> 128usize).to::<u128>() as i128
+}
+
+// The pool's Tick.Info, stored in the four words from ticks_slot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TickInfo {
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+    pub fee_growth_outside_0_x128: U256,
+    pub fee_growth_outside_1_x128: U256,
+    // int56 in storage
+    pub tick_cumulative_outside: i64,
+    // uint160 in storage
+    pub seconds_per_liquidity_outside_x128: U256,
+    pub seconds_outside: u32,
+    pub initialized: bool,
+}
+
+// Unpacks the four storage words of Tick.Info. The last one packs, from the low bits up, int56
+// tickCumulativeOutside, uint160 secondsPerLiquidityOutsideX128, uint32 secondsOutside and bool
+// initialized.
+pub fn decode_tick_words(words: &[U256; 4]) -> TickInfo {
+    let outside = words[TICK_OUTSIDE_OFFSET as usize];
+    let bits = |offset: usize, width: usize| (outside >> offset) & mask(width);
+
+    // Move the int56 sign bit into the i64 sign bit and shift back to extend it
+    let tick_cumulative_outside = ((bits(0, 56).to::<u64>() << 8) as i64) >> 8;
+
+    let liquidity = words[TICK_LIQUIDITY_OFFSET as usize];
+    TickInfo {
+        liquidity_gross: (liquidity & mask(128)).to::<u128>(),
+        liquidity_net: liquidity_net_from_word(liquidity),
+        fee_growth_outside_0_x128: words[TICK_FEE_GROWTH_OUTSIDE_0_X128_OFFSET as usize],
+        fee_growth_outside_1_x128: words[TICK_FEE_GROWTH_OUTSIDE_1_X128_OFFSET as usize],
+        tick_cumulative_outside,
+        seconds_per_liquidity_outside_x128: bits(56, 160),
+        seconds_outside: bits(216, 32).to::<u32>(),
+        initialized: bits(248, 8) != U256::ZERO,
+    }
+}
+
+// Packs info into the four storage words of Tick.Info, the inverse of decode_tick_words.
+// tick_cumulative_outside and seconds_per_liquidity_outside_x128 are truncated to their storage
+// widths like Solidity's casts.
+pub fn encode_tick_words(info: &TickInfo) -> [U256; 4] {
+    let liquidity =
+        U256::from(info.liquidity_net as u128) << 128usize | U256::from(info.liquidity_gross);
+    let outside = U256::from(info.tick_cumulative_outside as u64) & mask(56)
+        | (info.seconds_per_liquidity_outside_x128 & mask(160)) << 56usize
+        | U256::from(info.seconds_outside) << 216usize
+        | U256::from(info.initialized) << 248usize;
+
+    let mut words = [U256::ZERO; 4];
+    words[TICK_LIQUIDITY_OFFSET as usize] = liquidity;
+    words[TICK_FEE_GROWTH_OUTSIDE_0_X128_OFFSET as usize] = info.fee_growth_outside_0_x128;
+    words[TICK_FEE_GROWTH_OUTSIDE_1_X128_OFFSET as usize] = info.fee_growth_outside_1_x128;
+    words[TICK_OUTSIDE_OFFSET as usize] = outside;
+    words
+}
+
+fn mask(width: usize) -> U256 {
+    (RUINT_ONE << width) - RUINT_ONE
+}
+
+fn signed_mapping_slot(key: i32, slot: u8) -> U256 {
+    mapping_slot(
+        I256::try_from(key).unwrap().to_be_bytes::<32>().into(),
+        slot,
+    )
+}
+
+fn mapping_slot(key: B256, slot: u8) -> U256 {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(key.as_slice());
+    preimage[63] = slot;
+
+    U256::from_be_bytes(keccak256(preimage).0)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, b256};
+    use ruint::uint;
+
+    use super::*;
+
+    #[test]
+    fn test_slots() {
+        assert_eq!(slot0_slot(), U256::ZERO);
+        assert_eq!(liquidity_slot(), U256::from(4));
+
+        //computed offline as keccak256(abi.encode(key, slot)), not taken from eth_getProof:
+        //this pins the encoding, it does not prove it matches what a node serves
+        //negative keys are sign extended, not zero padded
+        for (word_pos, slot) in [
+            (
+                0,
+                uint!(0x54cdd369e4e8a8515e52ca72ec816c2101831ad1f18bf44102ed171459c9b4f8_U256),
+            ),
+            (
+                1,
+                uint!(0x3e5fec24aa4dc4e5aee2e025e51e1392c72a2500577559fae9665c6d52bd6a31_U256),
+            ),
+            (
+                -1,
+                uint!(0x63187d71e139eee983a88d0737447c7451979b3dbb75903c76b5fe430d36588e_U256),
+            ),
+            (
+                -3466,
+                uint!(0xd755e5d56eb0c02b88a608090bc46b45d16a68e39c7239e4014ce1f95c7907b8_U256),
+            ),
+        ] {
+            assert_eq!(tick_bitmap_slot(word_pos), slot);
+        }
+
+        for (tick, slot) in [
+            (
+                600,
+                uint!(0x87ab7a5a3e42e9ce80c9bfd4beffdff114c758e71396b4ddbbdcc38c72c3a00c_U256),
+            ),
+            (
+                -600,
+                uint!(0x4fad0605d72150cd0356aa715f173545037cc44027ed9e05328ba670878f2080_U256),
+            ),
+            (
+                887220,
+                uint!(0xdc3f4388499eef42a791da8d78f6e4b5cc6376b0349c5622dcbfad8d3aa7f5bd_U256),
+            ),
+            (
+                -887220,
+                uint!(0x7f16e4ac80e3195175c78aa64fe305d939ea0f7e52dc9181b922712fa7117c19_U256),
+            ),
+        ] {
+            assert_eq!(ticks_slot(tick), slot);
+        }
+
+        //full range position of the NonfungiblePositionManager
+        let key = position_key(
+            address!("C36442b4a4522E871399CD717aBDD847Ab11FE88"),
+            -887220,
+            887220,
+        );
+        assert_eq!(
+            key,
+            b256!("381aa6c2062f30ec4294109119a35ae6c664cb629c3c1fad6610316d27488a48")
+        );
+        assert_eq!(
+            positions_slot(key),
+            uint!(0x1e6af8071088a01456f67390dba7029f946afa9f048993785717e0f8f105596f_U256)
+        );
+    }
+
+    #[test]
+    fn test_decode_tick_words() {
+        //synthetic words packed by hand to the Tick.Info layout, not read from a pool:
+        //a lower tick that was crossed down, every field set, negative cumulative, top bits in use
+        let words = [
+            uint!(0xfffffffffffffffffffffff4d88728dd00000000000000000000000b2778d723_U256),
+            uint!(0x00000000000000000000000000000000000000123456789abcdef0123456789a_U256),
+            U256::MAX,
+            uint!(0x01659d6c51000000000000000000000000000000abcdef0ffffc72815b398000_U256),
+        ];
+        assert_eq!(
+            decode_tick_words(&words),
+            TickInfo {
+                liquidity_gross: 47906871075,
+                liquidity_net: -47906871075,
+                fee_growth_outside_0_x128: uint!(0x123456789abcdef0123456789a_U256),
+                fee_growth_outside_1_x128: U256::MAX,
+                tick_cumulative_outside: -1_000_000_000_000_000,
+                seconds_per_liquidity_outside_x128: uint!(0xabcdef0fff_U256),
+                seconds_outside: 1_704_815_697,
+                initialized: true,
+            }
+        );
+        assert_eq!(encode_tick_words(&decode_tick_words(&words)), words);
+
+        //the int56 bounds
+        for tick_cumulative_outside in [-1, -(1 << 55), (1 << 55) - 1] {
+            let info = TickInfo {
+                tick_cumulative_outside,
+                ..TickInfo::default()
+            };
+            assert_eq!(decode_tick_words(&encode_tick_words(&info)), info);
+        }
+        assert_eq!(decode_tick_words(&[U256::ZERO; 4]), TickInfo::default());
+    }
+
+    #[test]
+    fn test_tick_words_round_trip() {
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        let mut next_u128 = || (next() as u128) << 64 | next() as u128;
+
+        for _ in 0..1000 {
+            let info = TickInfo {
+                liquidity_gross: next_u128(),
+                liquidity_net: next_u128() as i128,
+                fee_growth_outside_0_x128: U256::from(next_u128()) << 128usize
+                    | U256::from(next_u128()),
+                fee_growth_outside_1_x128: U256::from(next_u128()),
+                tick_cumulative_outside: (next_u128() as i64) >> 8,
+                seconds_per_liquidity_outside_x128: U256::from(next_u128()) << 32usize
+                    | U256::from(next_u128() as u32),
+                seconds_outside: next_u128() as u32,
+                initialized: next_u128() % 2 == 1,
+            };
+            let words = encode_tick_words(&info);
+            assert_eq!(decode_tick_words(&words), info);
+            assert_eq!(liquidity_net_from_word(words[0]), info.liquidity_net);
+        }
+    }
+
+    #[test]
+    fn test_liquidity_net_from_word() {
+        let word = U256::from(-2_000_i128 as u128) << 128usize | U256::from(3_000);
+        assert_eq!(liquidity_net_from_word(word), -2_000);
+        let word = U256::from(i128::MAX as u128) << 128usize | U256::from(u128::MAX);
+        assert_eq!(liquidity_net_from_word(word), i128::MAX);
+        assert_eq!(liquidity_net_from_word(U256::MAX), -1);
+    }
+}
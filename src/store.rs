@@ -0,0 +1,496 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::{keccak256, Address, B256};
+
+use crate::{error::UniswapV3MathError, snapshot::PoolSnapshot};
+
+// Pools are keyed by their contract address
+pub type PoolId = Address;
+
+// Layout of a store file, all integers little endian:
+//   header   MAGIC, FORMAT_VERSION as u32, entry count as u32
+//   entry    pool id (20 bytes), blob length as u32, bincode PoolSnapshot blob,
+//            keccak256(pool id ++ blob)
+//   trailer  keccak256 of everything before it
+// Anything that changes this layout or the bincode encoding of PoolSnapshot needs a new
+// FORMAT_VERSION and a new golden file in tests/fixtures.
+pub const FORMAT_VERSION: u32 = 1;
+const MAGIC: [u8; 4] = *b"UV3S";
+const CHECKSUM_LEN: usize = 32;
+
+// Saves and loads sets of PoolSnapshots in one versioned file, so mirrors of many pools can be
+// restored without reading them from the chain again. Every snapshot is validated before it is
+// saved and after it is loaded.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SnapshotStore;
+
+// What load_lenient could read from a store file
+#[derive(Debug, Default)]
+pub struct LenientLoad {
+    pub snapshots: Vec<(PoolId, PoolSnapshot)>,
+    // Index and error of every entry that was skipped
+    pub skipped: Vec<(usize, UniswapV3MathError)>,
+    // A problem with the file as a whole: a version mismatch, in which case nothing was read,
+    // truncation, in which case the entries before it were, or a bad trailer hash
+    pub file_error: Option<UniswapV3MathError>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        SnapshotStore
+    }
+
+    // Writes snapshots to a temporary file next to path and renames it over path, so a crash while
+    // saving leaves the previous file in place
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        snapshots: &[(PoolId, PoolSnapshot)],
+    ) -> Result<(), UniswapV3MathError> {
+        let path = path.as_ref();
+        let bytes = encode(snapshots)?;
+
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        fs::write(&temp_path, bytes).map_err(|error| store_file_error(path, error))?;
+        fs::rename(&temp_path, path).map_err(|error| store_file_error(path, error))
+    }
+
+    // Reads every snapshot back, failing on the first problem with the file or any entry
+    pub fn load(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<(PoolId, PoolSnapshot)>, UniswapV3MathError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| store_file_error(path, error))?;
+
+        let mut reader = Reader::new(&bytes)?;
+        let mut snapshots = Vec::with_capacity(reader.count);
+        for index in 0..reader.count {
+            snapshots.push(reader.next_entry(index)??);
+        }
+        reader.finish()?;
+
+        Ok(snapshots)
+    }
+
+    // Reads every snapshot that passes its checksum and validation, reporting the others instead
+    // of failing. Only failing to read the file at all is an error.
+    pub fn load_lenient(&self, path: impl AsRef<Path>) -> Result<LenientLoad, UniswapV3MathError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| store_file_error(path, error))?;
+
+        let mut load = LenientLoad::default();
+        let mut reader = match Reader::new(&bytes) {
+            Ok(reader) => reader,
+            Err(error) => {
+                load.file_error = Some(error);
+                return Ok(load);
+            }
+        };
+        for index in 0..reader.count {
+            match reader.next_entry(index) {
+                Ok(Ok(entry)) => load.snapshots.push(entry),
+                Ok(Err(error)) => load.skipped.push((index, error)),
+                // Nothing after a truncated entry can be found
+                Err(error) => {
+                    load.file_error = Some(error);
+                    return Ok(load);
+                }
+            }
+        }
+        load.file_error = reader.finish().err();
+
+        Ok(load)
+    }
+}
+
+fn encode(snapshots: &[(PoolId, PoolSnapshot)]) -> Result<Vec<u8>, UniswapV3MathError> {
+    let count =
+        u32::try_from(snapshots.len()).map_err(|_| UniswapV3MathError::InvalidSnapshot {
+            reason: format!("{} snapshots don't fit in a store file", snapshots.len()),
+        })?;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&count.to_le_bytes());
+
+    for (pool, snapshot) in snapshots {
+        snapshot.validate()?;
+        let blob =
+            bincode::serialize(snapshot).map_err(|error| UniswapV3MathError::InvalidSnapshot {
+                reason: error.to_string(),
+            })?;
+        bytes.extend_from_slice(pool.as_slice());
+        bytes.extend_from_slice(&(blob.len() as u32).to_le_bytes());
+        let checksum = entry_checksum(pool, &blob);
+        bytes.extend_from_slice(&blob);
+        bytes.extend_from_slice(checksum.as_slice());
+    }
+
+    let trailer = keccak256(&bytes);
+    bytes.extend_from_slice(trailer.as_slice());
+    Ok(bytes)
+}
+
+fn entry_checksum(pool: &PoolId, blob: &[u8]) -> B256 {
+    let mut preimage = Vec::with_capacity(20 + blob.len());
+    preimage.extend_from_slice(pool.as_slice());
+    preimage.extend_from_slice(blob);
+    keccak256(preimage)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    count: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, UniswapV3MathError> {
+        let mut reader = Reader {
+            bytes,
+            offset: 0,
+            count: 0,
+        };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(UniswapV3MathError::SnapshotStoreCorrupted { entry: None });
+        }
+        let found = reader.take_u32()?;
+        if found != FORMAT_VERSION {
+            return Err(UniswapV3MathError::SnapshotStoreVersion {
+                found,
+                expected: FORMAT_VERSION,
+            });
+        }
+        reader.count = reader.take_u32()? as usize;
+
+        Ok(reader)
+    }
+
+    // The outer error is truncation, after which nothing else can be read. The inner one is a
+    // corrupt or invalid entry that can be skipped.
+    #[allow(clippy::type_complexity)]
+    fn next_entry(
+        &mut self,
+        index: usize,
+    ) -> Result<Result<(PoolId, PoolSnapshot), UniswapV3MathError>, UniswapV3MathError> {
+        let pool = PoolId::from_slice(self.take(20)?);
+        let len = self.take_u32()? as usize;
+        let blob = self.take(len)?;
+        let checksum = B256::from_slice(self.take(CHECKSUM_LEN)?);
+
+        if entry_checksum(&pool, blob) != checksum {
+            return Ok(Err(UniswapV3MathError::SnapshotStoreCorrupted {
+                entry: Some(index),
+            }));
+        }
+        let snapshot = match bincode::deserialize::<PoolSnapshot>(blob) {
+            Ok(snapshot) => snapshot,
+            Err(_) => {
+                return Ok(Err(UniswapV3MathError::SnapshotStoreCorrupted {
+                    entry: Some(index),
+                }))
+            }
+        };
+        Ok(snapshot.validate().map(|_| (pool, snapshot)))
+    }
+
+    // Checks the trailer hash over everything read so far, and that nothing follows it
+    fn finish(mut self) -> Result<(), UniswapV3MathError> {
+        let end = self.offset;
+        let trailer = B256::from_slice(self.take(CHECKSUM_LEN)?);
+        if keccak256(&self.bytes[..end]) != trailer || self.offset != self.bytes.len() {
+            return Err(UniswapV3MathError::SnapshotStoreCorrupted { entry: None });
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], UniswapV3MathError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(UniswapV3MathError::SnapshotStoreTruncated {
+                len: self.bytes.len(),
+                needed: self.offset.saturating_add(len),
+            })?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn take_u32(&mut self) -> Result<u32, UniswapV3MathError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+fn store_file_error(path: &Path, error: std::io::Error) -> UniswapV3MathError {
+    UniswapV3MathError::SnapshotFile {
+        path: path.to_path_buf(),
+        source: error.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use reth_primitives::U256;
+    use ruint::uint;
+
+    use super::*;
+    use crate::{
+        providers::MemoryTicksProvider,
+        test::fixture,
+        tick_math::{MAX_TICK, MIN_TICK},
+    };
+
+    const GOLDEN: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/snapshot_store_v1.bin"
+    );
+
+    // The fixture pool complete, recorded over every word and recorded over word 0 only
+    fn snapshots() -> Vec<(PoolId, PoolSnapshot)> {
+        let math = fixture();
+        let math = math.with_provider(MemoryTicksProvider::from_words_and_nets(
+            math.provider.words.clone(),
+            math.provider.liquidity_nets.clone(),
+        ));
+
+        vec![
+            (PoolId::repeat_byte(1), PoolSnapshot::from_math(&math)),
+            (
+                PoolId::repeat_byte(2),
+                PoolSnapshot::record(&math, MIN_TICK..=MAX_TICK).unwrap(),
+            ),
+            (
+                PoolId::repeat_byte(3),
+                PoolSnapshot::record(&math, 0..=15000).unwrap(),
+            ),
+        ]
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{name}.store", std::process::id()))
+    }
+
+    // Byte range of every entry's blob in an encoded file
+    fn blob_ranges(bytes: &[u8]) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut offset = 12;
+        for _ in 0..u32::from_le_bytes(bytes[8..12].try_into().unwrap()) {
+            let len = u32::from_le_bytes(bytes[offset + 20..offset + 24].try_into().unwrap());
+            ranges.push(offset + 24..offset + 24 + len as usize);
+            offset += 24 + len as usize + CHECKSUM_LEN;
+        }
+        ranges
+    }
+
+    fn write(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_snapshot_store_round_trip() {
+        let store = SnapshotStore::new();
+        let snapshots = snapshots();
+        let path = temp_path("round-trip");
+        store.save(&path, &snapshots).unwrap();
+        let loaded = store.load(&path).unwrap();
+        let lenient = store.load_lenient(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, snapshots);
+        assert_eq!(lenient.snapshots, snapshots);
+        assert!(lenient.skipped.is_empty() && lenient.file_error.is_none());
+
+        for ((pool, snapshot), (loaded_pool, loaded)) in snapshots.iter().zip(&loaded) {
+            assert_eq!(loaded_pool, pool);
+            assert_eq!(loaded.state_hash(), snapshot.state_hash());
+            for zero_for_one in [true, false] {
+                for amount_in in [
+                    uint!(1000000000000000_U256),
+                    uint!(300000000000000000_U256),
+                    uint!(1000000000000000000_U256),
+                ] {
+                    let expected = snapshot.math().simulate_swap(zero_for_one, amount_in);
+                    let result = loaded.math().simulate_swap(zero_for_one, amount_in);
+                    assert_eq!(format!("{result:?}"), format!("{expected:?}"));
+                }
+            }
+        }
+
+        //an empty set is a valid file too
+        store.save(&path, &[]).unwrap();
+        assert!(store.load(&path).unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+
+        //invalid snapshots are refused before anything is written
+        let invalid = PoolSnapshot {
+            sqrt_price_x96: U256::ZERO,
+            ..snapshots[0].1.clone()
+        };
+        assert!(store.save(&path, &[(PoolId::ZERO, invalid)]).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_snapshot_store_golden() {
+        //files written by format version 1 keep loading
+        let loaded = SnapshotStore::new().load(GOLDEN).unwrap();
+        assert_eq!(loaded, snapshots());
+
+        //and version 1 keeps writing them byte for byte, U256 values are hex strings with serde-hex
+        if !cfg!(feature = "serde-hex") {
+            assert_eq!(encode(&snapshots()).unwrap(), fs::read(GOLDEN).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_snapshot_store_corruption() {
+        let store = SnapshotStore::new();
+        let snapshots = snapshots();
+        let bytes = encode(&snapshots).unwrap();
+        let blobs = blob_ranges(&bytes);
+        assert_eq!(blobs.len(), 3);
+
+        //a flipped bit in any entry is reported at that entry, the others still load leniently
+        for (entry, blob) in blobs.iter().enumerate() {
+            for offset in [blob.start, (blob.start + blob.end) / 2, blob.end - 1] {
+                let mut corrupted = bytes.clone();
+                corrupted[offset] ^= 0x10;
+                let path = write("corrupted", &corrupted);
+                let result = store.load(&path);
+                let lenient = store.load_lenient(&path).unwrap();
+                fs::remove_file(&path).unwrap();
+
+                assert!(matches!(
+                    result.unwrap_err(),
+                    UniswapV3MathError::SnapshotStoreCorrupted { entry: Some(found) } if found == entry
+                ));
+                assert_eq!(lenient.skipped.len(), 1);
+                assert!(matches!(
+                    lenient.skipped[0],
+                    (found, UniswapV3MathError::SnapshotStoreCorrupted { entry: Some(_) })
+                        if found == entry
+                ));
+                let mut expected = snapshots.clone();
+                expected.remove(entry);
+                assert_eq!(lenient.snapshots, expected);
+                assert!(matches!(
+                    lenient.file_error,
+                    Some(UniswapV3MathError::SnapshotStoreCorrupted { entry: None })
+                ));
+            }
+        }
+
+        //a flipped bit in the magic or the trailer isn't any entry's
+        for offset in [0, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[offset] ^= 0x01;
+            let path = write("corrupted-file", &corrupted);
+            let result = store.load(&path);
+            fs::remove_file(&path).unwrap();
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::SnapshotStoreCorrupted { entry: None }
+            ));
+        }
+
+        //so are bytes after the trailer
+        let mut extended = bytes.clone();
+        extended.push(0);
+        let path = write("extended", &extended);
+        let result = store.load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SnapshotStoreCorrupted { entry: None }
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_store_truncation() {
+        let store = SnapshotStore::new();
+        let snapshots = snapshots();
+        let bytes = encode(&snapshots).unwrap();
+        let blobs = blob_ranges(&bytes);
+
+        // (length cut to, entries still readable)
+        for (len, readable) in [
+            (0, 0),
+            (6, 0),
+            (12, 0),
+            (blobs[0].start, 0),
+            (blobs[1].start + 10, 1),
+            (blobs[2].end + CHECKSUM_LEN, 3),
+            (bytes.len() - 1, 3),
+        ] {
+            let path = write("truncated", &bytes[..len]);
+            let result = store.load(&path);
+            let lenient = store.load_lenient(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert!(matches!(
+                result.unwrap_err(),
+                UniswapV3MathError::SnapshotStoreTruncated { len: found, .. } if found == len
+            ));
+            assert_eq!(lenient.snapshots, snapshots[..readable]);
+            assert!(lenient.skipped.is_empty());
+            assert!(matches!(
+                lenient.file_error,
+                Some(UniswapV3MathError::SnapshotStoreTruncated { .. })
+            ));
+        }
+
+        //a missing file is an error for both
+        let path = temp_path("missing");
+        assert!(matches!(
+            store.load(&path).unwrap_err(),
+            UniswapV3MathError::SnapshotFile { .. }
+        ));
+        assert!(store.load_lenient(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_store_version() {
+        let store = SnapshotStore::new();
+        let mut bytes = encode(&snapshots()).unwrap();
+
+        //a valid file from a later format version
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let end = bytes.len() - CHECKSUM_LEN;
+        let trailer = keccak256(&bytes[..end]);
+        bytes[end..].copy_from_slice(trailer.as_slice());
+
+        let path = write("version", &bytes);
+        let result = store.load(&path);
+        let lenient = store.load_lenient(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::SnapshotStoreVersion {
+                found: 2,
+                expected: 1
+            }
+        ));
+        assert!(lenient.snapshots.is_empty() && lenient.skipped.is_empty());
+        assert!(matches!(
+            lenient.file_error,
+            Some(UniswapV3MathError::SnapshotStoreVersion {
+                found: 2,
+                expected: 1
+            })
+        ));
+    }
+}
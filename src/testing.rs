@@ -0,0 +1,462 @@
+// Test helpers shared by this crate's tests and, with the testing feature, by downstream ones
+use std::collections::BTreeMap;
+
+use alloy_primitives::I256;
+use reth_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    liquidity_math::apply_liquidity_net,
+    providers::MemoryTicksProvider,
+    swap_math::compute_swap_step,
+    tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_TICK, MIN_TICK},
+    utils::u256_to_i256,
+    Math,
+};
+
+// A pool with its initialized ticks in a sorted map, for reference_swap. ticks holds the
+// liquidity_net of every initialized tick, all of them multiples of tick_spacing.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReferencePool {
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub liquidity: u128,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub ticks: BTreeMap<i32, i128>,
+}
+
+// Outcome of reference_swap, amounts signed like the pool's swap return values
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReferenceSwap {
+    pub amount0: I256,
+    pub amount1: I256,
+    pub fee_amount: U256,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub ticks_crossed: u32,
+}
+
+impl ReferencePool {
+    // The same pool for Math, its bitmap built from ticks
+    pub fn math(&self) -> Result<Math<MemoryTicksProvider>, UniswapV3MathError> {
+        let ticks = self
+            .ticks
+            .iter()
+            .map(|(tick, liquidity_net)| (*tick, *liquidity_net))
+            .collect::<Vec<_>>();
+        Ok(Math {
+            fee: self.fee,
+            fee_protocol: 0,
+            liquidity: self.liquidity,
+            sqrt_price_x96: self.sqrt_price_x96,
+            tick: self.tick,
+            tick_spacing: self.tick_spacing,
+            provider: MemoryTicksProvider::from_initialized_ticks(&ticks, self.tick_spacing)?,
+            max_steps: 0,
+            sqrt_ratio_cache: None,
+            occupancy: None,
+        })
+    }
+
+    // The pool's swap loop written out as directly as possible: each step looks for the next
+    // initialized tick one compressed tick at a time, giving up at the edge of the 256 tick word
+    // like nextInitializedTickWithinOneWord, and never past MIN_TICK or MAX_TICK. No bitmap, no
+    // word loading or skipping, no step limit. The swap math itself is the crate's
+    // compute_swap_step. Without a limit in the swap direction the caller passes the price bound,
+    // and the limit isn't checked against the current price.
+    pub fn reference_swap(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256,
+    ) -> Result<ReferenceSwap, UniswapV3MathError> {
+        let exact_input = amount_specified > I256::ZERO;
+        let mut remaining = amount_specified;
+        let mut calculated = I256::ZERO;
+        let mut fee_amount = U256::ZERO;
+        let mut sqrt_price_x96 = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+        let mut ticks_crossed = 0;
+
+        while remaining != I256::ZERO && sqrt_price_x96 != sqrt_price_limit_x96 {
+            let (tick_next, initialized) = self.next_tick(tick, zero_for_one);
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next_x96 = get_sqrt_ratio_at_tick(tick_next)?;
+
+            let target = if zero_for_one {
+                sqrt_price_next_x96.max(sqrt_price_limit_x96)
+            } else {
+                sqrt_price_next_x96.min(sqrt_price_limit_x96)
+            };
+            let sqrt_price_start_x96 = sqrt_price_x96;
+            let (amount_in, amount_out, step_fee);
+            (sqrt_price_x96, amount_in, amount_out, step_fee) =
+                compute_swap_step(sqrt_price_x96, target, liquidity, remaining, self.fee)?;
+            fee_amount += step_fee;
+
+            if exact_input {
+                remaining -= u256_to_i256(amount_in + step_fee);
+                calculated -= u256_to_i256(amount_out);
+            } else {
+                remaining += u256_to_i256(amount_out);
+                calculated += u256_to_i256(amount_in + step_fee);
+            }
+
+            if sqrt_price_x96 == sqrt_price_next_x96 {
+                if initialized {
+                    liquidity =
+                        apply_liquidity_net(liquidity, self.ticks[&tick_next], zero_for_one)?;
+                    ticks_crossed += 1;
+                }
+                tick = if zero_for_one {
+                    tick_next - 1
+                } else {
+                    tick_next
+                };
+            } else if sqrt_price_x96 != sqrt_price_start_x96 {
+                tick = get_tick_at_sqrt_ratio(sqrt_price_x96)?;
+            }
+        }
+
+        let used = amount_specified - remaining;
+        let (amount0, amount1) = if zero_for_one == exact_input {
+            (used, calculated)
+        } else {
+            (calculated, used)
+        };
+        Ok(ReferenceSwap {
+            amount0,
+            amount1,
+            fee_amount,
+            sqrt_price_x96,
+            tick,
+            liquidity,
+            ticks_crossed,
+        })
+    }
+
+    // Next initialized tick at or below tick when moving left and above it when moving right,
+    // within the word searched from, or that word's last tick in the direction of the search
+    fn next_tick(&self, tick: i32, zero_for_one: bool) -> (i32, bool) {
+        let mut compressed = tick.div_euclid(self.tick_spacing);
+        if !zero_for_one {
+            compressed += 1;
+        }
+
+        loop {
+            let tick = compressed * self.tick_spacing;
+            if self.ticks.contains_key(&tick) {
+                return (tick, true);
+            }
+            if zero_for_one && compressed.rem_euclid(256) == 0 {
+                return (tick, false);
+            }
+            if !zero_for_one && compressed.rem_euclid(256) == 255 {
+                return (tick, false);
+            }
+            compressed += if zero_for_one { -1 } else { 1 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt;
+
+    use ruint::uint;
+
+    use super::*;
+    use crate::{
+        tick_math::{max_usable_tick, min_usable_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        utils::RUINT_ONE,
+    };
+
+    // Runs every case of the word edge suite instead of every seventh one when set
+    const FULL_SUITE_VAR: &str = "WORD_EDGE_FULL_SUITE";
+
+    const LIQUIDITY: u128 = 1_000_000_000_000_000_000;
+
+    // One pool and swap direction of the word edge suite. Initialized ticks sit just inside, at
+    // and just across a word edge, by the bits of placement, and the pool starts one compressed
+    // tick before, on or after the edge, either exactly on a tick or partway into it.
+    #[derive(Debug, Clone, Copy)]
+    struct EdgeCase {
+        tick_spacing: i32,
+        // Compressed tick at the edge, the first (≡ 0) or last (≡ 255) of its word
+        edge: i32,
+        placement: u8,
+        start_offset: i32,
+        inside_tick: bool,
+        zero_for_one: bool,
+    }
+
+    // Case ids look like s60/e-256/p110/start+1/on/0for1, placement bits being inside, at, across
+    impl fmt::Display for EdgeCase {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "s{}/e{}/p{}{}{}/start{:+}/{}/{}",
+                self.tick_spacing,
+                self.edge,
+                self.placement & 1,
+                self.placement >> 1 & 1,
+                self.placement >> 2 & 1,
+                self.start_offset,
+                if self.inside_tick { "in" } else { "on" },
+                if self.zero_for_one { "0for1" } else { "1for0" },
+            )
+        }
+    }
+
+    impl EdgeCase {
+        // The pool and price limit for the case, None when its start or limit falls outside the
+        // usable ticks
+        fn pool(&self) -> Option<(ReferencePool, U256)> {
+            let tick_spacing = self.tick_spacing;
+            let min_compressed = min_usable_tick(tick_spacing) / tick_spacing;
+            let max_compressed = max_usable_tick(tick_spacing) / tick_spacing;
+
+            //a full range position, and positions from each placed tick to the upper bound
+            let mut ticks = BTreeMap::new();
+            *ticks.entry(min_compressed * tick_spacing).or_default() += LIQUIDITY as i128;
+            *ticks.entry(max_compressed * tick_spacing).or_default() -= LIQUIDITY as i128;
+            let inward = if self.edge.rem_euclid(256) == 0 {
+                1
+            } else {
+                -1
+            };
+            for (bit, offset) in [inward, 0, -inward].into_iter().enumerate() {
+                let compressed = self.edge + offset;
+                if self.placement >> bit & 1 == 0
+                    || !(min_compressed..=max_compressed).contains(&compressed)
+                {
+                    continue;
+                }
+                let liquidity_net = (LIQUIDITY as i128 / 10) << bit;
+                *ticks.entry(compressed * tick_spacing).or_default() += liquidity_net;
+                *ticks.entry(max_compressed * tick_spacing).or_default() -= liquidity_net;
+            }
+            ticks.retain(|_, liquidity_net| *liquidity_net != 0);
+
+            let tick = (self.edge + self.start_offset) * tick_spacing;
+            if tick < min_usable_tick(tick_spacing) || tick >= MAX_TICK {
+                return None;
+            }
+            let sqrt_price_x96 = if self.inside_tick {
+                let (lower, upper) = (
+                    get_sqrt_ratio_at_tick(tick).ok()?,
+                    get_sqrt_ratio_at_tick(tick + 1).ok()?,
+                );
+                (lower + upper) >> 1usize
+            } else {
+                get_sqrt_ratio_at_tick(tick).ok()?
+            };
+            let liquidity = ticks
+                .range(..=tick)
+                .map(|(_, liquidity_net)| *liquidity_net)
+                .sum::<i128>() as u128;
+
+            //far enough to cross every placed tick
+            let (limit_tick, bound) = if self.zero_for_one {
+                (tick - 4 * tick_spacing, MIN_SQRT_RATIO + RUINT_ONE)
+            } else {
+                (tick + 4 * tick_spacing, MAX_SQRT_RATIO - RUINT_ONE)
+            };
+            let limit = get_sqrt_ratio_at_tick(limit_tick.clamp(MIN_TICK, MAX_TICK)).ok()?;
+            let limit = if self.zero_for_one {
+                limit.max(bound)
+            } else {
+                limit.min(bound)
+            };
+            if (self.zero_for_one && limit >= sqrt_price_x96)
+                || (!self.zero_for_one && limit <= sqrt_price_x96)
+            {
+                return None;
+            }
+
+            let pool = ReferencePool {
+                fee: 3000,
+                tick_spacing,
+                liquidity,
+                sqrt_price_x96,
+                tick,
+                ticks,
+            };
+            Some((pool, limit))
+        }
+    }
+
+    // Word edges within two words of zero and within two words of each usable bound, for every
+    // spacing, with every placement, start and direction
+    fn edge_cases() -> Vec<EdgeCase> {
+        let mut cases = vec![];
+        for tick_spacing in [1, 10, 60, 200] {
+            let min_compressed = min_usable_tick(tick_spacing) / tick_spacing;
+            let max_compressed = max_usable_tick(tick_spacing) / tick_spacing;
+            let (min_word, max_word) = (
+                min_compressed.div_euclid(256),
+                max_compressed.div_euclid(256),
+            );
+
+            for word in (-2..=1)
+                .chain(min_word..=min_word + 1)
+                .chain(max_word - 1..=max_word)
+            {
+                for edge in [word * 256, word * 256 + 255] {
+                    if !(min_compressed..=max_compressed).contains(&edge) {
+                        continue;
+                    }
+                    for placement in 0..8 {
+                        for start_offset in [-1, 0, 1] {
+                            for inside_tick in [false, true] {
+                                for zero_for_one in [true, false] {
+                                    cases.push(EdgeCase {
+                                        tick_spacing,
+                                        edge,
+                                        placement,
+                                        start_offset,
+                                        inside_tick,
+                                        zero_for_one,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        cases
+    }
+
+    // Swaps through case's pool with Math and with the reference, a large exact input stopped by
+    // the limit, a small exact input and a small exact output
+    fn check_edge_case(case: &EdgeCase) -> bool {
+        let Some((pool, limit)) = case.pool() else {
+            return false;
+        };
+        let math = pool.math().unwrap();
+        let zero_for_one = case.zero_for_one;
+
+        for amount_in in [uint!(1000000_U256), RUINT_ONE << 126usize] {
+            let expected = pool
+                .reference_swap(zero_for_one, u256_to_i256(amount_in), limit)
+                .unwrap();
+            let result = math
+                .simulate_swap_with_limit(zero_for_one, amount_in, limit)
+                .unwrap_or_else(|err| panic!("{case}: {err}"));
+            let (expected_in, expected_out) = if zero_for_one {
+                (expected.amount0, expected.amount1)
+            } else {
+                (expected.amount1, expected.amount0)
+            };
+            assert_eq!(
+                (
+                    result.amount_in,
+                    result.amount_out,
+                    result.total_fee,
+                    result.sqrt_price_x96,
+                    result.tick,
+                    result.liquidity,
+                    result.ticks_crossed,
+                ),
+                (
+                    expected_in.unsigned_abs(),
+                    expected_out.unsigned_abs(),
+                    expected.fee_amount,
+                    expected.sqrt_price_x96,
+                    expected.tick,
+                    expected.liquidity,
+                    expected.ticks_crossed,
+                ),
+                "{case}: exact input {amount_in}"
+            );
+        }
+
+        let amount_out = I256::try_from(-1000).unwrap();
+        let expected = pool
+            .reference_swap(zero_for_one, amount_out, limit)
+            .unwrap();
+        let result = math
+            .simulate_swap_amount_specified(zero_for_one, amount_out, limit)
+            .unwrap_or_else(|err| panic!("{case}: {err}"));
+        assert_eq!(
+            result,
+            (expected.amount0, expected.amount1),
+            "{case}: exact output"
+        );
+
+        true
+    }
+
+    #[test]
+    fn test_reference_swap_fixture() {
+        //the fixture pool, [600, 14400] 1e18, [4200, 9000] 5e17 and [6600, 7800] 2e18
+        let pool = ReferencePool {
+            fee: 3000,
+            tick_spacing: 60,
+            liquidity: 3_500_000_000_000_000_000,
+            sqrt_price_x96: uint!(112430957613446531369126327961_U256),
+            tick: 7000,
+            ticks: BTreeMap::from([
+                (600, 1_000_000_000_000_000_000),
+                (4200, 500_000_000_000_000_000),
+                (6600, 2_000_000_000_000_000_000),
+                (7800, -2_000_000_000_000_000_000),
+                (9000, -500_000_000_000_000_000),
+                (14400, -1_000_000_000_000_000_000),
+            ]),
+        };
+        let math = pool.math().unwrap();
+
+        //out to the price bounds, walking every empty word on the way
+        for zero_for_one in [true, false] {
+            let limit = if zero_for_one {
+                MIN_SQRT_RATIO + RUINT_ONE
+            } else {
+                MAX_SQRT_RATIO - RUINT_ONE
+            };
+            for amount_in in [
+                uint!(1000000000000000_U256),
+                uint!(300000000000000000_U256),
+                uint!(1000000000000000000_U256),
+                uint!(100000000000000000000_U256),
+            ] {
+                let expected = pool
+                    .reference_swap(zero_for_one, u256_to_i256(amount_in), limit)
+                    .unwrap();
+                let result = math.simulate_swap(zero_for_one, amount_in).unwrap();
+                assert_eq!(
+                    result,
+                    if zero_for_one {
+                        expected.amount1.unsigned_abs()
+                    } else {
+                        expected.amount0.unsigned_abs()
+                    }
+                );
+                let signed = math
+                    .simulate_swap_signed(zero_for_one, u256_to_i256(amount_in))
+                    .unwrap();
+                assert_eq!(signed, (expected.amount0, expected.amount1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_word_edge_suite() {
+        let cases = edge_cases();
+        let full = std::env::var_os(FULL_SUITE_VAR).is_some();
+
+        //every seventh case, 7 being coprime with each dimension's size, covers every value of
+        // every dimension
+        let mut checked = 0;
+        for (i, case) in cases.iter().enumerate() {
+            if (full || i % 7 == 0) && check_edge_case(case) {
+                checked += 1;
+            }
+        }
+        assert!(checked > if full { 4000 } else { 600 }, "{checked} cases");
+    }
+}
@@ -1,12 +1,188 @@
-use super::U256;
-
-pub struct Tick {
-    pub liquidity_gross: u128,
-    pub liquidity_net: i128,
-    pub fee_growth_outside_0_x_128: U256,
-    pub fee_growth_outside_1_x_128: U256,
-    pub tick_cumulative_outside: U256,
-    pub seconds_per_liquidity_outside_x_128: U256,
-    pub seconds_outside: u32,
-    pub initialized: bool,
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
+use crate::{
+    error::UniswapV3MathError,
+    tick_math::{calculate_compressed, MAX_TICK, MIN_TICK},
+};
+
+// A tick within [MIN_TICK, MAX_TICK]. Kept apart from CompressedTick so that a tick can't be passed
+// where the tick bitmap expects a tick divided by the spacing, or the other way around. The
+// pool's Tick.Info is storage::TickInfo.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(i32);
+
+impl Tick {
+    pub const MIN: Tick = Tick(MIN_TICK);
+    pub const MAX: Tick = Tick(MAX_TICK);
+
+    pub fn new(tick: i32) -> Result<Self, UniswapV3MathError> {
+        if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+            return Err(UniswapV3MathError::TickOutOfRange { tick });
+        }
+
+        Ok(Tick(tick))
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+
+    // The tick's index in a tick bitmap with tick_spacing, rounded towards negative infinity like
+    // TickBitmap.nextInitializedTickWithinOneWord does
+    pub fn compress(self, tick_spacing: i32) -> CompressedTick {
+        calculate_compressed(self.0, tick_spacing)
+    }
+
+    // False for a non-positive tick_spacing, which no tick is aligned to
+    pub fn is_aligned(self, tick_spacing: i32) -> bool {
+        tick_spacing > 0 && self.0 % tick_spacing == 0
+    }
+
+    // The tick delta ticks away, or TickOutOfRange when that is past the bounds
+    pub fn checked_add(self, delta: i32) -> Result<Self, UniswapV3MathError> {
+        Tick::new(self.0.saturating_add(delta))
+    }
+}
+
+impl TryFrom<i32> for Tick {
+    type Error = UniswapV3MathError;
+
+    fn try_from(tick: i32) -> Result<Self, Self::Error> {
+        Tick::new(tick)
+    }
+}
+
+impl From<Tick> for i32 {
+    fn from(tick: Tick) -> Self {
+        tick.0
+    }
+}
+
+// Distance between two ticks
+impl Sub for Tick {
+    type Output = i32;
+
+    fn sub(self, other: Tick) -> i32 {
+        self.0 - other.0
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// A tick divided by the tick spacing, the index of its bit in the tick bitmap. Bitmap words extend
+// past the usable ticks, so it isn't bounds checked; decompress checks the tick it maps back to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompressedTick(i32);
+
+impl CompressedTick {
+    pub fn new(compressed: i32) -> Self {
+        CompressedTick(compressed)
+    }
+
+    pub fn get(self) -> i32 {
+        self.0
+    }
+
+    pub fn decompress(self, tick_spacing: i32) -> Result<Tick, UniswapV3MathError> {
+        Tick::new(self.0.saturating_mul(tick_spacing))
+    }
+
+    // (word_pos, bit_pos) of the tick's bit, the pool's TickBitmap.position
+    pub fn position(self) -> (i16, u8) {
+        ((self.0 >> 8) as i16, (self.0 % 256) as u8)
+    }
+}
+
+impl From<i32> for CompressedTick {
+    fn from(compressed: i32) -> Self {
+        CompressedTick::new(compressed)
+    }
+}
+
+impl From<CompressedTick> for i32 {
+    fn from(compressed: CompressedTick) -> Self {
+        compressed.0
+    }
+}
+
+// Moves along the bitmap by a number of bits
+impl Add<i32> for CompressedTick {
+    type Output = CompressedTick;
+
+    fn add(self, bits: i32) -> CompressedTick {
+        CompressedTick(self.0 + bits)
+    }
+}
+
+impl Sub<i32> for CompressedTick {
+    type Output = CompressedTick;
+
+    fn sub(self, bits: i32) -> CompressedTick {
+        CompressedTick(self.0 - bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        assert_eq!(Tick::new(MIN_TICK).unwrap(), Tick::MIN);
+        assert_eq!(Tick::try_from(MAX_TICK).unwrap(), Tick::MAX);
+        assert_eq!(i32::from(Tick::new(-60).unwrap()), -60);
+        for tick in [MIN_TICK - 1, MAX_TICK + 1, i32::MIN, i32::MAX] {
+            assert!(matches!(
+                Tick::new(tick),
+                Err(UniswapV3MathError::TickOutOfRange { tick: out }) if out == tick
+            ));
+        }
+
+        let tick = Tick::new(-61).unwrap();
+        assert!(!tick.is_aligned(60));
+        assert!(Tick::new(-120).unwrap().is_aligned(60));
+        for tick_spacing in [0, -60, i32::MIN] {
+            assert!(!Tick::new(-120).unwrap().is_aligned(tick_spacing));
+        }
+        assert_eq!(tick.checked_add(1).unwrap().get(), -60);
+        assert!(Tick::MAX.checked_add(1).is_err());
+        assert!(Tick::MIN.checked_add(i32::MIN).is_err());
+        assert_eq!(Tick::MAX - Tick::MIN, 2 * MAX_TICK);
+        assert_eq!(tick.to_string(), "-61");
+    }
+
+    #[test]
+    fn test_compressed_tick() {
+        //negative unaligned ticks round down, towards the next lower multiple
+        for (tick, expected) in [(-61, -2), (-60, -1), (-1, -1), (0, 0), (59, 0), (60, 1)] {
+            assert_eq!(Tick::new(tick).unwrap().compress(60).get(), expected);
+        }
+
+        let compressed = Tick::new(-887220).unwrap().compress(60);
+        assert_eq!(compressed.get(), -14787);
+        assert_eq!(compressed.decompress(60).unwrap().get(), -887220);
+        assert_eq!(compressed.position(), (-58, 61));
+        assert_eq!((compressed + 1).get(), -14786);
+        assert_eq!((compressed - 1).get(), -14788);
+
+        //MIN_TICK rounds down to a bit whose tick is past the bounds
+        assert_eq!(Tick::MIN.compress(60), compressed - 1);
+        assert!(matches!(
+            (compressed - 1).decompress(60),
+            Err(UniswapV3MathError::TickOutOfRange { tick: -887280 })
+        ));
+        assert!(CompressedTick::new(i32::MAX).decompress(60).is_err());
+
+        assert_eq!(CompressedTick::from(-14787), compressed);
+        assert_eq!(i32::from(compressed), -14787);
+        assert_eq!(CompressedTick::new(-1).position(), (-1, 255));
+        assert_eq!(CompressedTick::new(256).position(), (1, 0));
+    }
 }
@@ -1,5 +1,11 @@
 use super::U256;
-use crate::{bit_math, error::UniswapV3MathError, utils::RUINT_ONE, TicksProvider};
+use crate::{
+    bit_math,
+    error::UniswapV3MathError,
+    tick::{CompressedTick, Tick},
+    utils::RUINT_ONE,
+    TicksProvider,
+};
 
 //Returns next and initialized
 //current_word is the current word in the TickBitmap of the pool based on `tick`.
@@ -9,20 +15,23 @@ use crate::{bit_math, error::UniswapV3MathError, utils::RUINT_ONE, TicksProvider
 //current_word is the current word in the TickBitmap of the pool based on `tick`.
 // TickBitmap[word_pos] = current_word Where word_pos is the 256 bit offset of the ticks word_pos..
 // word_pos := tick >> 8
+// The returned tick is uncompressed. At the ends of the bitmap it can lie past MIN_TICK or MAX_TICK,
+// hence an i32 rather than a Tick.
 pub fn next_initialized_tick_within_one_word(
     bit_pos: u8,
     word: U256,
     tick_spacing: i32,
     lte: bool,
-    compressed: i32,
+    compressed: CompressedTick,
 ) -> Result<(i32, bool), UniswapV3MathError> {
+    let compressed = compressed.get();
     if lte {
         let mask: U256 =
             (RUINT_ONE << bit_pos as usize) - RUINT_ONE + (RUINT_ONE << bit_pos as usize);
 
         let masked = word & mask;
 
-        let initialized = !masked == U256::ZERO;
+        let initialized = masked != U256::ZERO;
 
         let next = if initialized {
             (compressed
@@ -39,7 +48,7 @@ pub fn next_initialized_tick_within_one_word(
         let mask: U256 = !((RUINT_ONE << bit_pos as usize) - RUINT_ONE);
 
         let masked = word & mask;
-        let initialized = !masked == U256::ZERO;
+        let initialized = masked != U256::ZERO;
 
         let next = if initialized {
             (compressed
@@ -61,7 +70,7 @@ pub fn next_initialized_tick_within_one_word(
 // TickBitmap[word_pos] = current_word Where word_pos is the 256 bit offset of the ticks word_pos..
 // word_pos := tick >> 8
 pub fn next_initialized_tick_within_one_word_from_provider<P>(
-    tick: i32,
+    tick: Tick,
     tick_spacing: i32,
     lte: bool,
     data_provider: P,
@@ -69,21 +78,18 @@ pub fn next_initialized_tick_within_one_word_from_provider<P>(
 where
     P: TicksProvider,
 {
-    let compressed = if tick < 0 && tick % tick_spacing != 0 {
-        (tick / tick_spacing) - 1
-    } else {
-        tick / tick_spacing
-    };
+    let compressed = tick.compress(tick_spacing);
 
     if lte {
-        let (word_pos, bit_pos) = position(compressed);
+        let (word_pos, bit_pos) = compressed.position();
+        let compressed = compressed.get();
         let mask = (RUINT_ONE << bit_pos as usize) - RUINT_ONE + (RUINT_ONE << bit_pos as usize);
 
         let word: U256 = data_provider.get_word_at_position(word_pos)?;
 
         let masked = word & mask;
 
-        let initialized = !masked == U256::ZERO;
+        let initialized = masked != U256::ZERO;
 
         let next = if initialized {
             (compressed
@@ -97,7 +103,8 @@ where
 
         Ok((next, initialized))
     } else {
-        let (word_pos, bit_pos) = position(compressed + 1);
+        let (word_pos, bit_pos) = (compressed + 1).position();
+        let compressed = compressed.get();
 
         let mask = !((RUINT_ONE << bit_pos as usize) - RUINT_ONE);
 
@@ -105,7 +112,7 @@ where
 
         let masked = word & mask;
 
-        let initialized = !masked == U256::ZERO;
+        let initialized = masked != U256::ZERO;
 
         let next = if initialized {
             (compressed
@@ -123,6 +130,17 @@ where
 }
 
 // returns (int16 wordPos, uint8 bitPos)
-pub fn position(tick: i32) -> (i16, u8) {
-    ((tick >> 8) as i16, (tick % 256) as u8)
+pub fn position(compressed: CompressedTick) -> (i16, u8) {
+    compressed.position()
+}
+
+// Ticks whose bits are set in the bitmap word at word_pos, in increasing order
+pub fn initialized_ticks(
+    word_pos: i16,
+    word: U256,
+    tick_spacing: i32,
+) -> impl Iterator<Item = i32> {
+    (0..256usize)
+        .filter(move |bit_pos| word.bit(*bit_pos))
+        .map(move |bit_pos| (word_pos as i32 * 256 + bit_pos as i32) * tick_spacing)
 }
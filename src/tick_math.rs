@@ -1,10 +1,10 @@
 use crate::utils::{u256_to_i256, RUINT_ONE};
 use alloy_primitives::I256;
 use reth_primitives::U256;
-use ruint::uint;
-use std::ops::{BitOr, Shl, Shr};
+use ruint::aliases::{U1024, U512};
+use std::f64::consts::LN_10;
 
-use crate::error::UniswapV3MathError;
+use crate::{error::UniswapV3MathError, tick::CompressedTick};
 
 pub const MIN_TICK: i32 = -887272;
 pub const MAX_TICK: i32 = -MIN_TICK;
@@ -13,208 +13,523 @@ pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
 pub const MAX_SQRT_RATIO: U256 =
     U256::from_limbs([6743328256752651558, 17280870778742802505, 4294805859, 0]);
 
+// sqrt(1.0001)^-(2^i) as Q128.128 for bits 1 through 19 of the absolute tick, bit 0's factor is
+// the first in the product
+const SQRT_RATIO_FACTORS: [u128; 19] = [
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
 pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256, UniswapV3MathError> {
-    let abs_tick = U256::from(tick.abs());
+    let abs_tick = tick.unsigned_abs();
 
-    if abs_tick > U256::from(MAX_TICK) {
+    if abs_tick > MAX_TICK as u32 {
         return Err(UniswapV3MathError::T);
     }
 
-    let mut ratio = if abs_tick & (U256::from(0x1)) != U256::ZERO {
-        uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256)
-    } else {
-        uint!(0x100000000000000000000000000000000_U256)
-    };
-
-    if !(abs_tick & (U256::from(0x2))) == U256::ZERO {
-        ratio = (ratio * uint!(0xfff97272373d413259a46990580e213a_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x4))) == U256::ZERO {
-        ratio = (ratio * uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x8))) == U256::ZERO {
-        ratio = (ratio * uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x10))) == U256::ZERO {
-        ratio = (ratio * uint!(0xffcb9843d60f6159c9db58835c926644_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x20))) == U256::ZERO {
-        ratio = (ratio * uint!(0xff973b41fa98c081472e6896dfb254c0_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x40))) == U256::ZERO {
-        ratio = (ratio * uint!(0xff2ea16466c96a3843ec78b326b52861_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x80))) == U256::ZERO {
-        ratio = (ratio * uint!(0xfe5dee046a99a2a811c461f1969c3053_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x100))) == U256::ZERO {
-        ratio = (ratio * uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x200))) == U256::ZERO {
-        ratio = (ratio * uint!(0xf987a7253ac413176f2b074cf7815e54_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x400))) == U256::ZERO {
-        ratio = (ratio * uint!(0xf3392b0822b70005940c7a398e4b70f3_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x800))) == U256::ZERO {
-        ratio = (ratio * uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x1000))) == U256::ZERO {
-        ratio = (ratio * uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x2000))) == U256::ZERO {
-        ratio = (ratio * uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x4000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x8000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x31be135f97d08fd981231505542fcfa6_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x10000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x20000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x5d6af8dedb81196699c329225ee604_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x40000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x2216e584f5fa1ea926041bedfe98_U256)) >> 128
-    }
-    if !(abs_tick & (U256::from(0x80000))) == U256::ZERO {
-        ratio = (ratio * uint!(0x48a170391f7dc42444e8fa2_U256)) >> 128
+    // Every factor is below 2^128, so the Q128.128 product fits a u128 once the first one is in
+    // and each factor only needs the high half of a 128 by 128 bit multiplication. None is the
+    // product before any factor, 2^128.
+    let mut ratio = (abs_tick & 0x1 != 0).then_some(0xfffcb933bd6fad37aa2d162d1a594001_u128);
+    for (bit, factor) in SQRT_RATIO_FACTORS.iter().enumerate() {
+        if abs_tick & (0x2 << bit) != 0 {
+            ratio = Some(match ratio {
+                Some(ratio) => mul_shr_128(ratio, *factor),
+                None => *factor,
+            });
+        }
     }
 
-    if tick > 0 {
-        ratio = U256::MAX / ratio;
-    }
+    let ratio = match ratio {
+        Some(ratio) if tick > 0 => U256::MAX / U256::from(ratio),
+        Some(ratio) => U256::from(ratio),
+        None => RUINT_ONE << 128usize,
+    };
 
-    Ok((ratio >> 32)
-        + if (ratio % (RUINT_ONE << 32)) == U256::ZERO {
+    Ok((ratio >> 32usize)
+        + if ratio.as_limbs()[0] & 0xffffffff == 0 {
             U256::ZERO
         } else {
             RUINT_ONE
         })
 }
 
+// (a * b) >> 128 without overflow, from the 64 bit halves' products
+fn mul_shr_128(a: u128, b: u128) -> u128 {
+    let (a_lo, a_hi) = (a as u64 as u128, a >> 64);
+    let (b_lo, b_hi) = (b as u64 as u128, b >> 64);
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let carry = ((a_lo * b_lo) >> 64) + (lo_hi as u64 as u128) + (hi_lo as u64 as u128);
+
+    a_hi * b_hi + (lo_hi >> 64) + (hi_lo >> 64) + (carry >> 64)
+}
+
 pub fn get_tick_at_sqrt_ratio(sqrt_price_x_96: U256) -> Result<i32, UniswapV3MathError> {
     if !(sqrt_price_x_96 >= MIN_SQRT_RATIO && sqrt_price_x_96 < MAX_SQRT_RATIO) {
         return Err(UniswapV3MathError::R);
     }
 
-    let ratio = sqrt_price_x_96.shl(32);
-    let mut r = ratio;
-    let mut msb = U256::ZERO;
+    // TickMath stops at fractional bit 50, which the error bounds of tick_low and tick_high are
+    // derived for
+    let log_2 = log2_x64_to_bit(sqrt_price_x_96 << 32usize, 50)?;
+    let log_sqrt10001 = log_2.wrapping_mul(log_sqrt10001_multiplier());
 
-    let mut f = if r > uint!(0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF_U256) {
-        1_usize.shl(7)
-    } else {
-        0
-    };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
+    let tick_low = ((log_sqrt10001
+        - I256::from_dec_str("3402992956809132418596140100660247210").unwrap())
+        >> 128_u8)
+        .low_i32();
 
-    f = if r > uint!(0xFFFFFFFFFFFFFFFF_U256) {
-        1_usize.shl(6)
-    } else {
-        0
-    };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
+    let tick_high = ((log_sqrt10001
+        + I256::from_dec_str("291339464771989622907027621153398088495").unwrap())
+        >> 128_u8)
+        .low_i32();
 
-    f = if r > uint!(0xFFFFFFFF_U256) {
-        1_usize.shl(5)
+    let tick = if tick_low == tick_high {
+        tick_low
+    } else if get_sqrt_ratio_at_tick(tick_high)? <= sqrt_price_x_96 {
+        tick_high
     } else {
-        0
+        tick_low
     };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
 
-    f = if r > uint!(0xFFFF_U256) {
-        1_usize.shl(4)
-    } else {
-        0
-    };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
+    Ok(tick)
+}
 
-    f = if r > uint!(0xFF_U256) {
-        1_usize.shl(3)
-    } else {
-        0
-    };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
+// log2(ratio / 2^128) as a Q64.64 number, for a Q128.128 ratio such as sqrt_price_x96 << 32.
+// Computes every fractional bit the way TickMath computes its top 14, by squaring the ratio
+// normalized to [1, 2), so the result is the exact log rounded down or at most a unit below it.
+pub fn log2_x64(ratio: U256) -> Result<I256, UniswapV3MathError> {
+    log2_x64_to_bit(ratio, 0)
+}
 
-    f = if r > uint!(0xF_U256) {
-        1_usize.shl(2)
-    } else {
-        0
-    };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
+// log_1.0001 of the price at sqrt_price_x96 as a Q128.128 number, its tick with the fraction of
+// the way to the next one. get_tick_at_sqrt_ratio is its floor.
+pub fn log_1_0001_x128(sqrt_price_x96: U256) -> Result<I256, UniswapV3MathError> {
+    if !(sqrt_price_x96 >= MIN_SQRT_RATIO && sqrt_price_x96 < MAX_SQRT_RATIO) {
+        return Err(UniswapV3MathError::R);
+    }
 
-    f = if r > uint!(0x3_U256) {
-        1_usize.shl(1_usize)
+    Ok(log2_x64(sqrt_price_x96 << 32usize)?.wrapping_mul(log_sqrt10001_multiplier()))
+}
+
+// log2_x64 with the fractional bits below lowest_bit left at zero
+fn log2_x64_to_bit(ratio: U256, lowest_bit: usize) -> Result<I256, UniswapV3MathError> {
+    if ratio == U256::ZERO {
+        return Err(UniswapV3MathError::ZeroValue);
+    }
+
+    let msb = ratio.bit_len() - 1;
+    // The ratio as a Q1.127 number in [1, 2)
+    let mut r = if msb >= 128 {
+        ratio >> (msb - 127)
     } else {
-        0
+        ratio << (127 - msb)
     };
-    msb = msb.bitor(U256::from(f));
-    r = r.shr(f);
 
-    f = if r > uint!(0x1_U256) { 1_usize } else { 0 };
+    let mut log_2 = (u256_to_i256(U256::from(msb)) - u256_to_i256(U256::from(128))) << 64usize;
+
+    // Squaring r doubles its log, an overflow past 2 is the next bit
+    for bit in (lowest_bit..64).rev() {
+        r = r.overflowing_mul(r).0 >> 127usize;
+        let f = r >> 128usize;
+        log_2 |= u256_to_i256(f << bit);
+
+        r >>= f.to::<usize>();
+    }
+
+    Ok(log_2)
+}
+
+// 2^64 / log2(sqrt(1.0001)), turning a Q64.64 log2 of the sqrt price into a Q128.128 log_1.0001 of
+// the price
+fn log_sqrt10001_multiplier() -> I256 {
+    I256::from_dec_str("255738958999603826347141").unwrap()
+}
+
+// Converts an ascending list of sqrt ratios to ticks. Nearby prices usually share a tick or sit a
+// tick or two apart, so each conversion first tries to extend the previous tick's bracket and only
+// falls back to get_tick_at_sqrt_ratio when the price has moved further than that.
+pub fn get_ticks_at_sqrt_ratios(sorted_prices: &[U256]) -> Result<Vec<i32>, UniswapV3MathError> {
+    // How many ticks past the previous result are probed before falling back to the full algorithm
+    const MAX_BRACKET_STEPS: usize = 2;
+
+    let mut ticks = Vec::with_capacity(sorted_prices.len());
+    // The last tick and the sqrt ratio at the tick above it
+    let mut bracket: Option<(i32, U256)> = None;
+
+    for (i, &sqrt_price_x_96) in sorted_prices.iter().enumerate() {
+        if i > 0 && sqrt_price_x_96 < sorted_prices[i - 1] {
+            return Err(UniswapV3MathError::SqrtRatiosNotSorted);
+        }
+
+        if !(sqrt_price_x_96 >= MIN_SQRT_RATIO && sqrt_price_x_96 < MAX_SQRT_RATIO) {
+            return Err(UniswapV3MathError::R);
+        }
+
+        // Sorted input guarantees the price is at or above the previous tick's ratio, so only the
+        // upper edge of the bracket has to be checked
+        let mut tick = None;
+        if let Some((mut prev_tick, mut upper)) = bracket {
+            for _ in 0..=MAX_BRACKET_STEPS {
+                if sqrt_price_x_96 < upper {
+                    tick = Some((prev_tick, upper));
+                    break;
+                }
+                prev_tick += 1;
+                upper = get_sqrt_ratio_at_tick(prev_tick + 1)?;
+            }
+        }
+
+        let (tick, upper) = match tick {
+            Some(bracket) => bracket,
+            None => {
+                let tick = get_tick_at_sqrt_ratio(sqrt_price_x_96)?;
+                (tick, get_sqrt_ratio_at_tick(tick + 1)?)
+            }
+        };
+
+        ticks.push(tick);
+        bracket = Some((tick, upper));
+    }
+
+    Ok(ticks)
+}
+
+// Lowest tick a pool with tick_spacing can initialize, MIN_TICK rounded up to a multiple of it.
+// tick_spacing must be positive, like for max_usable_tick: 0 panics and a negative spacing has no
+// meaningful result. validate_tick reports InvalidTickSpacing for both.
+pub fn min_usable_tick(tick_spacing: i32) -> i32 {
+    (MIN_TICK / tick_spacing) * tick_spacing
+}
+
+// Highest tick a pool with tick_spacing can initialize, MAX_TICK rounded down to a multiple of it
+pub fn max_usable_tick(tick_spacing: i32) -> i32 {
+    (MAX_TICK / tick_spacing) * tick_spacing
+}
+
+// Fee tiers the Uniswap V3 factory enables, in hundredths of a bip, and their tick spacings
+pub const FEE_TIERS: [(u32, i32); 4] = [(100, 1), (500, 10), (3000, 60), (10000, 200)];
+
+// Tick spacing of a canonical fee tier, None for fees the factory doesn't enable
+pub fn tick_spacing_for_fee(fee: u32) -> Option<i32> {
+    FEE_TIERS
+        .iter()
+        .find(|(tier_fee, _)| *tier_fee == fee)
+        .map(|(_, tick_spacing)| *tick_spacing)
+}
+
+// Fee of the canonical fee tier with tick_spacing
+pub fn fee_for_tick_spacing(tick_spacing: i32) -> Option<u32> {
+    FEE_TIERS
+        .iter()
+        .find(|(_, tier_spacing)| *tier_spacing == tick_spacing)
+        .map(|(fee, _)| *fee)
+}
 
-    msb = msb.bitor(U256::from(f));
-    let msb: usize = msb.to();
+pub fn is_canonical_fee(fee: u32) -> bool {
+    tick_spacing_for_fee(fee).is_some()
+}
 
-    r = if msb >= 128 {
-        ratio.shr(msb - 127)
+// Multiples of tick_spacing from `from` to `to` inclusive, within the usable ticks. Unaligned
+// bounds snap inwards, so iterating forward starts at the first multiple at or above from and
+// iterating in reverse, for zero_for_one walks, at the last one at or below to. Empty when from is
+// above to or tick_spacing isn't positive.
+pub fn tick_range(from: i32, to: i32, tick_spacing: i32) -> impl DoubleEndedIterator<Item = i32> {
+    let (lower, upper) = if tick_spacing > 0 {
+        let from = from.max(min_usable_tick(tick_spacing));
+        let to = to.min(max_usable_tick(tick_spacing));
+        // Compressed ticks, from rounded up and to rounded down
+        (
+            -(-from).div_euclid(tick_spacing),
+            to.div_euclid(tick_spacing),
+        )
     } else {
-        ratio.shl(127 - msb)
+        (1, 0)
     };
 
-    let mut log_2: I256 = (u256_to_i256(U256::from(msb)) - u256_to_i256(U256::from(128))).shl(64);
+    (lower..=upper).map(move |compressed| compressed * tick_spacing)
+}
+
+// Checks a tick the way the pool does before initializing it: within [MIN_TICK, MAX_TICK] and a
+// multiple of a positive tick_spacing
+pub fn validate_tick(tick: i32, tick_spacing: i32) -> Result<(), UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing });
+    }
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(UniswapV3MathError::TickOutOfRange { tick });
+    }
+    if tick % tick_spacing != 0 {
+        return Err(UniswapV3MathError::TickNotAligned {
+            tick,
+            spacing: tick_spacing,
+        });
+    }
 
-    for i in (51..=63).rev() {
-        r = r.overflowing_mul(r).0.shr(127);
-        let f = r.shr(128);
-        log_2 = log_2.bitor(u256_to_i256(f.shl(i)));
+    Ok(())
+}
 
-        r = r.shr(f.to::<usize>());
+// Checks a position's ticks in the pool's order: checkTicks requires lower below upper and both
+// within bounds, then flipTick requires each to be aligned
+pub fn validate_tick_range(
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_spacing: i32,
+) -> Result<(), UniswapV3MathError> {
+    if tick_lower >= tick_upper {
+        return Err(UniswapV3MathError::InvalidTickRange {
+            tick_lower,
+            tick_upper,
+        });
+    }
+    for tick in [tick_lower, tick_upper] {
+        if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+            return Err(UniswapV3MathError::TickOutOfRange { tick });
+        }
     }
 
-    r = r.overflowing_mul(r).0.shr(127);
-    let f = r.shr(128);
-    log_2 = log_2.bitor(u256_to_i256(f.shl(50)));
+    validate_tick(tick_lower, tick_spacing)?;
+    validate_tick(tick_upper, tick_spacing)
+}
 
-    let log_sqrt10001 = log_2.wrapping_mul(I256::from_dec_str("255738958999603826347141").unwrap());
+// get_sqrt_ratio_at_tick precomputed for every usable tick of a tick spacing, for swap loops that
+// would otherwise compute one per step. Takes 32 bytes per tick, about 5.7MB at spacing 10 and
+// under 1MB at 60 and up, so build one per spacing and share it between pools with
+// Math::with_sqrt_cache.
+#[derive(Clone)]
+pub struct SqrtRatioCache {
+    tick_spacing: i32,
+    min_tick: i32,
+    ratios: Vec<U256>,
+}
 
-    let tick_low = ((log_sqrt10001
-        - I256::from_dec_str("3402992956809132418596140100660247210").unwrap())
-        >> 128_u8)
-        .low_i32();
+impl SqrtRatioCache {
+    pub fn new(tick_spacing: i32) -> Result<Self, UniswapV3MathError> {
+        if tick_spacing <= 0 {
+            return Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing });
+        }
 
-    let tick_high = ((log_sqrt10001
-        + I256::from_dec_str("291339464771989622907027621153398088495").unwrap())
-        >> 128_u8)
-        .low_i32();
+        let min_tick = min_usable_tick(tick_spacing);
+        let ratios = (min_tick..=max_usable_tick(tick_spacing))
+            .step_by(tick_spacing as usize)
+            .map(get_sqrt_ratio_at_tick)
+            .collect::<Result<_, _>>()?;
 
-    let tick = if tick_low == tick_high {
-        tick_low
-    } else if get_sqrt_ratio_at_tick(tick_high)? <= sqrt_price_x_96 {
-        tick_high
+        Ok(SqrtRatioCache {
+            tick_spacing,
+            min_tick,
+            ratios,
+        })
+    }
+
+    pub fn tick_spacing(&self) -> i32 {
+        self.tick_spacing
+    }
+
+    // Same as get_sqrt_ratio_at_tick, read from the cache for usable multiples of the tick spacing
+    // and computed for any other tick
+    pub fn get(&self, tick: i32) -> Result<U256, UniswapV3MathError> {
+        if tick % self.tick_spacing == 0 {
+            let index = tick
+                .checked_sub(self.min_tick)
+                .and_then(|offset| usize::try_from(offset / self.tick_spacing).ok());
+            if let Some(ratio) = index.and_then(|index| self.ratios.get(index)) {
+                return Ok(*ratio);
+            }
+        }
+
+        get_sqrt_ratio_at_tick(tick)
+    }
+}
+
+impl std::fmt::Debug for SqrtRatioCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqrtRatioCache")
+            .field("tick_spacing", &self.tick_spacing)
+            .field("ticks", &self.ratios.len())
+            .finish()
+    }
+}
+
+// Price of one whole token0 in whole token1 at tick, 1.0001^tick scaled by 10^(decimals_0 -
+// decimals_1). The f64 result is within about 1e-14 relative error, far below the 1e-4 between
+// ticks, so price_to_tick always recovers the tick. Past f64's range, for extreme ticks combined
+// with large decimal differences, it saturates to 0 or infinity. Callers that need the exact
+// price can square get_sqrt_ratio_at_tick over 2^192 (Math::price_at_tick) instead.
+pub fn tick_to_price(tick: i32, decimals_0: u8, decimals_1: u8) -> f64 {
+    (tick as f64 * ln_1_0001()).exp() * 10f64.powi(decimals_0 as i32 - decimals_1 as i32)
+}
+
+// Tick whose price, as given by tick_to_price, is nearest to price in log space. The decimals are
+// taken out in log space too, so prices whose raw value would overflow an f64 still convert.
+// Ticks near the boundary between two are only as precise as price: a price within about 1e-12
+// relative of the midpoint can round either way.
+pub fn price_to_tick(
+    price: f64,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> Result<i32, UniswapV3MathError> {
+    if !(price.is_finite() && price > 0.0) {
+        return Err(UniswapV3MathError::InvalidPrice { price });
+    }
+
+    let ln_raw_price = price.ln() - (decimals_0 as i32 - decimals_1 as i32) as f64 * LN_10;
+    let tick = (ln_raw_price / ln_1_0001()).round();
+    if !(MIN_TICK as f64..=MAX_TICK as f64).contains(&tick) {
+        return Err(UniswapV3MathError::TickOutOfRange { tick: tick as i32 });
+    }
+
+    Ok(tick as i32)
+}
+
+// Price of one whole token0 in whole token1 at sqrt_price_x96, sqrt_price_x96^2 / 2^192 scaled by
+// 10^(decimals_0 - decimals_1), as a decimal with precision fractional digits rounded half up. The
+// square is taken in 512 bits, and the raw price being a fraction over 2^192 its expansion ends
+// within 192 fractional digits: from precision 192 + decimals_1 - decimals_0 the string is exact.
+pub fn sqrt_price_x96_to_decimal(
+    sqrt_price_x96: U256,
+    decimals_0: u8,
+    decimals_1: u8,
+    precision: usize,
+) -> String {
+    let square = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+    let fraction_mask = (U512::from(1) << 192usize) - U512::from(1);
+
+    let mut digits = (square >> 192usize).to_string().into_bytes();
+    let integer_digits = digits.len() as i64;
+    // The price's digits up to precision, plus one to round on
+    let kept = integer_digits + decimals_0 as i64 - decimals_1 as i64 + precision as i64;
+    let mut remainder = square & fraction_mask;
+    while (digits.len() as i64) <= kept {
+        remainder *= U512::from(10);
+        digits.push(b'0' + (remainder >> 192usize).to::<u8>());
+        remainder &= fraction_mask;
+    }
+
+    // Everything past the kept digits rounds to nothing when they start past the first digit
+    let round_up = kept >= 0 && digits[kept as usize] >= b'5';
+    digits.truncate(kept.max(0) as usize);
+    if round_up {
+        increment_digits(&mut digits);
+    }
+
+    // digits is now the price times 10^precision
+    if digits.len() <= precision {
+        let mut padded = vec![b'0'; precision + 1 - digits.len()];
+        padded.append(&mut digits);
+        digits = padded;
+    }
+    let fraction = digits.split_off(digits.len() - precision);
+    let integer = String::from_utf8(digits).unwrap();
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        integer => integer,
+    };
+    if fraction.is_empty() {
+        integer.to_string()
     } else {
-        tick_low
+        format!("{integer}.{}", String::from_utf8(fraction).unwrap())
+    }
+}
+
+// Sqrt price whose price, as printed by sqrt_price_x96_to_decimal, is price: the integer square root
+// of price * 10^(decimals_1 - decimals_0) * 2^192, rounded down. price is a plain decimal such as
+// "1843.52" with up to 250 significant digits, enough for the exact expansion of any sqrt price.
+pub fn decimal_to_sqrt_price_x96(
+    price: &str,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> Result<U256, UniswapV3MathError> {
+    let invalid = || UniswapV3MathError::InvalidDecimalPrice {
+        price: price.to_string(),
     };
 
-    Ok(tick)
+    let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+    let fraction = fraction.trim_end_matches('0');
+    let all_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) || price.ends_with('.') {
+        return Err(invalid());
+    }
+
+    let significant = format!("{integer}{fraction}");
+    let significant = significant.trim_start_matches('0');
+    if significant.len() > 250 {
+        return Err(invalid());
+    }
+    let numerator = significant.bytes().fold(U1024::ZERO, |numerator, digit| {
+        numerator * U1024::from(10) + U1024::from(digit - b'0')
+    }) << 192usize;
+
+    // price * 2^192 / 10^(fraction digits + decimals_0 - decimals_1), which is below the range when
+    // the power of ten doesn't fit and above it when the product doesn't
+    let exponent = fraction.len() as i64 + decimals_0 as i64 - decimals_1 as i64;
+    let ten = U1024::from(10);
+    let ratio_x192 = if exponent >= 0 {
+        match ten.checked_pow(U1024::from(exponent)) {
+            Some(denominator) => numerator / denominator,
+            None => U1024::ZERO,
+        }
+    } else {
+        ten.checked_pow(U1024::from(-exponent))
+            .and_then(|factor| numerator.checked_mul(factor))
+            .unwrap_or(U1024::MAX)
+    };
+
+    let sqrt_price_x96 = ratio_x192.root(2);
+    if sqrt_price_x96 < U1024::from(MIN_SQRT_RATIO) || sqrt_price_x96 >= U1024::from(MAX_SQRT_RATIO)
+    {
+        return Err(UniswapV3MathError::SqrtPriceOutOfRange {
+            sqrt_price_x96: U256::saturating_from(sqrt_price_x96),
+        });
+    }
+
+    Ok(U256::from(sqrt_price_x96))
 }
 
-pub fn calculate_compressed(tick: i32, tick_spacing: i32) -> i32 {
-    if tick < 0 && tick % tick_spacing != 0 {
+// Adds one to a decimal number given as ASCII digits
+fn increment_digits(digits: &mut Vec<u8>) {
+    for digit in digits.iter_mut().rev() {
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            return;
+        }
+    }
+    digits.insert(0, b'1');
+}
+
+// ln(1.0001), through ln_1p to keep the precision 1.0001 loses as an f64
+fn ln_1_0001() -> f64 {
+    0.0001f64.ln_1p()
+}
+
+// tick's index in a tick bitmap with tick_spacing, see Tick::compress. Takes a raw i32 for ticks
+// the swap loop can leave one below MIN_TICK.
+pub fn calculate_compressed(tick: i32, tick_spacing: i32) -> CompressedTick {
+    CompressedTick::new(if tick < 0 && tick % tick_spacing != 0 {
         (tick / tick_spacing) - 1
     } else {
         tick / tick_spacing
-    }
+    })
 }
 
 #[cfg(test)]
@@ -223,6 +538,90 @@ mod test {
     use ruint::uint;
     use std::ops::Sub;
 
+    // get_sqrt_ratio_at_tick as it was before the u128 rewrite, a line by line port of TickMath
+    fn reference_sqrt_ratio_at_tick(tick: i32) -> Result<U256, UniswapV3MathError> {
+        let abs_tick = U256::from(tick.abs());
+
+        if abs_tick > U256::from(MAX_TICK) {
+            return Err(UniswapV3MathError::T);
+        }
+
+        let mut ratio = if abs_tick & (U256::from(0x1)) != U256::ZERO {
+            uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256)
+        } else {
+            uint!(0x100000000000000000000000000000000_U256)
+        };
+
+        if abs_tick & (U256::from(0x2)) != U256::ZERO {
+            ratio = (ratio * uint!(0xfff97272373d413259a46990580e213a_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x4)) != U256::ZERO {
+            ratio = (ratio * uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x8)) != U256::ZERO {
+            ratio = (ratio * uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x10)) != U256::ZERO {
+            ratio = (ratio * uint!(0xffcb9843d60f6159c9db58835c926644_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x20)) != U256::ZERO {
+            ratio = (ratio * uint!(0xff973b41fa98c081472e6896dfb254c0_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x40)) != U256::ZERO {
+            ratio = (ratio * uint!(0xff2ea16466c96a3843ec78b326b52861_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x80)) != U256::ZERO {
+            ratio = (ratio * uint!(0xfe5dee046a99a2a811c461f1969c3053_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x100)) != U256::ZERO {
+            ratio = (ratio * uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x200)) != U256::ZERO {
+            ratio = (ratio * uint!(0xf987a7253ac413176f2b074cf7815e54_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x400)) != U256::ZERO {
+            ratio = (ratio * uint!(0xf3392b0822b70005940c7a398e4b70f3_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x800)) != U256::ZERO {
+            ratio = (ratio * uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x1000)) != U256::ZERO {
+            ratio = (ratio * uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x2000)) != U256::ZERO {
+            ratio = (ratio * uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x4000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x8000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x31be135f97d08fd981231505542fcfa6_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x10000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x20000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x5d6af8dedb81196699c329225ee604_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x40000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x2216e584f5fa1ea926041bedfe98_U256)) >> 128
+        }
+        if abs_tick & (U256::from(0x80000)) != U256::ZERO {
+            ratio = (ratio * uint!(0x48a170391f7dc42444e8fa2_U256)) >> 128
+        }
+
+        if tick > 0 {
+            ratio = U256::MAX / ratio;
+        }
+
+        Ok((ratio >> 32)
+            + if (ratio % (RUINT_ONE << 32)) == U256::ZERO {
+                U256::ZERO
+            } else {
+                RUINT_ONE
+            })
+    }
+
     #[test]
     fn get_sqrt_ratio_at_tick_bounds() {
         // the function should return an error if the tick is out of bounds
@@ -236,6 +635,34 @@ mod test {
         } else {
             panic!("get_qrt_ratio_at_tick did not respect upper tick bound")
         }
+        //ticks whose absolute value overflows i32
+        assert!(matches!(
+            get_sqrt_ratio_at_tick(i32::MIN),
+            Err(UniswapV3MathError::T)
+        ));
+        assert!(matches!(
+            get_sqrt_ratio_at_tick(i32::MAX),
+            Err(UniswapV3MathError::T)
+        ));
+    }
+
+    #[test]
+    fn get_sqrt_ratio_at_tick_matches_reference() {
+        //every tick near zero and both ends, a stride over the whole range, and every combination
+        //of the high bits with the low ones all set
+        let ticks = (-20_000..=20_000)
+            .chain(MIN_TICK..MIN_TICK + 2_000)
+            .chain(MAX_TICK - 2_000..=MAX_TICK)
+            .chain((MIN_TICK..=MAX_TICK).step_by(13))
+            .chain((0..1 << 8).map(|high| (high << 12 | 0xfff).min(MAX_TICK)))
+            .flat_map(|tick| [tick, -tick]);
+        for tick in ticks {
+            assert_eq!(
+                get_sqrt_ratio_at_tick(tick).unwrap(),
+                reference_sqrt_ratio_at_tick(tick).unwrap(),
+                "tick {tick}"
+            );
+        }
     }
 
     #[test]
@@ -352,4 +779,593 @@ mod test {
         let result = get_tick_at_sqrt_ratio(uint!(4295343490_U256)).unwrap();
         assert_eq!(result, MIN_TICK + 1);
     }
+
+    #[test]
+    fn test_get_tick_at_sqrt_ratio_boundaries() {
+        //the ratio closest to the max tick
+        let result = get_tick_at_sqrt_ratio(MAX_SQRT_RATIO.sub(RUINT_ONE)).unwrap();
+        assert_eq!(result, MAX_TICK - 1);
+
+        //every 97th tick across the range, and every tick near both ends and around 0
+        let ticks = (MIN_TICK..MAX_TICK)
+            .step_by(97)
+            .chain(MIN_TICK..MIN_TICK + 300)
+            .chain(-300..300)
+            .chain(MAX_TICK - 300..MAX_TICK);
+        for tick in ticks {
+            let ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            let next_ratio = get_sqrt_ratio_at_tick(tick + 1).unwrap();
+
+            //a ratio maps to the highest tick whose ratio is at or below it
+            assert_eq!(get_tick_at_sqrt_ratio(ratio).unwrap(), tick, "at {tick}");
+            assert_eq!(
+                get_tick_at_sqrt_ratio(ratio + RUINT_ONE).unwrap(),
+                tick,
+                "above {tick}"
+            );
+            assert_eq!(
+                get_tick_at_sqrt_ratio(ratio + (next_ratio - ratio) / U256::from(2)).unwrap(),
+                tick,
+                "within {tick}"
+            );
+            if tick < MAX_TICK - 1 {
+                assert_eq!(
+                    get_tick_at_sqrt_ratio(next_ratio.sub(RUINT_ONE)).unwrap(),
+                    tick,
+                    "below {}",
+                    tick + 1
+                );
+            }
+            if tick > MIN_TICK {
+                assert_eq!(
+                    get_tick_at_sqrt_ratio(ratio.sub(RUINT_ONE)).unwrap(),
+                    tick - 1,
+                    "below {tick}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_log2_x64() {
+        //floor(log2(ratio / 2^128) * 2^64), computed independently at 120 digits
+        let cases = [
+            (U256::from(1) << 128usize, "0"),
+            (U256::from(3) << 127usize, "10790653543520307103"),
+            (U256::from(1) << 200usize, "1328165573307087716352"),
+            (U256::from(1), "-2361183241434822606848"),
+            (U256::MAX, "2361183241434822606847"),
+            (MIN_SQRT_RATIO << 32usize, "-1180590620382427594147"),
+            (
+                (MAX_SQRT_RATIO - RUINT_ONE) << 32usize,
+                "1180590620387809708934",
+            ),
+            (
+                uint!(0x1234567890abcdef1234567890abcdef_U256),
+                "-70351845590934268917",
+            ),
+        ];
+        for (ratio, expected) in cases {
+            assert_eq!(
+                log2_x64(ratio).unwrap(),
+                I256::from_dec_str(expected).unwrap(),
+                "{ratio:#x}"
+            );
+        }
+
+        assert!(matches!(
+            log2_x64(U256::ZERO),
+            Err(UniswapV3MathError::ZeroValue)
+        ));
+    }
+
+    #[test]
+    fn test_log_1_0001_x128() {
+        //log(price) / log(1.0001) * 2^128, computed independently at 120 digits and truncated
+        let cases = [
+            (uint!(79228162514264337593543950336_U256), "0"),
+            (
+                MIN_SQRT_RATIO,
+                "-301923016261298495921305158595855868367748899",
+            ),
+            (
+                MAX_SQRT_RATIO - RUINT_ONE,
+                "301923016262674912354273705065401629397407062",
+            ),
+            (
+                uint!(3598751819609688046946419_U256),
+                "-68056473384187692692674919760763388475588928",
+            ),
+            (
+                uint!(1744244129640337381386292603617837_U256),
+                "68056473384187692692674921486353640697063334",
+            ),
+            (
+                U256::from(1) << 110usize,
+                "66045715768796286385093943841850653670763997",
+            ),
+        ];
+        //within 2^-40 of a tick
+        let tolerance = I256::ONE << 88usize;
+        for (sqrt_price_x96, expected) in cases {
+            let expected = I256::from_dec_str(expected).unwrap();
+            let result = log_1_0001_x128(sqrt_price_x96).unwrap();
+            assert!((result - expected).abs() < tolerance, "{sqrt_price_x96}");
+        }
+
+        //the floor is the tick, halfway between two ticks' prices is about halfway between them
+        for tick in (MIN_TICK..MAX_TICK).step_by(9973) {
+            let lower = get_sqrt_ratio_at_tick(tick).unwrap();
+            let upper = get_sqrt_ratio_at_tick(tick + 1).unwrap();
+            let log = log_1_0001_x128((lower + upper) >> 1usize).unwrap();
+            assert_eq!(log.asr(128), I256::try_from(tick).unwrap());
+            let fraction = log - (I256::try_from(tick).unwrap() << 128usize);
+            assert!(
+                fraction > I256::ONE << 126usize
+                    && fraction < I256::try_from(3).unwrap() << 126usize,
+                "tick {tick}"
+            );
+        }
+
+        for sqrt_price_x96 in [U256::ZERO, MIN_SQRT_RATIO - RUINT_ONE, MAX_SQRT_RATIO] {
+            assert!(matches!(
+                log_1_0001_x128(sqrt_price_x96),
+                Err(UniswapV3MathError::R)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_fee_tiers() {
+        for (fee, tick_spacing) in [(100, 1), (500, 10), (3000, 60), (10000, 200)] {
+            assert_eq!(tick_spacing_for_fee(fee), Some(tick_spacing));
+            assert_eq!(fee_for_tick_spacing(tick_spacing), Some(fee));
+            assert!(is_canonical_fee(fee));
+        }
+
+        //forks' tiers and fees in other units
+        for fee in [0, 2500, 30, 1_000_000] {
+            assert_eq!(tick_spacing_for_fee(fee), None);
+            assert!(!is_canonical_fee(fee));
+        }
+        for tick_spacing in [0, 50, -60] {
+            assert_eq!(fee_for_tick_spacing(tick_spacing), None);
+        }
+    }
+
+    #[test]
+    fn test_usable_ticks() {
+        for (tick_spacing, max_usable) in [(1, 887272), (10, 887270), (60, 887220), (200, 887200)] {
+            assert_eq!(min_usable_tick(tick_spacing), -max_usable);
+            assert_eq!(max_usable_tick(tick_spacing), max_usable);
+            assert_eq!(max_usable % tick_spacing, 0);
+            assert!(max_usable + tick_spacing > MAX_TICK);
+        }
+    }
+
+    #[test]
+    fn test_tick_range() {
+        //unaligned bounds snap inwards on both sides of zero
+        let ticks = tick_range(-125, 125, 60).collect::<Vec<_>>();
+        assert_eq!(ticks, vec![-120, -60, 0, 60, 120]);
+        assert_eq!(tick_range(-125, 125, 60).next_back(), Some(120));
+        assert_eq!(tick_range(-61, -59, 60).collect::<Vec<_>>(), vec![-60]);
+        assert_eq!(tick_range(-59, -1, 60).next(), None);
+        assert_eq!(tick_range(1, 59, 60).next(), None);
+        assert_eq!(tick_range(-60, -60, 60).collect::<Vec<_>>(), vec![-60]);
+
+        //clamped to the usable ticks, without overflowing at the i32 bounds
+        let mut ticks = tick_range(i32::MIN, i32::MAX, 60);
+        assert_eq!(ticks.next(), Some(min_usable_tick(60)));
+        assert_eq!(ticks.next_back(), Some(max_usable_tick(60)));
+        assert_eq!(tick_range(MAX_TICK, i32::MAX, 60).next(), None);
+
+        //empty for reversed bounds and non positive spacings
+        assert_eq!(tick_range(60, -60, 60).next(), None);
+        assert_eq!(tick_range(-60, 60, 0).next(), None);
+        assert_eq!(tick_range(-60, 60, -60).next(), None);
+
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for i in 0..1000 {
+            let tick_spacing = match i % 5 {
+                0 => 1,
+                1 => 10,
+                2 => 60,
+                3 => 200,
+                _ => (next() % 16384) as i32 + 1,
+            };
+            //bounds anywhere in and slightly past the tick range, spans up to 4000 ticks and
+            //some reversed
+            let from = (next() % (2 * MAX_TICK as u64 + 2001)) as i32 - MAX_TICK - 1000;
+            let to = from + (next() % 4500) as i32 - 500;
+
+            let ticks = tick_range(from, to, tick_spacing).collect::<Vec<_>>();
+            for tick in &ticks {
+                assert_eq!(tick % tick_spacing, 0, "spacing {tick_spacing}");
+                assert!((from..=to).contains(tick));
+                assert!(
+                    (min_usable_tick(tick_spacing)..=max_usable_tick(tick_spacing)).contains(tick)
+                );
+            }
+            assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+
+            //no aligned usable tick is skipped, and reverse iteration is the same ticks backwards
+            let expected = (from..=to)
+                .filter(|tick| {
+                    tick % tick_spacing == 0
+                        && (min_usable_tick(tick_spacing)..=max_usable_tick(tick_spacing))
+                            .contains(tick)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                ticks, expected,
+                "from {from} to {to} spacing {tick_spacing}"
+            );
+            let mut reversed = tick_range(from, to, tick_spacing).rev().collect::<Vec<_>>();
+            reversed.reverse();
+            assert_eq!(reversed, ticks);
+        }
+    }
+
+    #[test]
+    fn test_validate_tick() {
+        assert!(validate_tick(-120, 60).is_ok());
+        assert!(validate_tick(0, 60).is_ok());
+        assert!(validate_tick(MIN_TICK, 1).is_ok());
+        assert!(validate_tick(max_usable_tick(60), 60).is_ok());
+
+        assert!(matches!(
+            validate_tick(MAX_TICK + 1, 1),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick == MAX_TICK + 1
+        ));
+        //bounds come before alignment, as in the pool
+        assert!(matches!(
+            validate_tick(MIN_TICK - 8, 60),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick == MIN_TICK - 8
+        ));
+        assert!(matches!(
+            validate_tick(-30, 60),
+            Err(UniswapV3MathError::TickNotAligned {
+                tick: -30,
+                spacing: 60
+            })
+        ));
+        assert!(matches!(
+            validate_tick(0, 0),
+            Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_validate_tick_range() {
+        assert!(validate_tick_range(-120, 60, 60).is_ok());
+        assert!(validate_tick_range(MIN_TICK, MAX_TICK, 1).is_ok());
+
+        for (tick_lower, tick_upper) in [(60, 60), (120, 60)] {
+            assert!(matches!(
+                validate_tick_range(tick_lower, tick_upper, 60),
+                Err(UniswapV3MathError::InvalidTickRange { tick_lower: lower, tick_upper: upper })
+                    if lower == tick_lower && upper == tick_upper
+            ));
+        }
+        //an unaligned lower tick is reported after an upper tick out of bounds, like checkTicks
+        //before flipTick
+        assert!(matches!(
+            validate_tick_range(-30, MAX_TICK + 1, 60),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick == MAX_TICK + 1
+        ));
+        assert!(matches!(
+            validate_tick_range(-30, 60, 60),
+            Err(UniswapV3MathError::TickNotAligned {
+                tick: -30,
+                spacing: 60
+            })
+        ));
+        assert!(matches!(
+            validate_tick_range(-60, 90, 60),
+            Err(UniswapV3MathError::TickNotAligned {
+                tick: 90,
+                spacing: 60
+            })
+        ));
+    }
+
+    #[test]
+    fn test_sqrt_ratio_cache() {
+        for tick_spacing in [10, 60, 200] {
+            let cache = SqrtRatioCache::new(tick_spacing).unwrap();
+            assert_eq!(cache.tick_spacing(), tick_spacing);
+
+            //aligned ticks across the range, both usable ends and the ticks just past them
+            let min_usable = min_usable_tick(tick_spacing);
+            let max_usable = max_usable_tick(tick_spacing);
+            let ticks = (min_usable..=max_usable)
+                .step_by(tick_spacing as usize * 997)
+                .chain([min_usable, max_usable, 0, MIN_TICK, MAX_TICK]);
+            for tick in ticks {
+                assert_eq!(
+                    cache.get(tick).unwrap(),
+                    get_sqrt_ratio_at_tick(tick).unwrap(),
+                    "spacing {tick_spacing} tick {tick}"
+                );
+                //unaligned neighbours fall back to the direct computation
+                for tick in [tick - 1, tick + 1] {
+                    assert_eq!(
+                        cache.get(tick).ok(),
+                        get_sqrt_ratio_at_tick(tick).ok(),
+                        "spacing {tick_spacing} tick {tick}"
+                    );
+                }
+            }
+
+            //out of range aligned ticks error like get_sqrt_ratio_at_tick, up to the i32 bounds
+            let ticks = [
+                max_usable + 2 * tick_spacing * 1000,
+                min_usable - 2 * tick_spacing * 1000,
+                i32::MAX - i32::MAX % tick_spacing,
+                i32::MIN - i32::MIN % tick_spacing,
+            ];
+            for tick in ticks {
+                assert_eq!(
+                    format!("{:?}", cache.get(tick).unwrap_err()),
+                    format!("{:?}", get_sqrt_ratio_at_tick(tick).unwrap_err()),
+                    "spacing {tick_spacing} tick {tick}"
+                );
+            }
+        }
+
+        assert!(matches!(
+            SqrtRatioCache::new(0),
+            Err(UniswapV3MathError::InvalidTickSpacing { tick_spacing: 0 })
+        ));
+        assert!(SqrtRatioCache::new(-60).is_err());
+    }
+
+    #[test]
+    fn test_tick_to_price() {
+        //USDC/WETH, USDC is token0 with 6 decimals
+        let price = tick_to_price(200000, 6, 18);
+        assert!((price / 4.846803050257336e-4 - 1.0).abs() < 1e-13);
+        //USDC/USDT around the peg, and DAI/USDC where the raw price is around 1e-12
+        assert!((tick_to_price(1, 6, 6) - 1.0001).abs() < 1e-15);
+        assert!((tick_to_price(-276324, 18, 6) - 1.0000026438309507).abs() < 1e-13);
+        //the extreme ticks, in raw price
+        assert!((tick_to_price(MAX_TICK, 18, 18) / 3.402567868363881e38 - 1.0).abs() < 1e-12);
+        assert!((tick_to_price(MIN_TICK, 18, 18) / 2.938956807585585e-39 - 1.0).abs() < 1e-12);
+
+        //every 7th tick over the full range round trips, with token and stablecoin decimals
+        for (decimals_0, decimals_1) in [(18, 18), (6, 18), (18, 6), (6, 6), (8, 18), (0, 24)] {
+            let ticks = (MIN_TICK..=MAX_TICK)
+                .step_by(7)
+                .chain(MIN_TICK..MIN_TICK + 100)
+                .chain(MAX_TICK - 100..=MAX_TICK);
+            for tick in ticks {
+                let price = tick_to_price(tick, decimals_0, decimals_1);
+                assert_eq!(
+                    price_to_tick(price, decimals_0, decimals_1).unwrap(),
+                    tick,
+                    "{tick} at decimals {decimals_0}/{decimals_1}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_price_to_tick() {
+        assert_eq!(price_to_tick(1.0, 18, 18).unwrap(), 0);
+        //nearest tick in log space, a price a third of the way to the next tick rounds down
+        assert_eq!(price_to_tick(1.0001f64.powf(10.3), 18, 18).unwrap(), 10);
+        assert_eq!(price_to_tick(1.0001f64.powf(-10.7), 18, 18).unwrap(), -11);
+        //1 USDC per DAI, DAI being token0 with 18 decimals
+        assert_eq!(price_to_tick(1.0, 18, 6).unwrap(), -276324);
+
+        for price in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                price_to_tick(price, 18, 18),
+                Err(UniswapV3MathError::InvalidPrice { .. })
+            ));
+        }
+        assert!(matches!(
+            price_to_tick(1e39, 18, 18),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick > MAX_TICK
+        ));
+        assert!(matches!(
+            price_to_tick(1e-40, 18, 18),
+            Err(UniswapV3MathError::TickOutOfRange { tick }) if tick < MIN_TICK
+        ));
+    }
+
+    #[test]
+    fn test_sqrt_price_x96_to_decimal() {
+        let q96 = RUINT_ONE << 96usize;
+        assert_eq!(sqrt_price_x96_to_decimal(q96, 18, 18, 0), "1");
+        assert_eq!(sqrt_price_x96_to_decimal(q96, 18, 18, 4), "1.0000");
+        assert_eq!(sqrt_price_x96_to_decimal(q96, 6, 18, 12), "0.000000000001");
+        assert_eq!(sqrt_price_x96_to_decimal(q96, 6, 18, 11), "0.00000000000");
+        assert_eq!(sqrt_price_x96_to_decimal(q96, 18, 6, 2), "1000000000000.00");
+        assert_eq!(sqrt_price_x96_to_decimal(U256::ZERO, 6, 18, 3), "0.000");
+
+        //USDC/WETH, USDC is token0 with 6 decimals, rounded half up at each precision
+        let sqrt_price_x96 = uint!(1744244129640337381386292603617838_U256);
+        let full = "0.0004846803050257335883327160063504904004321876310447949473473814921024849473249488\
+                    083809824164690706206841300722941960360400040086916487018400204395403538349209639213\
+                    288625114728347398340702056884765625";
+        assert_eq!(
+            sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, full.len() - 2),
+            full
+        );
+        assert_eq!(
+            sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, full.len() + 1),
+            format!("{full}000")
+        );
+        assert_eq!(
+            sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, 10),
+            "0.0004846803"
+        );
+        assert_eq!(
+            sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, 12),
+            "0.000484680305"
+        );
+        assert_eq!(sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, 3), "0.000");
+        assert_eq!(
+            sqrt_price_x96_to_decimal(sqrt_price_x96, 6, 18, 4),
+            "0.0005"
+        );
+        //carries through the integer part
+        assert_eq!(
+            sqrt_price_x96_to_decimal(MAX_SQRT_RATIO - RUINT_ONE, 18, 18, 0),
+            "340256786836388094070642339899681172762"
+        );
+        assert_eq!(
+            sqrt_price_x96_to_decimal(MAX_SQRT_RATIO - RUINT_ONE, 0, 18, 0),
+            "340256786836388094071"
+        );
+    }
+
+    #[test]
+    fn test_decimal_to_sqrt_price_x96() {
+        let q96 = RUINT_ONE << 96usize;
+        assert_eq!(decimal_to_sqrt_price_x96("1", 18, 18).unwrap(), q96);
+        assert_eq!(decimal_to_sqrt_price_x96("001.000", 18, 18).unwrap(), q96);
+        assert_eq!(
+            decimal_to_sqrt_price_x96("0.000000000001", 6, 18).unwrap(),
+            q96
+        );
+        assert_eq!(
+            decimal_to_sqrt_price_x96("1000000000000", 18, 6).unwrap(),
+            q96
+        );
+        //2.25 has the exact root 1.5
+        assert_eq!(
+            decimal_to_sqrt_price_x96("2.25", 18, 18).unwrap(),
+            q96 + (q96 >> 1usize)
+        );
+
+        for price in ["", ".5", "1.", "-1", "1e5", "1.2.3", " 1", "0x10"] {
+            assert!(
+                matches!(
+                    decimal_to_sqrt_price_x96(price, 18, 18),
+                    Err(UniswapV3MathError::InvalidDecimalPrice { .. })
+                ),
+                "{price:?}"
+            );
+        }
+        for price in ["0", "0.0000000000000000000000000000000000000001", "1e40"] {
+            let price = price.replace("1e40", &format!("1{}", "0".repeat(40)));
+            assert!(matches!(
+                decimal_to_sqrt_price_x96(&price, 18, 18),
+                Err(UniswapV3MathError::SqrtPriceOutOfRange { .. })
+            ));
+        }
+        assert!(matches!(
+            decimal_to_sqrt_price_x96("1", 0, 255),
+            Err(UniswapV3MathError::SqrtPriceOutOfRange { .. })
+        ));
+        assert!(matches!(
+            decimal_to_sqrt_price_x96("1", 255, 0),
+            Err(UniswapV3MathError::SqrtPriceOutOfRange { .. })
+        ));
+
+        //pseudo-random sqrt prices over the whole range, just above a tick's ratio, round trip
+        //exactly through the full expansion. Away from the tick's bounds, 30 significant digits
+        //are enough to get back to the same tick.
+        let mut seed = 0x9e3779b97f4a7c15_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for (decimals_0, decimals_1) in [(18_u8, 18_u8), (6, 18), (18, 6), (0, 24)] {
+            for _ in 0..200 {
+                let tick = (next() % (2 * MAX_TICK as u64)) as i32 - MAX_TICK;
+                let sqrt_price_x96 =
+                    get_sqrt_ratio_at_tick(tick).unwrap() + U256::from(next() % 1_000_000);
+                let tick = get_tick_at_sqrt_ratio(sqrt_price_x96).unwrap();
+
+                let exact_precision = 192 + (decimals_1 - decimals_0.min(decimals_1)) as usize;
+                let price = sqrt_price_x96_to_decimal(
+                    sqrt_price_x96,
+                    decimals_0,
+                    decimals_1,
+                    exact_precision,
+                );
+                let parsed = decimal_to_sqrt_price_x96(&price, decimals_0, decimals_1).unwrap();
+                assert_eq!(parsed, sqrt_price_x96, "{price}");
+
+                let (lower, upper) = (
+                    get_sqrt_ratio_at_tick(tick).unwrap(),
+                    get_sqrt_ratio_at_tick(tick + 1).unwrap(),
+                );
+                let sqrt_price_x96 = lower + (upper - lower) / U256::from(4 + next() % 2);
+                let exponent = tick_to_price(tick, decimals_0, decimals_1).log10();
+                let precision = (30 - exponent.floor() as i64).max(0) as usize;
+                let price =
+                    sqrt_price_x96_to_decimal(sqrt_price_x96, decimals_0, decimals_1, precision);
+                let parsed = decimal_to_sqrt_price_x96(&price, decimals_0, decimals_1).unwrap();
+                assert_eq!(get_tick_at_sqrt_ratio(parsed).unwrap(), tick, "{price}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_ticks_at_sqrt_ratios() {
+        //empty input
+        assert!(get_ticks_at_sqrt_ratios(&[]).unwrap().is_empty());
+
+        //throws for unsorted input
+        let result = get_ticks_at_sqrt_ratios(&[MIN_SQRT_RATIO + RUINT_ONE, MIN_SQRT_RATIO]);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Sqrt ratios must be sorted in ascending order"
+        );
+
+        //throws for out of range prices, even after a valid prefix
+        let result = get_ticks_at_sqrt_ratios(&[MIN_SQRT_RATIO, MAX_SQRT_RATIO]);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::R));
+
+        //tight clusters and duplicates around tick boundaries
+        let mut prices = vec![MIN_SQRT_RATIO, MIN_SQRT_RATIO];
+        for tick in [-200_000, -60, -1, 0, 1, 2, 3, 60, 61, 200_000, MAX_TICK - 1] {
+            let ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            prices.extend([ratio.sub(RUINT_ONE), ratio, ratio, ratio + RUINT_ONE]);
+        }
+        prices.push(MAX_SQRT_RATIO.sub(RUINT_ONE));
+        assert_batch_matches_single(&prices);
+
+        //sorted pseudo-random prices, both spread out and packed within a few ticks
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for spread in [MAX_TICK as u64, 10, 1] {
+            let base = (next() % (MAX_TICK as u64)) as i32 - MAX_TICK / 2;
+            let mut prices = vec![];
+            for _ in 0..200 {
+                let tick = base + (next() % spread) as i32;
+                let ratio = get_sqrt_ratio_at_tick(tick.clamp(MIN_TICK, MAX_TICK - 1)).unwrap();
+                prices.push(ratio + U256::from(next() % 1_000_000));
+            }
+            prices.sort();
+            assert_batch_matches_single(&prices);
+        }
+    }
+
+    fn assert_batch_matches_single(prices: &[U256]) {
+        let ticks = get_ticks_at_sqrt_ratios(prices).unwrap();
+        assert_eq!(ticks.len(), prices.len());
+        for (price, tick) in prices.iter().zip(ticks) {
+            assert_eq!(tick, get_tick_at_sqrt_ratio(*price).unwrap(), "at {price}");
+        }
+    }
 }
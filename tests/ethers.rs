@@ -0,0 +1,99 @@
+#![cfg(feature = "ethers")]
+// Reads tick data through EthersTicksProvider from an Anvil mainnet fork and compares it with the
+// pool's own tickBitmap and ticks getters. Needs a fork in ANVIL_URL and is skipped without one.
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, TransactionRequest},
+};
+use reth_primitives::U256;
+use uniswap_v3_math::{async_provider::AsyncTicksProvider, providers::EthersTicksProvider};
+
+// (pool, tick spacing). USDC/WETH 0.05% trades at positive ticks, DAI/USDC 0.01% at negative ones.
+const POOLS: [(&str, i32); 2] = [
+    ("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640", 10),
+    ("0x5777d92f208679DB4b9778590Fa3CAB3aC9e2168", 1),
+];
+
+// slot0(), tickBitmap(int16) and ticks(int24)
+const SLOT0: [u8; 4] = [0x38, 0x50, 0xc7, 0xbd];
+const TICK_BITMAP: [u8; 4] = [0x53, 0x39, 0xc2, 0x96];
+const TICKS: [u8; 4] = [0xf3, 0x0d, 0xba, 0x93];
+
+async fn call(
+    provider: &Provider<Http>,
+    pool: Address,
+    block_id: BlockId,
+    selector: [u8; 4],
+    arg: Option<i32>,
+) -> Vec<u8> {
+    let mut calldata = selector.to_vec();
+    if let Some(arg) = arg {
+        // abi.encode sign extends int16 and int24 arguments to 32 bytes
+        let mut word = [if arg < 0 { 0xff } else { 0 }; 32];
+        word[28..].copy_from_slice(&arg.to_be_bytes());
+        calldata.extend_from_slice(&word);
+    }
+    let tx: TypedTransaction = TransactionRequest::new()
+        .to(pool)
+        .data(Bytes::from(calldata))
+        .into();
+    provider.call(&tx, Some(block_id)).await.unwrap().to_vec()
+}
+
+// Sign extends the int256 abi word at index
+fn int_at(data: &[u8], index: usize) -> i128 {
+    i128::from_be_bytes(data[index * 32 + 16..(index + 1) * 32].try_into().unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_ethers_provider_matches_getters() {
+    let Ok(anvil_url) = std::env::var("ANVIL_URL") else {
+        eprintln!("ANVIL_URL is not set, skipping");
+        return;
+    };
+
+    let provider = Provider::<Http>::try_from(anvil_url).unwrap();
+    let block_id = BlockId::from(provider.get_block_number().await.unwrap());
+
+    for (pool, tick_spacing) in POOLS {
+        let pool: Address = pool.parse().unwrap();
+        let ticks = EthersTicksProvider::new(provider.clone(), pool, block_id);
+        // The sync path, straight from this task
+        let sync_ticks = EthersTicksProvider::new(provider.clone(), pool, block_id)
+            .with_handle(tokio::runtime::Handle::current());
+
+        let slot0 = call(&provider, pool, block_id, SLOT0, None).await;
+        let tick = int_at(&slot0, 1) as i32;
+        let compressed = tick.div_euclid(tick_spacing);
+
+        // The word holding the current tick and its neighbours, with the liquidity_net of every
+        // initialized tick in them
+        for position in [-1, 0, 1].map(|offset| (compressed >> 8) as i16 + offset) {
+            let expected = call(
+                &provider,
+                pool,
+                block_id,
+                TICK_BITMAP,
+                Some(position.into()),
+            )
+            .await;
+            let word = ticks.get_word_at_position(position).await.unwrap();
+            assert_eq!(word, U256::from_be_slice(&expected));
+            assert_eq!(
+                uniswap_v3_math::TicksProvider::get_word_at_position(&sync_ticks, position)
+                    .unwrap(),
+                word
+            );
+
+            for bit in (0..256).filter(|bit| word.bit(*bit)) {
+                let tick = ((position as i32) * 256 + bit as i32) * tick_spacing;
+                let expected = call(&provider, pool, block_id, TICKS, Some(tick)).await;
+                assert_eq!(
+                    ticks.get_liquidity_net_at_tick(tick).await.unwrap(),
+                    int_at(&expected, 1)
+                );
+            }
+        }
+    }
+}
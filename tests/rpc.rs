@@ -0,0 +1,83 @@
+#![cfg(feature = "rpc")]
+// Quotes a real swap through RpcTicksProvider and compares it with the Quoter at the same block.
+// Needs an archive node in RPC_URL and is skipped without one.
+
+use alloy_primitives::{address, Address, Bytes};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::{BlockId, TransactionRequest};
+use reth_primitives::U256;
+use uniswap_v3_math::{providers::RpcTicksProvider, storage, Math};
+
+// USDC/WETH 0.05%, USDC is token0
+const POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const QUOTER: Address = address!("b27308f9F90D607463bb33eA1BeBb41C27CE5AB6");
+const FEE: u32 = 500;
+const TICK_SPACING: i32 = 10;
+const BLOCK: u64 = 19_000_000;
+
+// quoteExactInputSingle(address,address,uint24,uint256,uint160)
+const QUOTE_EXACT_INPUT_SINGLE: [u8; 4] = [0xf7, 0x72, 0x9d, 0x43];
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_quote_matches_quoter() {
+    let Ok(rpc_url) = std::env::var("RPC_URL") else {
+        eprintln!("RPC_URL is not set, skipping");
+        return;
+    };
+
+    let provider = ProviderBuilder::new().connect_http(rpc_url.parse().unwrap());
+    let block_id = BlockId::number(BLOCK);
+
+    let slot0 = provider
+        .get_storage_at(POOL, storage::slot0_slot())
+        .block_id(block_id)
+        .await
+        .unwrap();
+    let liquidity = provider
+        .get_storage_at(POOL, storage::liquidity_slot())
+        .block_id(block_id)
+        .await
+        .unwrap()
+        .to::<u128>();
+
+    let math = |provider| Math::from_slot0(slot0, liquidity, FEE, TICK_SPACING, provider).unwrap();
+
+    // 100 WETH for USDC, one for zero
+    let amount_in = U256::from(100_000_000_000_000_000_000u128);
+
+    let async_math = math(RpcTicksProvider::new(provider.clone(), POOL, block_id));
+    let amount_out = async_math
+        .simulate_swap_async(false, amount_in)
+        .await
+        .unwrap();
+
+    let mut calldata = QUOTE_EXACT_INPUT_SINGLE.to_vec();
+    calldata.extend_from_slice(WETH.into_word().as_slice());
+    calldata.extend_from_slice(USDC.into_word().as_slice());
+    calldata.extend_from_slice(&U256::from(FEE).to_be_bytes::<32>());
+    calldata.extend_from_slice(&amount_in.to_be_bytes::<32>());
+    calldata.extend_from_slice(&[0u8; 32]);
+    let tx = TransactionRequest::default()
+        .to(QUOTER)
+        .input(Bytes::from(calldata).into());
+    let quoted = provider.call(tx).block(block_id).await.unwrap();
+    assert_eq!(amount_out, U256::from_be_slice(&quoted[..32]));
+
+    // The sync TicksProvider path blocks on the runtime, straight from this task or from a
+    // blocking thread
+    let sync_math = math(
+        RpcTicksProvider::new(provider, POOL, block_id)
+            .with_handle(tokio::runtime::Handle::current()),
+    );
+    assert_eq!(
+        sync_math.simulate_swap(false, amount_in).unwrap(),
+        amount_out
+    );
+    let sync_amount_out =
+        tokio::task::spawn_blocking(move || sync_math.simulate_swap(false, amount_in).unwrap())
+            .await
+            .unwrap();
+    assert_eq!(sync_amount_out, amount_out);
+}
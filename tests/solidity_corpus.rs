@@ -0,0 +1,449 @@
+//! Table-driven cases imported from the Uniswap v3-core test suites (SwapMath.spec.ts,
+//! SqrtPriceMath.spec.ts, FullMath.spec.ts, TickMath.spec.ts and TickBitmap.spec.ts), with the
+//! upstream expected values pinned. Each case id is `<spec file>/<describe>/<it>`.
+//!
+//! Coverage gaps found while auditing the unit tests, all filled here:
+//! - SwapMath: every computeSwapStep case (the port in swap_math.rs is commented out)
+//! - FullMath: all mulDivRoundingUp cases, including the two "overflows 256 bits after rounding
+//!   up" echidna findings and the phantom overflow cases
+//! - SqrtPriceMath: getNextSqrtPriceFromInput with a zero amount and zeroForOne = false (the unit
+//!   test repeats the zeroForOne = true case), and getAmount0Delta/getAmount1Delta with equal
+//!   prices and nonzero liquidity
+//! - TickMath: getTickAtSqrtRatio for the ratio of max tick - 1, the ratio closest to max tick and
+//!   the 1:1 price
+//! - TickBitmap: every nextInitializedTickWithinOneWord case
+//!
+//! Divergences found while importing, fixed alongside:
+//! - TickBitmap: `initialized` was computed as `!masked == 0`, i.e. only true for an all-ones
+//!   word, so initialized ticks were never reported.
+
+use std::collections::HashMap;
+
+use alloy_primitives::I256;
+use reth_primitives::U256;
+use ruint::uint;
+use uniswap_v3_math::{
+    error::UniswapV3MathError,
+    full_math::mul_div_rounding_up,
+    sqrt_price_math::{
+        _get_amount_0_delta, _get_amount_1_delta, get_next_sqrt_price_from_input,
+        get_next_sqrt_price_from_output,
+    },
+    swap_math::compute_swap_step,
+    tick::{CompressedTick, Tick},
+    tick_bitmap::{next_initialized_tick_within_one_word_from_provider, position},
+    tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK},
+    TicksProvider,
+};
+
+const Q128: U256 = U256::from_limbs([0, 0, 1, 0]);
+// encodePriceSqrt(1, 1)
+const PRICE_1_1: U256 = U256::from_limbs([0, 4294967296, 0, 0]);
+
+struct SwapStepCase {
+    id: &'static str,
+    price: U256,
+    price_target: U256,
+    liquidity: u128,
+    amount_remaining: &'static str,
+    fee_pips: u32,
+    // (sqrt_q, amount_in, amount_out, fee_amount); None when sqrt_q is only bounded, not pinned
+    expected: (Option<U256>, U256, U256, U256),
+}
+
+#[test]
+fn swap_math_compute_swap_step() {
+    let price_20282 = uint!(20282409603651670423947251286016_U256);
+
+    let cases = [
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/exact amount in that gets capped at price target in one for zero",
+            price: PRICE_1_1,
+            price_target: uint!(79623317895830914510639640423_U256),
+            liquidity: 2e18 as u128,
+            amount_remaining: "1000000000000000000",
+            fee_pips: 600,
+            expected: (
+                Some(uint!(79623317895830914510639640423_U256)),
+                uint!(9975124224178055_U256),
+                uint!(9925619580021728_U256),
+                uint!(5988667735148_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/exact amount out that gets capped at price target in one for zero",
+            price: PRICE_1_1,
+            price_target: uint!(79623317895830914510639640423_U256),
+            liquidity: 2e18 as u128,
+            amount_remaining: "-1000000000000000000",
+            fee_pips: 600,
+            expected: (
+                Some(uint!(79623317895830914510639640423_U256)),
+                uint!(9975124224178055_U256),
+                uint!(9925619580021728_U256),
+                uint!(5988667735148_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/exact amount in that is fully spent in one for zero",
+            price: PRICE_1_1,
+            price_target: uint!(250541448375047931186413801569_U256),
+            liquidity: 2e18 as u128,
+            amount_remaining: "1000000000000000000",
+            fee_pips: 600,
+            expected: (
+                None,
+                uint!(999400000000000000_U256),
+                uint!(666399946655997866_U256),
+                uint!(600000000000000_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/exact amount out that is fully received in one for zero",
+            price: PRICE_1_1,
+            price_target: uint!(792281625142643375935439503360_U256),
+            liquidity: 2e18 as u128,
+            amount_remaining: "-1000000000000000000",
+            fee_pips: 600,
+            expected: (
+                None,
+                uint!(2000000000000000000_U256),
+                uint!(1000000000000000000_U256),
+                uint!(1200720432259356_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/amount out is capped at the desired amount out",
+            price: uint!(417332158212080721273783715441582_U256),
+            price_target: uint!(1452870262520218020823638996_U256),
+            liquidity: 159344665391607089467575320103,
+            amount_remaining: "-1",
+            fee_pips: 1,
+            expected: (
+                Some(uint!(417332158212080721273783715441581_U256)),
+                uint!(1_U256),
+                uint!(1_U256),
+                uint!(1_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/target price of 1 uses partial input amount",
+            price: uint!(2_U256),
+            price_target: uint!(1_U256),
+            liquidity: 1,
+            amount_remaining: "3915081100057732413702495386755767",
+            fee_pips: 1,
+            expected: (
+                Some(uint!(1_U256)),
+                uint!(39614081257132168796771975168_U256),
+                U256::ZERO,
+                uint!(39614120871253040049813_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/entire input amount taken as fee",
+            price: uint!(2413_U256),
+            price_target: uint!(79887613182836312_U256),
+            liquidity: 1985041575832132834610021537970,
+            amount_remaining: "10",
+            fee_pips: 1872,
+            expected: (
+                Some(uint!(2413_U256)),
+                U256::ZERO,
+                U256::ZERO,
+                uint!(10_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/handles intermediate insufficient liquidity in zero for one exact output case",
+            price: price_20282,
+            price_target: price_20282 * U256::from(11) / U256::from(10),
+            liquidity: 1024,
+            amount_remaining: "-4",
+            fee_pips: 3000,
+            expected: (
+                Some(price_20282 * U256::from(11) / U256::from(10)),
+                uint!(26215_U256),
+                U256::ZERO,
+                uint!(79_U256),
+            ),
+        },
+        SwapStepCase {
+            id: "SwapMath.spec/computeSwapStep/handles intermediate insufficient liquidity in one for zero exact output case",
+            price: price_20282,
+            price_target: price_20282 * U256::from(9) / U256::from(10),
+            liquidity: 1024,
+            amount_remaining: "-263000",
+            fee_pips: 3000,
+            expected: (
+                Some(price_20282 * U256::from(9) / U256::from(10)),
+                uint!(1_U256),
+                uint!(26214_U256),
+                uint!(1_U256),
+            ),
+        },
+    ];
+
+    for case in cases {
+        let amount_remaining = I256::from_dec_str(case.amount_remaining).unwrap();
+        let (sqrt_q, amount_in, amount_out, fee_amount) = compute_swap_step(
+            case.price,
+            case.price_target,
+            case.liquidity,
+            amount_remaining,
+            case.fee_pips,
+        )
+        .unwrap_or_else(|err| panic!("{}: {err}", case.id));
+
+        let (expected_sqrt_q, expected_in, expected_out, expected_fee) = case.expected;
+        if let Some(expected_sqrt_q) = expected_sqrt_q {
+            assert_eq!(sqrt_q, expected_sqrt_q, "{}", case.id);
+        } else {
+            // the target is never reached in the partially filled cases
+            assert!(sqrt_q < case.price_target, "{}", case.id);
+        }
+        assert_eq!(amount_in, expected_in, "{}", case.id);
+        assert_eq!(amount_out, expected_out, "{}", case.id);
+        assert_eq!(fee_amount, expected_fee, "{}", case.id);
+    }
+}
+
+#[test]
+fn swap_math_fully_spent_and_fully_received_end_prices() {
+    // SwapMath.spec/computeSwapStep/exact amount in that is fully spent in one for zero
+    let (sqrt_q, _, _, fee_amount) = compute_swap_step(
+        PRICE_1_1,
+        uint!(250541448375047931186413801569_U256),
+        2e18 as u128,
+        I256::from_dec_str("1000000000000000000").unwrap(),
+        600,
+    )
+    .unwrap();
+    let price_after_whole_input_amount_less_fee = get_next_sqrt_price_from_input(
+        PRICE_1_1,
+        2e18 as u128,
+        uint!(1000000000000000000_U256) - fee_amount,
+        false,
+    )
+    .unwrap();
+    assert_eq!(sqrt_q, price_after_whole_input_amount_less_fee);
+
+    // SwapMath.spec/computeSwapStep/exact amount out that is fully received in one for zero
+    let (sqrt_q, _, _, _) = compute_swap_step(
+        PRICE_1_1,
+        uint!(792281625142643375935439503360_U256),
+        2e18 as u128,
+        I256::from_dec_str("-1000000000000000000").unwrap(),
+        600,
+    )
+    .unwrap();
+    let price_after_whole_output_amount = get_next_sqrt_price_from_output(
+        PRICE_1_1,
+        2e18 as u128,
+        uint!(1000000000000000000_U256),
+        false,
+    )
+    .unwrap();
+    assert_eq!(sqrt_q, price_after_whole_output_amount);
+}
+
+#[test]
+fn full_math_mul_div_rounding_up() {
+    let reverts: [(&str, U256, U256, U256); 6] = [
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts if denominator is 0",
+            Q128,
+            U256::from(5),
+            U256::ZERO,
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts if denominator is 0 and numerator overflows",
+            Q128,
+            Q128,
+            U256::ZERO,
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts if output overflows uint256",
+            Q128,
+            Q128,
+            uint!(1_U256),
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts on overflow with all max inputs",
+            U256::MAX,
+            U256::MAX,
+            U256::MAX - uint!(1_U256),
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts if mulDiv overflows 256 bits after rounding up",
+            uint!(535006138814359_U256),
+            uint!(432862656469423142931042426214547535783388063929571229938474969_U256),
+            uint!(2_U256),
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/reverts if mulDiv overflows 256 bits after rounding up case 2",
+            uint!(115792089237316195423570985008687907853269984659341747863450311749907997002549_U256),
+            uint!(115792089237316195423570985008687907853269984659341747863450311749907997002550_U256),
+            uint!(115792089237316195423570985008687907853269984653042931687443039491902864365164_U256),
+        ),
+    ];
+    for (id, a, b, denominator) in reverts {
+        assert!(mul_div_rounding_up(a, b, denominator).is_err(), "{id}");
+    }
+
+    let values: [(&str, U256, U256, U256, U256); 4] = [
+        (
+            "FullMath.spec/mulDivRoundingUp/all max inputs",
+            U256::MAX,
+            U256::MAX,
+            U256::MAX,
+            U256::MAX,
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/accurate without phantom overflow",
+            Q128,
+            U256::from(50) * Q128 / U256::from(100),
+            U256::from(150) * Q128 / U256::from(100),
+            Q128 / U256::from(3) + uint!(1_U256),
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/accurate with phantom overflow",
+            Q128,
+            U256::from(35) * Q128,
+            U256::from(8) * Q128,
+            U256::from(4375) * Q128 / U256::from(1000),
+        ),
+        (
+            "FullMath.spec/mulDivRoundingUp/accurate with phantom overflow and repeating decimal",
+            Q128,
+            U256::from(1000) * Q128,
+            U256::from(3000) * Q128,
+            Q128 / U256::from(3) + uint!(1_U256),
+        ),
+    ];
+    for (id, a, b, denominator, expected) in values {
+        assert_eq!(
+            mul_div_rounding_up(a, b, denominator).unwrap(),
+            expected,
+            "{id}"
+        );
+    }
+}
+
+#[test]
+fn sqrt_price_math_gaps() {
+    // SqrtPriceMath.spec/getNextSqrtPriceFromInput/returns input price if amount in is zero and zeroForOne = false
+    let result = get_next_sqrt_price_from_input(PRICE_1_1, 1e17 as u128, U256::ZERO, false);
+    assert_eq!(result.unwrap(), PRICE_1_1);
+
+    // SqrtPriceMath.spec/getAmount0Delta/returns 0 if prices are equal
+    let amount_0 = _get_amount_0_delta(PRICE_1_1, PRICE_1_1, 1e18 as u128, true);
+    assert_eq!(amount_0.unwrap(), U256::ZERO);
+
+    // SqrtPriceMath.spec/getAmount1Delta/returns 0 if prices are equal
+    let amount_1 = _get_amount_1_delta(PRICE_1_1, PRICE_1_1, 1e18 as u128, true);
+    assert_eq!(amount_1.unwrap(), U256::ZERO);
+}
+
+#[test]
+fn tick_math_gaps() {
+    let cases: [(&str, U256, i32); 3] = [
+        (
+            "TickMath.spec/getTickAtSqrtRatio/ratio of max tick - 1",
+            uint!(1461373636630004318706518188784493106690254656249_U256),
+            MAX_TICK - 1,
+        ),
+        (
+            "TickMath.spec/getTickAtSqrtRatio/ratio closest to max tick",
+            MAX_SQRT_RATIO - uint!(1_U256),
+            MAX_TICK - 1,
+        ),
+        ("TickMath.spec/getTickAtSqrtRatio/1:1 price", PRICE_1_1, 0),
+    ];
+    for (id, ratio, tick) in cases {
+        assert_eq!(get_tick_at_sqrt_ratio(ratio).unwrap(), tick, "{id}");
+    }
+
+    // TickMath.spec/getSqrtRatioAtTick/tick 0 is exactly 2**96
+    assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), PRICE_1_1);
+}
+
+// Bitmap with spacing 1 built from a list of initialized ticks, as TickBitmap.spec's
+// initTicks helper does
+#[derive(Clone, Default)]
+struct Bitmap {
+    words: HashMap<i16, U256>,
+}
+
+impl Bitmap {
+    fn new(ticks: &[i32]) -> Self {
+        let mut bitmap = Self::default();
+        for &tick in ticks {
+            let (word_pos, bit_pos) = position(CompressedTick::new(tick));
+            *bitmap.words.entry(word_pos).or_default() |= uint!(1_U256) << bit_pos as usize;
+        }
+        bitmap
+    }
+}
+
+impl TicksProvider for Bitmap {
+    fn get_word_at_position(&self, position: i16) -> Result<U256, UniswapV3MathError> {
+        Ok(self.words.get(&position).copied().unwrap_or_default())
+    }
+
+    fn get_liquidity_net_at_tick(&self, _tick: i32) -> Result<i128, UniswapV3MathError> {
+        Ok(0)
+    }
+}
+
+// (id, extra initialized ticks, tick, lte, expected next, expected initialized)
+type BitmapCase = (&'static str, &'static [i32], i32, bool, i32, bool);
+
+#[test]
+fn tick_bitmap_next_initialized_tick_within_one_word() {
+    const TICKS: [i32; 9] = [-200, -55, -4, 70, 78, 84, 139, 240, 535];
+
+    let cases: [BitmapCase; 18] = [
+        ("TickBitmap.spec/lte = false/returns tick to right if at initialized tick", &[], 78, false, 84, true),
+        ("TickBitmap.spec/lte = false/returns tick to right if at initialized tick (negative)", &[], -55, false, -4, true),
+        ("TickBitmap.spec/lte = false/returns the tick directly to the right", &[], 77, false, 78, true),
+        ("TickBitmap.spec/lte = false/returns the tick directly to the right (negative)", &[], -56, false, -55, true),
+        ("TickBitmap.spec/lte = false/returns the next words initialized tick if on the right boundary", &[], 255, false, 511, false),
+        ("TickBitmap.spec/lte = false/returns the next words initialized tick if on the right boundary (negative)", &[], -257, false, -200, true),
+        ("TickBitmap.spec/lte = false/returns the next initialized tick from the next word", &[340], 328, false, 340, true),
+        ("TickBitmap.spec/lte = false/does not exceed boundary", &[], 508, false, 511, false),
+        ("TickBitmap.spec/lte = false/skips entire word", &[], 255, false, 511, false),
+        ("TickBitmap.spec/lte = false/skips half word", &[], 383, false, 511, false),
+        ("TickBitmap.spec/lte = true/returns same tick if initialized", &[], 78, true, 78, true),
+        ("TickBitmap.spec/lte = true/returns tick directly to the left of input tick if not initialized", &[], 79, true, 78, true),
+        ("TickBitmap.spec/lte = true/will not exceed the word boundary", &[], 258, true, 256, false),
+        ("TickBitmap.spec/lte = true/at the word boundary", &[], 256, true, 256, false),
+        ("TickBitmap.spec/lte = true/word boundary less 1 (next initialized tick in next word)", &[], 72, true, 70, true),
+        ("TickBitmap.spec/lte = true/word boundary", &[], -257, true, -512, false),
+        ("TickBitmap.spec/lte = true/entire empty word", &[], 1023, true, 768, false),
+        ("TickBitmap.spec/lte = true/boundary is initialized", &[329], 456, true, 329, true),
+    ];
+
+    for (id, extra, tick, lte, expected_next, expected_initialized) in cases {
+        let bitmap = Bitmap::new(&[&TICKS[..], extra].concat());
+        let (next, initialized) = next_initialized_tick_within_one_word_from_provider(
+            Tick::new(tick).unwrap(),
+            1,
+            lte,
+            bitmap,
+        )
+        .unwrap();
+        assert_eq!(next, expected_next, "{id}");
+        assert_eq!(initialized, expected_initialized, "{id}");
+    }
+
+    // TickBitmap.spec/lte = true/halfway through empty word
+    let (next, initialized) = next_initialized_tick_within_one_word_from_provider(
+        Tick::new(900).unwrap(),
+        1,
+        true,
+        Bitmap::new(&TICKS),
+    )
+    .unwrap();
+    assert_eq!((next, initialized), (768, false));
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "serde")]
+// Quotes through tick data in the subgraph's JSON shape. The fixture is laid out like the USDC/WETH
+// 0.3% pool, fee 3000 and tick spacing 60 around tick 200000 with a full range position, and the
+// expected amounts come from an independent big integer model of the pool.
+
+use reth_primitives::U256;
+use ruint::uint;
+use uniswap_v3_math::{providers::SubgraphTicks, Math};
+
+const TICKS: &str = include_str!("fixtures/subgraph_ticks_usdc_weth_3000.json");
+
+#[test]
+fn test_subgraph_quote() {
+    let ticks = SubgraphTicks::from_json(TICKS, 60).unwrap();
+    let math = Math::builder()
+        .fee(3000)
+        .liquidity(1653563533642153397)
+        .sqrt_price_x96(uint!(1755003744971735478821014702974090_U256))
+        .tick_spacing(60)
+        .provider(ticks)
+        .build()
+        .unwrap();
+    assert_eq!(math.tick, 200123);
+
+    for (zero_for_one, amount_in, amount_out) in [
+        //1M USDC crosses one tick, 500 WETH two
+        (true, 1_000_000_000_000_u128, 483405592524715123198_u128),
+        (false, 500_000_000_000_000_000_000, 1002407565750),
+        //1B USDC empties every range down to the full range position
+        (true, 1_000_000_000_000_000, 4671121977858934181599),
+    ] {
+        assert_eq!(
+            math.simulate_swap(zero_for_one, U256::from(amount_in))
+                .unwrap(),
+            U256::from(amount_out)
+        );
+    }
+}
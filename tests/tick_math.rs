@@ -0,0 +1,60 @@
+// Checks get_sqrt_ratio_at_tick against reference values for every tick in -1000..=1000 and the
+// spot ticks of TickMath.spec.ts, and get_tick_at_sqrt_ratio against their inverse. Each bit of
+// the absolute tick selects one factor of the ratio, so a dense range exercises every factor up to
+// 0x200 in every combination.
+
+use std::str::FromStr;
+
+use reth_primitives::U256;
+use uniswap_v3_math::tick_math::{
+    get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_TICK, MIN_TICK,
+};
+
+const REFERENCE: &str = include_str!("fixtures/sqrt_ratio_at_tick.txt");
+
+fn reference() -> Vec<(i32, U256)> {
+    REFERENCE
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let (tick, sqrt_price_x96) = line.split_once(' ').unwrap();
+            (
+                tick.parse().unwrap(),
+                U256::from_str(sqrt_price_x96).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_get_sqrt_ratio_at_tick_reference() {
+    let reference = reference();
+    for tick in (-1000..=1000).chain([MIN_TICK, MIN_TICK + 1, MAX_TICK - 1, MAX_TICK]) {
+        assert!(reference.iter().any(|&(t, _)| t == tick), "tick {tick}");
+    }
+
+    for (tick, expected) in reference {
+        assert_eq!(
+            get_sqrt_ratio_at_tick(tick).unwrap(),
+            expected,
+            "tick {tick}"
+        );
+    }
+}
+
+#[test]
+fn test_get_tick_at_sqrt_ratio_reference() {
+    for (tick, sqrt_price_x96) in reference() {
+        //MAX_TICK's ratio is MAX_SQRT_RATIO, outside get_tick_at_sqrt_ratio's domain
+        if tick == MAX_TICK {
+            continue;
+        }
+        assert_eq!(get_tick_at_sqrt_ratio(sqrt_price_x96).unwrap(), tick);
+        if tick > MIN_TICK {
+            assert_eq!(
+                get_tick_at_sqrt_ratio(sqrt_price_x96 - U256::from(1)).unwrap(),
+                tick - 1
+            );
+        }
+    }
+}